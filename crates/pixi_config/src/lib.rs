@@ -15,7 +15,7 @@ use rattler_conda_types::{
     version_spec::{EqualityOperator, LogicalOperator, RangeOperator},
     ChannelConfig, NamedChannelOrUrl, Version, VersionBumpType, VersionSpec,
 };
-use rattler_repodata_gateway::{Gateway, SourceConfig};
+use rattler_repodata_gateway::{fetch::CacheAction, Gateway, SourceConfig};
 use reqwest_middleware::ClientWithMiddleware;
 use serde::{de::IntoDeserializer, Deserialize, Serialize};
 use url::Url;
@@ -103,6 +103,98 @@ pub fn get_cache_dir() -> miette::Result<PathBuf> {
                 .map_err(|_| miette::miette!("could not determine default cache directory"))
         })
 }
+
+/// Returns whether the `PIXI_OFFLINE` environment variable enables or disables
+/// offline mode, or `None` if it isn't set.
+pub fn offline_from_env() -> Option<bool> {
+    let value = std::env::var("PIXI_OFFLINE").ok()?;
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "" | "0" | "false" | "no" | "off" => Some(false),
+        _ => {
+            tracing::warn!("Ignoring invalid value '{value}' for PIXI_OFFLINE");
+            None
+        }
+    }
+}
+
+/// The prefix of the environment variables that override configuration keys.
+pub const CONFIG_ENV_PREFIX: &str = "PIXI_CONFIG_";
+
+/// Returns the name of the environment variable that overrides the given
+/// configuration key, e.g. `PIXI_CONFIG_PYPI_CONFIG__INDEX_URL` for
+/// `pypi-config.index-url`.
+pub fn config_env_var(key: &str) -> String {
+    format!(
+        "{CONFIG_ENV_PREFIX}{}",
+        key.to_uppercase().replace('-', "_").replace('.', "__")
+    )
+}
+
+/// Creates a config layer for every `PIXI_CONFIG_<KEY>` variable in `vars`,
+/// sorted by the name of the variable. Empty variables are ignored.
+fn env_layers(
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Vec<miette::Result<(ConfigOrigin, Config)>> {
+    let defaults = Config::default();
+    let keys: HashMap<String, &str> = defaults
+        .get_keys()
+        .iter()
+        .map(|key| (config_env_var(key), *key))
+        .collect();
+
+    vars.into_iter()
+        .filter(|(name, value)| name.starts_with(CONFIG_ENV_PREFIX) && !value.is_empty())
+        .sorted()
+        .map(|(name, value)| {
+            let Some(key) = keys.get(&name) else {
+                return Err(miette!(
+                    help = format!(
+                        "Supported variables:\n\t{}",
+                        keys.keys().sorted().join(",\n\t")
+                    ),
+                    "the environment variable `{name}` doesn't match a configuration key"
+                ));
+            };
+            let mut config = Config::empty_layer();
+            config
+                .set(key, Some(value))
+                .and_then(|_| config.validate())
+                .wrap_err_with(|| {
+                    format!("invalid value for `{key}` in the environment variable `{name}`")
+                })?;
+            Ok((ConfigOrigin::Environment(name), config))
+        })
+        .collect()
+}
+
+/// Where a configuration layer was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// The system wide config file.
+    System(PathBuf),
+    /// One of the global config files.
+    Global(PathBuf),
+    /// The config file of the project.
+    Project(PathBuf),
+    /// The command line arguments.
+    Cli,
+    /// An environment variable.
+    Environment(String),
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigOrigin::System(path) => write!(f, "system: {}", path.display()),
+            ConfigOrigin::Global(path) => write!(f, "global: {}", path.display()),
+            ConfigOrigin::Project(path) => write!(f, "project: {}", path.display()),
+            ConfigOrigin::Cli => write!(f, "command line"),
+            ConfigOrigin::Environment(name) => write!(f, "environment: {name}"),
+        }
+    }
+}
+
 #[derive(Parser, Debug, Default, Clone)]
 pub struct ConfigCli {
     /// Do not verify the TLS certificate of the server.
@@ -188,7 +280,9 @@ pub struct ConfigCliActivation {
 impl ConfigCliActivation {
     pub fn merge_config(self, config: Config) -> Config {
         let mut config = config;
-        config.force_activate = Some(self.force_activate);
+        if self.force_activate {
+            config.force_activate = Some(true);
+        }
         config
     }
 }
@@ -196,7 +290,7 @@ impl ConfigCliActivation {
 impl From<ConfigCliActivation> for Config {
     fn from(cli: ConfigCliActivation) -> Self {
         Self {
-            force_activate: Some(cli.force_activate),
+            force_activate: cli.force_activate.then_some(true),
             ..Default::default()
         }
     }
@@ -274,6 +368,73 @@ pub struct PyPIConfig {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub keyring_provider: Option<KeyringProvider>,
+    /// Where to get the conda to PyPI name mapping from, for the channels
+    /// that are not in the `conda-pypi-map` of the project.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conda_pypi_map: Option<CondaPypiMapSource>,
+}
+
+/// The source of the conda to PyPI name mapping.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CondaPypiMapSource {
+    /// The mapping hosted by prefix.dev.
+    Prefix,
+    /// Derive the mapping from the `.dist-info` metadata of the conda
+    /// packages, without network access to a mapping.
+    PackageContents,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProxyConfig {
+    /// The proxy to use for `https` requests.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub https: Option<Url>,
+    /// The proxy to use for `http` requests.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<Url>,
+    /// Hosts that are accessed without the proxy, e.g. `localhost` or
+    /// `.example.com`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub non_proxy_hosts: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// Merge the given ProxyConfig into the current one.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            https: other.https.or(self.https),
+            http: other.http.or(self.http),
+            non_proxy_hosts: self
+                .non_proxy_hosts
+                .into_iter()
+                .chain(other.non_proxy_hosts)
+                .unique()
+                .collect(),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.https.is_none() && self.http.is_none() && self.non_proxy_hosts.is_empty()
+    }
+}
+
+/// A client certificate that is used for mutual TLS with a host.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ClientCertificate {
+    /// Path to the PEM encoded certificate (chain).
+    pub cert: PathBuf,
+    /// Path to the PEM encoded private key, can be omitted if the key is part
+    /// of the certificate file.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -299,6 +460,17 @@ impl DetachedEnvironments {
             _ => Ok(None),
         }
     }
+
+    /// Get the path to the store of shared environments, which is in the
+    /// detached-environments directory if one is set and in the cache
+    /// directory otherwise.
+    pub fn store_path(&self) -> miette::Result<PathBuf> {
+        let root = match self.path()? {
+            Some(path) => path,
+            None => get_cache_dir()?,
+        };
+        Ok(root.join(consts::ENVIRONMENT_STORE_DIR))
+    }
 }
 impl Default for DetachedEnvironments {
     fn default() -> Self {
@@ -346,6 +518,7 @@ impl PyPIConfig {
             index_url: other.index_url.or(self.index_url),
             extra_index_urls,
             keyring_provider: other.keyring_provider.or(self.keyring_provider),
+            conda_pypi_map: other.conda_pypi_map.or(self.conda_pypi_map),
         }
     }
 
@@ -366,6 +539,7 @@ impl PyPIConfig {
         self.index_url.is_none()
             && self.extra_index_urls.is_empty()
             && self.keyring_provider.is_none()
+            && self.conda_pypi_map.is_none()
     }
 }
 
@@ -509,6 +683,23 @@ pub struct Config {
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub mirrors: HashMap<Url, Vec<Url>>,
 
+    /// The proxy configuration, by default the `HTTPS_PROXY`, `HTTP_PROXY`
+    /// and `NO_PROXY` environment variables are used.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "ProxyConfig::is_default")]
+    pub proxy: ProxyConfig,
+
+    /// Path to a PEM bundle with extra CA certificates that are trusted in
+    /// addition to the system certificates.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_certificates: Option<PathBuf>,
+
+    /// Client certificates used for mutual TLS, keyed by host name.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub client_certificate: HashMap<String, ClientCertificate>,
+
     /// Dependency Pinning strategy used for dependency modification through
     /// automated logic like `pixi add`
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -540,11 +731,30 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detached_environments: Option<DetachedEnvironments>,
 
+    /// When set to true, environments are installed once in a store that is
+    /// shared between projects and keyed by the hash of the locked
+    /// environment, the environments of the projects link to it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_environments: Option<bool>,
+
     /// The option to disable the environment activation cache
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub force_activate: Option<bool>,
 
+    /// When set to true, pixi doesn't access the network and only uses the
+    /// cached repodata and packages.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
+
+    /// Glob patterns of environment variables that are passed through to
+    /// tasks that run in a clean environment, e.g. `AWS_*`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env_passthrough: Vec<String>,
+
     /// Experimental features that can be enabled.
     #[serde(default)]
     #[serde(skip_serializing_if = "ExperimentalConfig::is_default")]
@@ -559,13 +769,19 @@ impl Default for Config {
             authentication_override_file: None,
             tls_no_verify: None,
             mirrors: HashMap::new(),
+            proxy: ProxyConfig::default(),
+            ca_certificates: None,
+            client_certificate: HashMap::new(),
             loaded_from: Vec::new(),
             channel_config: default_channel_config(),
             repodata_config: RepodataConfig::default(),
             pypi_config: PyPIConfig::default(),
             detached_environments: Some(DetachedEnvironments::default()),
+            shared_environments: None,
             pinning_strategy: Default::default(),
             force_activate: None,
+            offline: None,
+            env_passthrough: Vec::new(),
             experimental: Default::default(),
         }
     }
@@ -608,10 +824,21 @@ impl From<&Config> for rattler_repodata_gateway::ChannelConfig {
             })
             .collect();
 
-        rattler_repodata_gateway::ChannelConfig {
+        let mut channel_config = rattler_repodata_gateway::ChannelConfig {
             default,
             per_channel,
+        };
+
+        // Only use the cached repodata when offline, even if it is outdated
+        if config.offline() {
+            for source_config in std::iter::once(&mut channel_config.default)
+                .chain(channel_config.per_channel.values_mut())
+            {
+                source_config.cache_action = CacheAction::ForceCacheOnly;
+            }
         }
+
+        channel_config
     }
 }
 
@@ -735,14 +962,83 @@ impl Config {
     ///
     /// The loaded global config
     pub fn load_global() -> Config {
-        let mut config = Self::load_system();
+        Self::load_layered(None)
+    }
+
+    /// Load the global config and layer the given cli config on top of it.
+    pub fn with_cli_config(cli: &ConfigCli) -> Config {
+        let config = Config::load_global();
+        config.merge_config(cli.clone().into())
+    }
+
+    /// Load the config from the given path (project root).
+    ///
+    /// # Returns
+    ///
+    /// The loaded config (merged with the global config)
+    pub fn load(project_root: &Path) -> Config {
+        Self::load_layered(Some(project_root))
+    }
+
+    /// Merges all configuration layers, invalid `PIXI_CONFIG_<KEY>`
+    /// environment variables are ignored with a warning.
+    fn load_layered(project_root: Option<&Path>) -> Config {
+        let (layers, errors) = Self::collect_layers(project_root);
+        for error in errors {
+            tracing::warn!("Ignoring environment variable: {:?}", error);
+        }
+        Self::merge_layers(layers)
+    }
+
+    /// Loads all configuration layers in the order in which they are merged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error that names the variable when one of the
+    /// `PIXI_CONFIG_<KEY>` environment variables is invalid.
+    pub fn try_load_layers(
+        project_root: Option<&Path>,
+    ) -> miette::Result<Vec<(ConfigOrigin, Config)>> {
+        let (layers, errors) = Self::collect_layers(project_root);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(layers),
+        }
+    }
+
+    /// Merges the given layers, later layers take precedence.
+    pub fn merge_layers(layers: impl IntoIterator<Item = (ConfigOrigin, Config)>) -> Config {
+        layers
+            .into_iter()
+            .fold(Config::default(), |config, (_, layer)| {
+                config.merge_config(layer)
+            })
+    }
+
+    /// Collects the system and global config files, the command line, the
+    /// project config file and the environment variables in the order of
+    /// their precedence, together with the errors for the invalid
+    /// environment variables.
+    fn collect_layers(
+        project_root: Option<&Path>,
+    ) -> (Vec<(ConfigOrigin, Config)>, Vec<miette::Report>) {
+        let mut layers = Vec::new();
+
+        match Self::try_load_system() {
+            Ok(c) => layers.push((ConfigOrigin::System(config_path_system()), c)),
+            Err(e) => tracing::debug!(
+                "Failed to load system config: {} (error: {})",
+                config_path_system().display(),
+                e
+            ),
+        }
 
         for p in config_path_global() {
             if !p.is_file() {
                 continue;
             }
             match Self::from_path(&p) {
-                Ok(c) => config = config.merge_config(c),
+                Ok(c) => layers.push((ConfigOrigin::Global(p), c)),
                 Err(e) => tracing::warn!(
                     "Failed to load global config '{}' with error: {}",
                     p.display(),
@@ -751,41 +1047,55 @@ impl Config {
             }
         }
 
-        // Load the default CLI config and layer it on top of the global config
+        // Load the default CLI config and layer it on top of the global config files
         // This will add any environment variables defined in the `clap` attributes to
-        // the config
+        // the config, the project config still takes precedence over it
         let mut default_cli = ConfigCli::default();
         default_cli.update_from(std::env::args().take(0));
-        config.merge_config(default_cli.into())
-    }
-
-    /// Load the global config and layer the given cli config on top of it.
-    pub fn with_cli_config(cli: &ConfigCli) -> Config {
-        let config = Config::load_global();
-        config.merge_config(cli.clone().into())
-    }
+        layers.push((ConfigOrigin::Cli, default_cli.into()));
+
+        if let Some(project_root) = project_root {
+            let local_config_path = project_root
+                .join(consts::PIXI_DIR)
+                .join(consts::CONFIG_FILE);
+
+            match Self::from_path(&local_config_path) {
+                Ok(c) => layers.push((ConfigOrigin::Project(local_config_path), c)),
+                Err(e) => tracing::debug!(
+                    "Failed to load local config: {} (error: {})",
+                    local_config_path.display(),
+                    e
+                ),
+            }
+        }
 
-    /// Load the config from the given path (project root).
-    ///
-    /// # Returns
-    ///
-    /// The loaded config (merged with the global config)
-    pub fn load(project_root: &Path) -> Config {
-        let mut config = Self::load_global();
-        let local_config_path = project_root
-            .join(consts::PIXI_DIR)
-            .join(consts::CONFIG_FILE);
+        let mut errors = Vec::new();
+        for layer in env_layers(std::env::vars()) {
+            match layer {
+                Ok(layer) => layers.push(layer),
+                Err(e) => errors.push(e),
+            }
+        }
 
-        match Self::from_path(&local_config_path) {
-            Ok(c) => config = config.merge_config(c),
-            Err(e) => tracing::debug!(
-                "Failed to load local config: {} (error: {})",
-                local_config_path.display(),
-                e
-            ),
+        // The global `--offline` flag is passed through the environment
+        if let Some(offline) = offline_from_env() {
+            let layer = Config {
+                offline: Some(offline),
+                ..Config::empty_layer()
+            };
+            layers.push((ConfigOrigin::Environment("PIXI_OFFLINE".to_string()), layer));
         }
 
-        config
+        (layers, errors)
+    }
+
+    /// A config without any values, that doesn't override anything when it is
+    /// merged.
+    fn empty_layer() -> Config {
+        Config {
+            detached_environments: None,
+            ..Default::default()
+        }
     }
 
     // Get all possible keys of the configuration
@@ -796,8 +1106,17 @@ impl Config {
             "authentication-override-file",
             "tls-no-verify",
             "mirrors",
+            "proxy",
+            "proxy.https",
+            "proxy.http",
+            "proxy.non-proxy-hosts",
+            "ca-certificates",
+            "client-certificate",
             "detached-environments",
+            "shared-environments",
             "pinning-strategy",
+            "offline",
+            "env-passthrough",
             "repodata-config",
             "repodata-config.disable-jlap",
             "repodata-config.disable-bzip2",
@@ -807,6 +1126,7 @@ impl Config {
             "pypi-config.index-url",
             "pypi-config.extra-index-urls",
             "pypi-config.keyring-provider",
+            "pypi-config.conda-pypi-map",
             "experimental.use-environment-activation-cache",
         ]
     }
@@ -815,6 +1135,7 @@ impl Config {
     #[must_use]
     pub fn merge_config(mut self, other: Config) -> Self {
         self.mirrors.extend(other.mirrors);
+        self.client_certificate.extend(other.client_certificate);
         self.loaded_from.extend(other.loaded_from);
 
         Self {
@@ -829,14 +1150,25 @@ impl Config {
                 .authentication_override_file
                 .or(self.authentication_override_file),
             mirrors: self.mirrors,
+            proxy: self.proxy.merge(other.proxy),
+            ca_certificates: other.ca_certificates.or(self.ca_certificates),
+            client_certificate: self.client_certificate,
             loaded_from: self.loaded_from,
             // currently this is always the default so just use the other value
             channel_config: other.channel_config,
             repodata_config: other.repodata_config.merge(self.repodata_config),
             pypi_config: other.pypi_config.merge(self.pypi_config),
             detached_environments: other.detached_environments.or(self.detached_environments),
+            shared_environments: other.shared_environments.or(self.shared_environments),
             pinning_strategy: other.pinning_strategy.or(self.pinning_strategy),
-            force_activate: other.force_activate,
+            force_activate: other.force_activate.or(self.force_activate),
+            offline: other.offline.or(self.offline),
+            env_passthrough: self
+                .env_passthrough
+                .into_iter()
+                .chain(other.env_passthrough)
+                .unique()
+                .collect(),
             experimental: other.experimental.merge(self.experimental),
         }
     }
@@ -890,15 +1222,59 @@ impl Config {
         &self.mirrors
     }
 
+    pub fn proxy_config(&self) -> &ProxyConfig {
+        &self.proxy
+    }
+
+    /// Retrieve the path to the bundle with extra CA certificates.
+    pub fn ca_certificates(&self) -> Option<&PathBuf> {
+        self.ca_certificates.as_ref()
+    }
+
+    /// Retrieve the client certificates keyed by host name.
+    pub fn client_certificates(&self) -> &HashMap<String, ClientCertificate> {
+        &self.client_certificate
+    }
+
     /// Retrieve the value for the target_environments_directory field.
     pub fn detached_environments(&self) -> DetachedEnvironments {
         self.detached_environments.clone().unwrap_or_default()
     }
 
+    /// Retrieve the value for the shared_environments field (defaults to
+    /// false).
+    pub fn shared_environments(&self) -> bool {
+        self.shared_environments.unwrap_or(false)
+    }
+
     pub fn force_activate(&self) -> bool {
         self.force_activate.unwrap_or(false)
     }
 
+    /// Retrieve the value for the offline field (defaults to false).
+    pub fn offline(&self) -> bool {
+        self.offline.unwrap_or(false)
+    }
+
+    /// Retrieve where the conda to PyPI name mapping comes from for the
+    /// channels without a mapping in the project (defaults to the contents of
+    /// the packages when offline and prefix.dev otherwise).
+    pub fn conda_pypi_map_source(&self) -> CondaPypiMapSource {
+        self.pypi_config
+            .conda_pypi_map
+            .unwrap_or(if self.offline() {
+                CondaPypiMapSource::PackageContents
+            } else {
+                CondaPypiMapSource::Prefix
+            })
+    }
+
+    /// Retrieve the glob patterns of the environment variables that are passed
+    /// through to clean environments.
+    pub fn env_passthrough(&self) -> &[String] {
+        &self.env_passthrough
+    }
+
     pub fn experimental_activation_cache_usage(&self) -> bool {
         self.experimental.use_environment_activation_cache()
     }
@@ -941,6 +1317,52 @@ impl Config {
                     .into_diagnostic()?
                     .unwrap_or_default();
             }
+            "ca-certificates" => {
+                self.ca_certificates = value.map(PathBuf::from);
+            }
+            "client-certificate" => {
+                self.client_certificate = value
+                    .map(|v| serde_json::de::from_str(&v))
+                    .transpose()
+                    .into_diagnostic()?
+                    .unwrap_or_default();
+            }
+            key if key.starts_with("proxy") => {
+                if key == "proxy" {
+                    self.proxy = value
+                        .map(|v| serde_json::de::from_str(&v))
+                        .transpose()
+                        .into_diagnostic()?
+                        .unwrap_or_default();
+                    return Ok(());
+                } else if !key.starts_with("proxy.") {
+                    return Err(err);
+                }
+
+                let subkey = key.strip_prefix("proxy.").unwrap();
+                match subkey {
+                    "https" => {
+                        self.proxy.https = value
+                            .map(|v| Url::parse(&v))
+                            .transpose()
+                            .into_diagnostic()?;
+                    }
+                    "http" => {
+                        self.proxy.http = value
+                            .map(|v| Url::parse(&v))
+                            .transpose()
+                            .into_diagnostic()?;
+                    }
+                    "non-proxy-hosts" => {
+                        self.proxy.non_proxy_hosts = value
+                            .map(|v| serde_json::de::from_str(&v))
+                            .transpose()
+                            .into_diagnostic()?
+                            .unwrap_or_default();
+                    }
+                    _ => return Err(err),
+                }
+            }
             "detached-environments" => {
                 self.detached_environments = value.map(|v| match v.as_str() {
                     "true" => DetachedEnvironments::Boolean(true),
@@ -948,6 +1370,20 @@ impl Config {
                     _ => DetachedEnvironments::Path(PathBuf::from(v)),
                 });
             }
+            "shared-environments" => {
+                self.shared_environments =
+                    value.map(|v| v.parse()).transpose().into_diagnostic()?;
+            }
+            "offline" => {
+                self.offline = value.map(|v| v.parse()).transpose().into_diagnostic()?;
+            }
+            "env-passthrough" => {
+                self.env_passthrough = value
+                    .map(|v| serde_json::de::from_str(&v))
+                    .transpose()
+                    .into_diagnostic()?
+                    .unwrap_or_default();
+            }
             "pinning-strategy" => {
                 self.pinning_strategy = value
                     .map(|v| PinningStrategy::from_str(v.as_str()))
@@ -1023,6 +1459,15 @@ impl Config {
                             })
                             .transpose()?;
                     }
+                    "conda-pypi-map" => {
+                        self.pypi_config.conda_pypi_map = value
+                            .map(|v| match v.as_str() {
+                                "prefix" => Ok(CondaPypiMapSource::Prefix),
+                                "package-contents" => Ok(CondaPypiMapSource::PackageContents),
+                                _ => Err(miette::miette!("invalid conda-pypi-map source")),
+                            })
+                            .transpose()?;
+                    }
                     _ => return Err(err),
                 }
             }
@@ -1433,6 +1878,20 @@ UNUSED = "unused"
             Some(KeyringProvider::Subprocess)
         );
 
+        config
+            .set(
+                "pypi-config.conda-pypi-map",
+                Some("package-contents".to_string()),
+            )
+            .unwrap();
+        assert_eq!(
+            config.pypi_config().conda_pypi_map,
+            Some(CondaPypiMapSource::PackageContents)
+        );
+        config
+            .set("pypi-config.conda-pypi-map", Some("unknown".to_string()))
+            .unwrap_err();
+
         config.set("change-ps1", None).unwrap();
         assert_eq!(config.change_ps1, None);
 
@@ -1562,4 +2021,175 @@ UNUSED = "unused"
         assert_eq!(anaconda_config.disable_zstd, Some(false));
         assert_eq!(anaconda_config.disable_sharded, None);
     }
+
+    #[test]
+    fn test_proxy_and_certificates_config() {
+        let toml = r#"
+            ca-certificates = "/etc/ssl/corporate.pem"
+
+            [proxy]
+            https = "http://proxy.example.com:8080"
+            non-proxy-hosts = ["localhost", ".internal.example.com"]
+
+            [client-certificate."conda.internal.example.com"]
+            cert = "/path/to/client.pem"
+            key = "/path/to/client.key"
+        "#;
+        let (config, _) = Config::from_toml(toml).unwrap();
+        assert_eq!(
+            config.ca_certificates(),
+            Some(&PathBuf::from("/etc/ssl/corporate.pem"))
+        );
+        assert_eq!(
+            config.proxy_config().https,
+            Some(Url::parse("http://proxy.example.com:8080").unwrap())
+        );
+        assert_eq!(config.proxy_config().http, None);
+        let certificate = &config.client_certificates()["conda.internal.example.com"];
+        assert_eq!(certificate.cert, PathBuf::from("/path/to/client.pem"));
+        assert_eq!(certificate.key, Some(PathBuf::from("/path/to/client.key")));
+
+        let other = Config {
+            proxy: ProxyConfig {
+                http: Some(Url::parse("http://other-proxy.example.com").unwrap()),
+                non_proxy_hosts: vec!["localhost".to_string(), "127.0.0.1".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let config = config.merge_config(other);
+        assert_eq!(
+            config.proxy_config().https,
+            Some(Url::parse("http://proxy.example.com:8080").unwrap())
+        );
+        assert_eq!(
+            config.proxy_config().http,
+            Some(Url::parse("http://other-proxy.example.com").unwrap())
+        );
+        assert_eq!(
+            config.proxy_config().non_proxy_hosts,
+            vec!["localhost", ".internal.example.com", "127.0.0.1"]
+        );
+    }
+
+    #[test]
+    fn test_env_layers() {
+        assert_eq!(
+            config_env_var("pypi-config.index-url"),
+            "PIXI_CONFIG_PYPI_CONFIG__INDEX_URL"
+        );
+
+        let vars = [
+            ("PIXI_CONFIG_TLS_NO_VERIFY", "true"),
+            (
+                "PIXI_CONFIG_PYPI_CONFIG__INDEX_URL",
+                "https://pypi.example.com/simple",
+            ),
+            ("PIXI_CONFIG_CHANGE_PS1", ""),
+            ("PIXI_HOME", "/not/a/config/key"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let layers = env_layers(vars)
+            .into_iter()
+            .collect::<miette::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            layers
+                .iter()
+                .map(|(origin, _)| origin.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "environment: PIXI_CONFIG_PYPI_CONFIG__INDEX_URL",
+                "environment: PIXI_CONFIG_TLS_NO_VERIFY"
+            ]
+        );
+
+        let config = Config {
+            tls_no_verify: Some(false),
+            change_ps1: Some(false),
+            ..Default::default()
+        };
+        let config =
+            Config::merge_layers(std::iter::once((ConfigOrigin::Cli, config)).chain(layers));
+        assert!(config.tls_no_verify());
+        assert!(!config.change_ps1());
+        assert_eq!(
+            config.pypi_config().index_url,
+            Some(Url::parse("https://pypi.example.com/simple").unwrap())
+        );
+
+        let errors = env_layers([
+            ("PIXI_CONFIG_TLS_NO_VERIFY".to_string(), "maybe".to_string()),
+            ("PIXI_CONFIG_UNKNOWN".to_string(), "1".to_string()),
+        ])
+        .into_iter()
+        .map(|layer| layer.unwrap_err().to_string())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "invalid value for `tls-no-verify` in the environment variable `PIXI_CONFIG_TLS_NO_VERIFY`",
+                "the environment variable `PIXI_CONFIG_UNKNOWN` doesn't match a configuration key",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_all_layers() {
+        let (system, _) = Config::from_toml("tls-no-verify = true\nchange-ps1 = false").unwrap();
+        let (global, _) = Config::from_toml("shared-environments = true").unwrap();
+        let (project, _) =
+            Config::from_toml("force-activate = true\nchange-ps1 = true\noffline = false").unwrap();
+        let env = env_layers([("PIXI_CONFIG_OFFLINE".to_string(), "true".to_string())])
+            .into_iter()
+            .collect::<miette::Result<Vec<_>>>()
+            .unwrap();
+
+        // A command line without any flags doesn't override the config files
+        let cli = ConfigCli::default();
+        let activation = ConfigCliActivation::default();
+        let config = Config::merge_layers(
+            [
+                (ConfigOrigin::System(PathBuf::from("system.toml")), system),
+                (ConfigOrigin::Global(PathBuf::from("global.toml")), global),
+                (ConfigOrigin::Cli, cli.into()),
+                (
+                    ConfigOrigin::Project(PathBuf::from("project.toml")),
+                    project,
+                ),
+                (ConfigOrigin::Cli, activation.into()),
+            ]
+            .into_iter()
+            .chain(env),
+        );
+        assert!(config.tls_no_verify());
+        assert!(config.change_ps1());
+        assert!(config.shared_environments());
+        assert!(config.force_activate());
+        assert!(config.offline());
+
+        let config = ConfigCliActivation::default().merge_config(config);
+        assert!(config.force_activate());
+    }
+
+    #[test]
+    fn test_offline_channel_config() {
+        let toml = r#"
+            offline = true
+
+            [repodata-config."https://prefix.dev/conda-forge"]
+            disable-jlap = true
+        "#;
+        let (config, _) = Config::from_toml(toml).unwrap();
+        assert!(config.offline());
+
+        let channel_config = rattler_repodata_gateway::ChannelConfig::from(&config);
+        assert_eq!(
+            channel_config.default.cache_action,
+            CacheAction::ForceCacheOnly
+        );
+        for source_config in channel_config.per_channel.values() {
+            assert_eq!(source_config.cache_action, CacheAction::ForceCacheOnly);
+        }
+    }
 }
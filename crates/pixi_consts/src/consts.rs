@@ -31,6 +31,10 @@ pub const CONDA_META_DIR: &str = "conda-meta";
 pub const PYPI_CACHE_DIR: &str = "uv-cache";
 pub const CONDA_PYPI_MAPPING_CACHE_DIR: &str = "conda-pypi-mapping";
 pub const CACHED_ENVS_DIR: &str = "cached-envs-v0";
+pub const ENVIRONMENT_STORE_DIR: &str = "env-store-v0";
+/// The file in the cache directory that lists the lock-files of the projects
+/// that use the cache.
+pub const CACHE_REGISTRY_FILE: &str = "registered-lock-files-v0.json";
 
 pub const CONDA_INSTALLER: &str = "conda";
 
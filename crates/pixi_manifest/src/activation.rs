@@ -1,10 +1,203 @@
+use std::path::PathBuf;
+
 use indexmap::IndexMap;
 use serde::Deserialize;
+use thiserror::Error;
+
+use crate::utils::PixiSpanned;
 
 #[derive(Default, Clone, Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Activation {
     pub scripts: Option<Vec<String>>,
     /// Environment variables to set before running the scripts.
     pub env: Option<IndexMap<String, String>>,
+    /// Dotenv files to read environment variables from, relative to the
+    /// project root. Variables from `env` take precedence over the files.
+    pub env_file: Option<Vec<PixiSpanned<PathBuf>>>,
+}
+
+/// An error that occurred while parsing a dotenv file.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum EnvFileError {
+    #[error("line {0}: expected `KEY=VALUE`")]
+    MissingEquals(usize),
+    #[error("line {0}: '{1}' is not a valid environment variable name")]
+    InvalidName(usize, String),
+    #[error("line {0}: the quoted value is never closed")]
+    UnterminatedQuote(usize),
+}
+
+/// Parses the contents of a dotenv file and adds the variables to `variables`.
+///
+/// Values can be unquoted, single quoted or double quoted, quoted values can
+/// span multiple lines. References to other variables (`$VAR`, `${VAR}` or
+/// `${VAR:-default}`) in unquoted and double quoted values are replaced with
+/// the value from `variables` or else from the environment of the current
+/// process. Single quoted values are used literally.
+pub fn parse_env_file(
+    contents: &str,
+    variables: &mut IndexMap<String, String>,
+) -> Result<(), EnvFileError> {
+    let mut lines = contents.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line
+            .strip_prefix("export ")
+            .map(str::trim_start)
+            .unwrap_or(line);
+
+        let (name, value) = line
+            .split_once('=')
+            .ok_or(EnvFileError::MissingEquals(line_number))?;
+        let name = name.trim();
+        if name.is_empty()
+            || name.starts_with(|c: char| c.is_ascii_digit())
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(EnvFileError::InvalidName(line_number, name.to_string()));
+        }
+
+        let value = value.trim_start();
+        let value = if let Some(quote @ ('\'' | '"')) = value.chars().next() {
+            // Read until the closing quote, which might be on one of the next lines
+            let mut raw = value[1..].to_string();
+            let end = loop {
+                if let Some(end) = find_closing_quote(&raw, quote) {
+                    break end;
+                }
+                let (_, next) = lines
+                    .next()
+                    .ok_or(EnvFileError::UnterminatedQuote(line_number))?;
+                raw.push('\n');
+                raw.push_str(next);
+            };
+            raw.truncate(end);
+            if quote == '\'' {
+                raw
+            } else {
+                expand(&raw, variables, true)
+            }
+        } else {
+            // Everything after ` #` is a comment
+            let value = value.find(" #").map_or(value, |comment| &value[..comment]);
+            expand(value.trim_end(), variables, false)
+        };
+
+        variables.insert(name.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Returns the index of the first unescaped `quote` in `value`.
+fn find_closing_quote(value: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in value.char_indices() {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            c if c == quote && !escaped => return Some(index),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Replaces the references to variables in `value`, and if `unescape` is set
+/// also the escape sequences.
+fn expand(value: &str, variables: &IndexMap<String, String>, unescape: bool) -> String {
+    let lookup = |name: &str| {
+        variables
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    };
+
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if unescape => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            },
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let reference: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let value = match reference.split_once(":-") {
+                    Some((name, default)) => lookup(name)
+                        .filter(|value| !value.is_empty())
+                        .unwrap_or_else(|| default.to_string()),
+                    None => lookup(&reference).unwrap_or_default(),
+                };
+                result.push_str(&value);
+            }
+            '$' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                if name.is_empty() {
+                    result.push('$');
+                } else {
+                    result.push_str(&lookup(&name).unwrap_or_default());
+                }
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_file() {
+        let contents = r#"
+# A comment
+export HOST=localhost
+PORT = 8080 # the port
+URL=http://${HOST}:$PORT/api
+LITERAL='$HOST is not expanded'
+QUOTED="line one\nline \"two\""
+MULTI_LINE="first
+second"
+DEFAULT=${PIXI_TEST_ENV_FILE_UNSET:-fallback}
+EMPTY=
+"#;
+        let mut variables = IndexMap::new();
+        parse_env_file(contents, &mut variables).unwrap();
+        assert_eq!(variables["HOST"], "localhost");
+        assert_eq!(variables["PORT"], "8080");
+        assert_eq!(variables["URL"], "http://localhost:8080/api");
+        assert_eq!(variables["LITERAL"], "$HOST is not expanded");
+        assert_eq!(variables["QUOTED"], "line one\nline \"two\"");
+        assert_eq!(variables["MULTI_LINE"], "first\nsecond");
+        assert_eq!(variables["DEFAULT"], "fallback");
+        assert_eq!(variables["EMPTY"], "");
+    }
+
+    #[test]
+    fn test_parse_env_file_errors() {
+        let mut variables = IndexMap::new();
+        assert_eq!(
+            parse_env_file("A=1\nB", &mut variables),
+            Err(EnvFileError::MissingEquals(2))
+        );
+        assert_eq!(
+            parse_env_file("1A=1", &mut variables),
+            Err(EnvFileError::InvalidName(1, "1A".to_string()))
+        );
+        assert_eq!(
+            parse_env_file("A=\"open\nB=2", &mut variables),
+            Err(EnvFileError::UnterminatedQuote(1))
+        );
+    }
 }
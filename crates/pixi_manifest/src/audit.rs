@@ -0,0 +1,94 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Configuration of the audits of the locked packages of the project.
+#[derive(Debug, Clone, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Audit {
+    /// The vulnerabilities that are not reported by the audit.
+    #[serde(default)]
+    pub ignore: Vec<IgnoredVulnerability>,
+}
+
+/// A vulnerability that is not reported by the audit, until it expires.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoredVulnerability {
+    /// The id of the vulnerability or one of its aliases, e.g. `GHSA-...` or
+    /// `CVE-...`.
+    pub id: String,
+
+    /// The date from which the vulnerability is reported again, formatted
+    /// as `YYYY-MM-DD`.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub expires: Option<NaiveDate>,
+
+    /// Why the vulnerability is ignored.
+    pub reason: Option<String>,
+}
+
+impl Audit {
+    /// Returns the entry that ignores a vulnerability with the given id or
+    /// aliases, including entries that expired.
+    pub fn ignored<'a>(
+        &self,
+        mut ids: impl Iterator<Item = &'a str>,
+    ) -> Option<&IgnoredVulnerability> {
+        ids.find_map(|id| self.ignore.iter().find(|ignored| ignored.id == id))
+    }
+}
+
+impl IgnoredVulnerability {
+    /// Returns true if the vulnerability is no longer ignored on the given
+    /// date.
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.expires.is_some_and(|expires| expires <= today)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParsedManifest;
+
+    #[test]
+    fn test_audit_ignore() {
+        let manifest = ParsedManifest::from_toml_str(
+            r#"
+            [project]
+            name = "foo"
+            channels = []
+            platforms = []
+
+            [audit]
+            ignore = [
+                { id = "GHSA-h5c8-rqwp-cp95", expires = "2024-06-01", reason = "not reachable" },
+                { id = "PYSEC-2024-1" },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let ignored = manifest
+            .audit
+            .ignored(["CVE-2024-22195", "GHSA-h5c8-rqwp-cp95"].into_iter())
+            .unwrap();
+        assert_eq!(ignored.reason.as_deref(), Some("not reachable"));
+        assert!(ignored.is_expired(today));
+        assert!(!ignored.is_expired(today.pred_opt().unwrap()));
+
+        let ignored = manifest
+            .audit
+            .ignored(["PYSEC-2024-1"].into_iter())
+            .unwrap();
+        assert!(!ignored.is_expired(today));
+        assert!(manifest
+            .audit
+            .ignored(["PYSEC-2024-2"].into_iter())
+            .is_none());
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     collections::HashMap,
     fmt,
     hash::{Hash, Hasher},
+    path::PathBuf,
 };
 
 use indexmap::{IndexMap, IndexSet};
@@ -19,7 +20,7 @@ use crate::{
     parsed_manifest::deserialize_opt_package_map,
     parsed_manifest::deserialize_package_map,
     pypi::{pypi_options::PypiOptions, PyPiPackageName},
-    target::Targets,
+    target::{split_spanned_tasks, Targets},
     task::{Task, TaskName},
     utils::PixiSpanned,
     Activation, PyPiRequirement, SpecType, SystemRequirements, Target, TargetSelector,
@@ -285,6 +286,27 @@ impl Feature {
             })
     }
 
+    /// Returns the dotenv files of all targets that match the given
+    /// `platform` together with their location in the manifest, the files of
+    /// the most specific target come last so their variables take precedence.
+    pub fn activation_env_files(
+        &self,
+        platform: Option<Platform>,
+    ) -> Vec<(&PathBuf, Option<std::ops::Range<usize>>)> {
+        let mut env_files = self
+            .targets
+            .resolve(platform)
+            .filter_map(|t| t.activation.as_ref())
+            .filter_map(|a| a.env_file.as_ref())
+            .collect::<Vec<_>>();
+        env_files.reverse();
+        env_files
+            .into_iter()
+            .flatten()
+            .map(|env_file| (env_file.get_ref(), env_file.span()))
+            .collect()
+    }
+
     /// Returns true if the feature contains any reference to a pypi
     /// dependencies.
     pub fn has_pypi_dependencies(&self) -> bool {
@@ -337,7 +359,7 @@ impl<'de> Deserialize<'de> for Feature {
 
             /// Target specific tasks to run in the environment
             #[serde(default)]
-            tasks: HashMap<TaskName, Task>,
+            tasks: HashMap<TaskName, PixiSpanned<Task>>,
 
             /// Additional options for PyPi dependencies.
             #[serde(default)]
@@ -353,11 +375,13 @@ impl<'de> Deserialize<'de> for Feature {
             dependencies.insert(SpecType::Build, build_deps);
         }
 
+        let (tasks, task_source_locs) = split_spanned_tasks(inner.tasks);
         let default_target = Target {
             dependencies,
             pypi_dependencies: inner.pypi_dependencies,
             activation: inner.activation,
-            tasks: inner.tasks,
+            tasks,
+            task_source_locs,
         };
 
         Ok(Feature {
@@ -1,4 +1,5 @@
 mod activation;
+mod audit;
 pub(crate) mod channel;
 mod dependencies;
 mod environment;
@@ -30,7 +31,8 @@ pub use manifests::TomlManifest;
 pub use crate::environments::Environments;
 pub use crate::parsed_manifest::{deserialize_package_map, ParsedManifest};
 pub use crate::solve_group::{SolveGroup, SolveGroups};
-pub use activation::Activation;
+pub use activation::{parse_env_file, Activation, EnvFileError};
+pub use audit::{Audit, IgnoredVulnerability};
 pub use channel::{PrioritizedChannel, TomlPrioritizedChannelStrOrMap};
 pub use environment::{Environment, EnvironmentName};
 pub use error::TomlError;
@@ -38,6 +40,7 @@ pub use feature::{Feature, FeatureName};
 use itertools::Itertools;
 pub use metadata::ProjectMetadata;
 use miette::Diagnostic;
+pub use pypi::clobber_policy::PypiClobberPolicy;
 pub use pypi::pypi_requirement::PyPiRequirement;
 use rattler_conda_types::Platform;
 pub use spec_type::SpecType;
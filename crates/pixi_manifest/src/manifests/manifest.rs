@@ -42,6 +42,52 @@ impl ManifestKind {
             _ => None,
         }
     }
+
+    /// Returns the locations of the specs of conda dependencies in the
+    /// `contents` of a manifest of this kind, parsing the contents only once.
+    /// The location is `None` for a dependency that is not defined in the
+    /// manifest, e.g. when it is derived from `requires-python`.
+    pub fn dependency_source_locs<'a>(
+        &self,
+        contents: &str,
+        dependencies: impl IntoIterator<
+            Item = (
+                &'a PackageName,
+                SpecType,
+                Option<&'a TargetSelector>,
+                &'a FeatureName,
+            ),
+        >,
+    ) -> Vec<Option<std::ops::Range<usize>>> {
+        let document = toml_edit::ImDocument::parse(contents).ok();
+        dependencies
+            .into_iter()
+            .map(|(name, spec_type, target, feature_name)| {
+                let document = document.as_ref()?;
+
+                let mut path = Vec::new();
+                if let ManifestKind::Pyproject = self {
+                    path.extend(consts::PYPROJECT_PIXI_PREFIX.split('.').map(str::to_string));
+                }
+                if let FeatureName::Named(feature) = feature_name {
+                    path.extend(["feature".to_string(), feature.clone()]);
+                }
+                if let Some(target) = target {
+                    path.extend(["target".to_string(), target.to_string()]);
+                }
+                path.push(spec_type.name().to_string());
+
+                let mut item = document.as_item();
+                for key in &path {
+                    item = item.get(key.as_str())?;
+                }
+                let (_, spec) = item.as_table_like()?.iter().find(|(key, _)| {
+                    PackageName::from_str(key).is_ok_and(|package_name| &package_name == name)
+                })?;
+                spec.span()
+            })
+            .collect()
+    }
 }
 
 /// Handles the project's manifest file.
@@ -78,6 +124,14 @@ impl Manifest {
         Self::from_str(manifest_path.as_ref(), contents)
     }
 
+    /// Returns the kind of the manifest
+    pub fn kind(&self) -> ManifestKind {
+        match self.document {
+            ManifestSource::PixiToml(_) => ManifestKind::Pixi,
+            ManifestSource::PyProjectToml(_) => ManifestKind::Pyproject,
+        }
+    }
+
     /// Return the toml manifest file name ('pixi.toml' or 'pyproject.toml')
     pub fn file_name(&self) -> &str {
         match self.document {
@@ -271,7 +325,10 @@ impl Manifest {
         self.feature_mut(feature_name)?
             .targets
             .for_opt_target_mut(platform.map(TargetSelector::from).as_ref())
-            .map(|target| target.tasks.remove(&name));
+            .map(|target| {
+                target.task_source_locs.remove(&name);
+                target.tasks.remove(&name)
+            });
 
         Ok(())
     }
@@ -728,6 +785,24 @@ impl Manifest {
     pub fn preview(&self) -> Option<&Preview> {
         self.parsed.project.preview.as_ref()
     }
+
+    /// Returns the location of the spec of a conda dependency in the source of
+    /// the manifest, or `None` if the dependency is not defined in the
+    /// manifest, e.g. when it is derived from `requires-python`. Use
+    /// [`ManifestKind::dependency_source_locs`] to locate multiple
+    /// dependencies.
+    pub fn dependency_source_loc(
+        &self,
+        name: &PackageName,
+        spec_type: SpecType,
+        target: Option<&TargetSelector>,
+        feature_name: &FeatureName,
+    ) -> Option<std::ops::Range<usize>> {
+        self.kind()
+            .dependency_source_locs(&self.contents, [(name, spec_type, target, feature_name)])
+            .pop()
+            .flatten()
+    }
 }
 
 #[cfg(test)]
@@ -747,7 +822,7 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
-    use crate::channel::PrioritizedChannel;
+    use crate::{channel::PrioritizedChannel, PypiClobberPolicy};
     use glob::glob;
 
     const PROJECT_BOILERPLATE: &str = r#"
@@ -1963,6 +2038,45 @@ test = "test initial"
         assert_snapshot!(manifest.document.to_string());
     }
 
+    #[test]
+    fn test_task_environments() {
+        let contents = format!(
+            r#"
+            {PROJECT_BOILERPLATE}
+            [tasks]
+            build = {{ cmd = "make", environment = "py311" }}
+            test = {{ cmd = "pytest", environment = ["py311", "py312"] }}
+
+            [feature.py311.dependencies]
+            python = "3.11.*"
+            [feature.py312.dependencies]
+            python = "3.12.*"
+
+            [environments]
+            py311 = ["py311"]
+            py312 = ["py312"]
+            "#
+        );
+        let manifest = Manifest::from_str(Path::new("pixi.toml"), &contents).unwrap();
+        let tasks = manifest.tasks(None, &FeatureName::Default).unwrap();
+        assert_eq!(
+            tasks[&TaskName::from("build")].environments(),
+            &[EnvironmentName::Named("py311".to_string())]
+        );
+        assert_eq!(tasks[&TaskName::from("test")].environments().len(), 2);
+
+        // An undefined environment is an error
+        let contents = format!(
+            r#"
+            {PROJECT_BOILERPLATE}
+            [tasks]
+            build = {{ cmd = "make", environment = "py311" }}
+            "#
+        );
+        let err = Manifest::from_str(Path::new("pixi.toml"), &contents).unwrap_err();
+        assert!(err.to_string().contains("py311"));
+    }
+
     #[test]
     fn test_add_dependency() {
         let file_contents = r#"
@@ -2236,6 +2350,104 @@ bar = "*"
         );
     }
 
+    #[test]
+    fn test_pypi_clobber_policy() {
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = []
+        channels = []
+        "#,
+        )
+        .unwrap();
+        assert!(manifest.parsed.project.pypi_clobber_policy.is_none());
+
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = []
+        channels = []
+        pypi-clobber-policy = "prefer-conda"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.parsed.project.pypi_clobber_policy,
+            Some(PypiClobberPolicy::PreferConda)
+        );
+
+        assert!(Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = []
+        channels = []
+        pypi-clobber-policy = "ignore"
+        "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dependency_source_loc() {
+        let contents = r#"
+        [project]
+        name = "foo"
+        platforms = ["linux-64"]
+        channels = []
+
+        [dependencies]
+        Python = "3.12.*"
+
+        [feature.test.target.unix.host-dependencies]
+        numpy = { version = ">=2" }
+        "#;
+        let manifest = Manifest::from_str(Path::new("pixi.toml"), contents).unwrap();
+
+        let python_loc = manifest
+            .dependency_source_loc(
+                &PackageName::from_str("python").unwrap(),
+                SpecType::Run,
+                None,
+                &FeatureName::Default,
+            )
+            .unwrap();
+        assert_eq!(&contents[python_loc.clone()], r#""3.12.*""#);
+
+        let numpy = PackageName::from_str("numpy").unwrap();
+        let feature = FeatureName::Named("test".to_string());
+        let numpy_loc = manifest
+            .dependency_source_loc(
+                &numpy,
+                SpecType::Host,
+                Some(&TargetSelector::Unix),
+                &feature,
+            )
+            .unwrap();
+        assert_eq!(&contents[numpy_loc], r#"{ version = ">=2" }"#);
+        assert!(manifest
+            .dependency_source_loc(&numpy, SpecType::Run, Some(&TargetSelector::Unix), &feature)
+            .is_none());
+
+        // Multiple dependencies are located at once.
+        let python = PackageName::from_str("python").unwrap();
+        assert_eq!(
+            manifest.kind().dependency_source_locs(
+                &manifest.contents,
+                [
+                    (&numpy, SpecType::Run, None, &FeatureName::Default),
+                    (&python, SpecType::Run, None, &FeatureName::Default),
+                ]
+            ),
+            vec![None, Some(python_loc)]
+        );
+    }
+
     #[test]
     pub fn test_unsupported_pep508_errors() {
         let manifest_error = Manifest::from_str(
@@ -7,7 +7,7 @@ use serde::Deserialize;
 use serde_with::{serde_as, DisplayFromStr};
 use url::Url;
 
-use super::pypi::pypi_options::PypiOptions;
+use super::pypi::{clobber_policy::PypiClobberPolicy, pypi_options::PypiOptions};
 use crate::preview::Preview;
 use crate::utils::PixiSpanned;
 
@@ -63,6 +63,10 @@ pub struct ProjectMetadata {
     /// URL or Path of the conda to pypi name mapping
     pub conda_pypi_map: Option<HashMap<NamedChannelOrUrl, String>>,
 
+    /// What to do when a PyPI package overwrites the files of a conda package
+    #[serde(default)]
+    pub pypi_clobber_policy: Option<PypiClobberPolicy>,
+
     /// The pypi options supported in the project
     pub pypi_options: Option<PypiOptions>,
 
@@ -9,6 +9,7 @@ use toml_edit::DocumentMut;
 
 use crate::{
     activation::Activation,
+    audit::Audit,
     consts,
     environment::{Environment, EnvironmentIdx, EnvironmentName, TomlEnvironmentMapOrSeq},
     environments::Environments,
@@ -22,7 +23,7 @@ use crate::{
     solve_group::SolveGroups,
     spec_type::SpecType,
     system_requirements::SystemRequirements,
-    target::{Target, TargetSelector, Targets},
+    target::{split_spanned_tasks, Target, TargetSelector, Targets},
     task::{Task, TaskName},
     utils::PixiSpanned,
 };
@@ -41,6 +42,9 @@ pub struct ParsedManifest {
 
     /// The solve groups that are part of the project.
     pub solve_groups: SolveGroups,
+
+    /// The configuration of the audits of the locked packages.
+    pub audit: Audit,
 }
 
 impl ParsedManifest {
@@ -139,7 +143,7 @@ impl<'de> Deserialize<'de> for ParsedManifest {
 
             /// Target specific tasks to run in the environment
             #[serde(default)]
-            tasks: HashMap<TaskName, Task>,
+            tasks: HashMap<TaskName, PixiSpanned<Task>>,
 
             /// The features defined in the project.
             #[serde(default)]
@@ -153,6 +157,10 @@ impl<'de> Deserialize<'de> for ParsedManifest {
             #[serde(default)]
             pypi_options: Option<PypiOptions>,
 
+            /// The configuration of `pixi audit`
+            #[serde(default)]
+            audit: Audit,
+
             /// The tool configuration which is unused by pixi
             #[serde(default, skip_serializing, rename = "tool")]
             _tool: serde::de::IgnoredAny,
@@ -172,11 +180,13 @@ impl<'de> Deserialize<'de> for ParsedManifest {
             dependencies.insert(SpecType::Build, build_deps);
         }
 
+        let (tasks, task_source_locs) = split_spanned_tasks(toml_manifest.tasks);
         let default_target = Target {
             dependencies,
             pypi_dependencies: toml_manifest.pypi_dependencies,
             activation: toml_manifest.activation,
-            tasks: toml_manifest.tasks,
+            tasks,
+            task_source_locs,
         };
 
         // Construct a default feature
@@ -257,6 +267,7 @@ impl<'de> Deserialize<'de> for ParsedManifest {
             features,
             environments,
             solve_groups,
+            audit: toml_manifest.audit,
         })
     }
 }
@@ -337,7 +348,7 @@ mod tests {
     use itertools::Itertools;
     use rattler_conda_types::{NamedChannelOrUrl, Platform};
 
-    use crate::{parsed_manifest::ParsedManifest, TargetSelector};
+    use crate::{parsed_manifest::ParsedManifest, FeatureName, TargetSelector, TaskName};
 
     const PROJECT_BOILERPLATE: &str = r#"
         [project]
@@ -608,6 +619,39 @@ mod tests {
             .join("\n"));
     }
 
+    #[test]
+    fn test_task_source_locs() {
+        let contents = format!(
+            r#"
+            {PROJECT_BOILERPLATE}
+            [tasks]
+            test = "test multi"
+
+            [feature.foo.target.linux-64.tasks]
+            test = {{ cmd = "test linux" }}
+            "#
+        );
+
+        let manifest = ParsedManifest::from_toml_str(&contents).unwrap();
+
+        let name = TaskName::from("test");
+        let default_loc = manifest
+            .default_feature()
+            .targets
+            .default()
+            .task_source_loc(&name)
+            .unwrap();
+        assert_eq!(&contents[default_loc], "\"test multi\"");
+
+        let linux_loc = manifest.features[&FeatureName::Named("foo".to_string())]
+            .targets
+            .for_target(&TargetSelector::Platform(Platform::Linux64))
+            .unwrap()
+            .task_source_loc(&name)
+            .unwrap();
+        assert_eq!(&contents[linux_loc], "{ cmd = \"test linux\" }");
+    }
+
     #[test]
     fn test_python_dependencies() {
         let contents = format!(
@@ -0,0 +1,32 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// What to do when a PyPI package overwrites the files of a conda package in
+/// the same environment.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PypiClobberPolicy {
+    /// Warn about the overlapping packages and install the PyPI package.
+    #[default]
+    Warn,
+    /// Fail when a PyPI package overwrites the files of a conda package.
+    Error,
+    /// Skip the PyPI package if the conda to PyPI mapping says that the conda
+    /// package provides it, warn otherwise.
+    PreferConda,
+    /// Install the PyPI package over the conda package without warning.
+    PreferPypi,
+}
+
+impl Display for PypiClobberPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            PypiClobberPolicy::Warn => "warn",
+            PypiClobberPolicy::Error => "error",
+            PypiClobberPolicy::PreferConda => "prefer-conda",
+            PypiClobberPolicy::PreferPypi => "prefer-pypi",
+        };
+        write!(f, "{}", s)
+    }
+}
@@ -1,3 +1,4 @@
+pub mod clobber_policy;
 pub mod pypi_options;
 pub mod pypi_requirement;
 pub mod pypi_requirement_types;
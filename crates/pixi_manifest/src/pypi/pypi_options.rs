@@ -3,7 +3,7 @@ use indexmap::IndexSet;
 use rattler_lock::{FindLinksUrlOrPath, PypiIndexes};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
-use std::{fmt::Display, hash::Hash, iter};
+use std::{fmt::Display, hash::Hash, iter, str::FromStr};
 use thiserror::Error;
 use url::Url;
 
@@ -49,6 +49,12 @@ pub struct PypiOptions {
     pub no_build_isolation: Option<Vec<String>>,
     /// The strategy to use when resolving against multiple index URLs.
     pub index_strategy: Option<IndexStrategy>,
+    /// Never build source distributions, only install wheels
+    pub no_build: Option<bool>,
+    /// Packages that are always built from source, wheels are not used
+    pub no_binary: Option<Vec<String>>,
+    /// Packages that are only installed from wheels, never built from source
+    pub only_binary: Option<Vec<String>>,
 }
 
 /// Clones and deduplicates two iterators of values
@@ -64,6 +70,14 @@ fn clone_and_deduplicate<'a, I: Iterator<Item = &'a T>, T: Clone + Eq + Hash + '
         .collect::<Vec<_>>()
 }
 
+/// Returns true if the list of package names contains the package
+fn contains_package(names: Option<&[String]>, package: &pep508_rs::PackageName) -> bool {
+    names
+        .into_iter()
+        .flatten()
+        .any(|name| pep508_rs::PackageName::from_str(name).is_ok_and(|name| &name == package))
+}
+
 impl PypiOptions {
     pub fn new(
         index: Option<Url>,
@@ -78,9 +92,22 @@ impl PypiOptions {
             find_links: flat_indexes,
             no_build_isolation,
             index_strategy,
+            no_build: None,
+            no_binary: None,
+            only_binary: None,
         }
     }
 
+    /// Returns true if the package may be built from a source distribution.
+    pub fn allows_build(&self, package: &pep508_rs::PackageName) -> bool {
+        !self.no_build.unwrap_or(false) && !contains_package(self.only_binary.as_deref(), package)
+    }
+
+    /// Returns true if the package may be installed from a wheel.
+    pub fn allows_binary(&self, package: &pep508_rs::PackageName) -> bool {
+        !contains_package(self.no_binary.as_deref(), package)
+    }
+
     /// Return an iterator over all [`Url`] entries.
     /// In order of:
     /// - `find_links`
@@ -106,6 +133,8 @@ impl PypiOptions {
     /// - There can only be one primary index
     /// - Extra indexes are merged and deduplicated, in the order they are provided
     /// - Flat indexes are merged and deduplicated, in the order they are provided
+    /// - Building from source is disabled if any of the options disables it
+    /// - The `no-binary` and `only-binary` packages are merged and deduplicated
     pub fn union(&self, other: &PypiOptions) -> Result<PypiOptions, PypiOptionsMergeError> {
         let index = if let Some(other_index) = other.index_url.clone() {
             // Allow only one index
@@ -174,12 +203,45 @@ impl PypiOptions {
             })
             .or_else(|| other.no_build_isolation.clone());
 
+        // Disable building from source if either of the options disables it
+        let no_build = match (self.no_build, other.no_build) {
+            (None, None) => None,
+            (own, other) => Some(own.unwrap_or(false) || other.unwrap_or(false)),
+        };
+
+        // Merge all the packages that are always built from source
+        let no_binary = self
+            .no_binary
+            .as_ref()
+            .map(|no_binary| {
+                clone_and_deduplicate(
+                    no_binary.iter(),
+                    other.no_binary.clone().unwrap_or_default().iter(),
+                )
+            })
+            .or_else(|| other.no_binary.clone());
+
+        // Merge all the packages that are never built from source
+        let only_binary = self
+            .only_binary
+            .as_ref()
+            .map(|only_binary| {
+                clone_and_deduplicate(
+                    only_binary.iter(),
+                    other.only_binary.clone().unwrap_or_default().iter(),
+                )
+            })
+            .or_else(|| other.only_binary.clone());
+
         Ok(PypiOptions {
             index_url: index,
             extra_index_urls: extra_indexes,
             find_links: flat_indexes,
             no_build_isolation,
             index_strategy,
+            no_build,
+            no_binary,
+            only_binary,
         })
     }
 }
@@ -230,6 +292,9 @@ mod tests {
                  index-url = "https://example.com/pypi"
                  extra-index-urls = ["https://example.com/extra"]
                  no-build-isolation = ["pkg1", "pkg2"]
+                 no-build = true
+                 no-binary = ["pkg3"]
+                 only-binary = ["pkg4"]
 
                  [[find-links]]
                  path = "/path/to/flat/index"
@@ -249,6 +314,9 @@ mod tests {
                 ]),
                 no_build_isolation: Some(vec!["pkg1".to_string(), "pkg2".to_string()]),
                 index_strategy: None,
+                no_build: Some(true),
+                no_binary: Some(vec!["pkg3".to_string()]),
+                only_binary: Some(vec!["pkg4".to_string()]),
             },
         );
     }
@@ -265,6 +333,9 @@ mod tests {
             ]),
             no_build_isolation: Some(vec!["foo".to_string(), "bar".to_string()]),
             index_strategy: None,
+            no_build: None,
+            no_binary: Some(vec!["foo".to_string()]),
+            only_binary: None,
         };
 
         // Create the second set of options
@@ -277,6 +348,9 @@ mod tests {
             ]),
             no_build_isolation: Some(vec!["foo".to_string()]),
             index_strategy: None,
+            no_build: Some(true),
+            no_binary: Some(vec!["foo".to_string(), "bar".to_string()]),
+            only_binary: Some(vec!["baz".to_string()]),
         };
 
         // Merge the two options
@@ -294,6 +368,9 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: None,
+            no_build: None,
+            no_binary: None,
+            only_binary: None,
         };
 
         // Create the second set of options
@@ -303,6 +380,9 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: None,
+            no_build: None,
+            no_binary: None,
+            only_binary: None,
         };
 
         // Merge the two options
@@ -320,6 +400,9 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: Some(IndexStrategy::FirstIndex),
+            no_build: None,
+            no_binary: None,
+            only_binary: None,
         };
 
         // Create the second set of options
@@ -329,6 +412,9 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: Some(IndexStrategy::UnsafeBestMatch),
+            no_build: None,
+            no_binary: None,
+            only_binary: None,
         };
 
         // Merge the two options
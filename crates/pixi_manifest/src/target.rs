@@ -32,9 +32,37 @@ pub struct Target {
 
     /// Target specific tasks to run in the environment
     pub tasks: HashMap<TaskName, Task>,
+
+    /// The source location of the task definitions in the manifest.
+    pub task_source_locs: HashMap<TaskName, std::ops::Range<usize>>,
+}
+
+/// Splits the spanned tasks of a target into the tasks and their source
+/// locations in the manifest.
+pub(crate) fn split_spanned_tasks(
+    spanned_tasks: HashMap<TaskName, PixiSpanned<Task>>,
+) -> (
+    HashMap<TaskName, Task>,
+    HashMap<TaskName, std::ops::Range<usize>>,
+) {
+    let mut tasks = HashMap::with_capacity(spanned_tasks.len());
+    let mut source_locs = HashMap::with_capacity(spanned_tasks.len());
+    for (name, task) in spanned_tasks {
+        if let Some(span) = task.span {
+            source_locs.insert(name.clone(), span);
+        }
+        tasks.insert(name, task.value);
+    }
+    (tasks, source_locs)
 }
 
 impl Target {
+    /// Returns the source location of the task with the given name in the
+    /// manifest.
+    pub fn task_source_loc(&self, name: &TaskName) -> Option<std::ops::Range<usize>> {
+        self.task_source_locs.get(name).cloned()
+    }
+
     /// Returns the run dependencies of the target
     pub fn run_dependencies(&self) -> Option<&IndexMap<PackageName, PixiSpec>> {
         self.dependencies.get(&SpecType::Run)
@@ -359,10 +387,11 @@ impl<'de> Deserialize<'de> for Target {
 
             /// Target specific tasks to run in the environment
             #[serde(default)]
-            tasks: HashMap<TaskName, Task>,
+            tasks: HashMap<TaskName, PixiSpanned<Task>>,
         }
 
         let target = TomlTarget::deserialize(deserializer)?;
+        let (tasks, task_source_locs) = split_spanned_tasks(target.tasks);
 
         let mut dependencies = HashMap::from_iter([(SpecType::Run, target.dependencies)]);
         if let Some(host_deps) = target.host_dependencies {
@@ -376,7 +405,8 @@ impl<'de> Deserialize<'de> for Target {
             dependencies,
             pypi_dependencies: target.pypi_dependencies,
             activation: target.activation,
-            tasks: target.tasks,
+            tasks,
+            task_source_locs,
         })
     }
 }
@@ -1,5 +1,6 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
+use rattler_conda_types::Platform;
 use serde::{Deserialize, Serialize};
 use serde_with::{formats::PreferMany, serde_as, OneOrMany};
 use std::borrow::Cow;
@@ -7,6 +8,8 @@ use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 use toml_edit::{Array, Item, Table, Value};
 
+use crate::EnvironmentName;
+
 /// Represents a task name
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct TaskName(String);
@@ -62,6 +65,23 @@ impl Task {
         }
     }
 
+    /// Returns the names of the environments this task should run in. An
+    /// empty slice means the environment is selected when the task is run.
+    pub fn environments(&self) -> &[EnvironmentName] {
+        match self {
+            Task::Execute(cmd) => &cmd.environment,
+            Task::Plain(_) | Task::Custom(_) | Task::Alias(_) => &[],
+        }
+    }
+
+    /// Returns the conditions that must be met for this task to run.
+    pub fn run_if(&self) -> Option<&RunIf> {
+        match self {
+            Task::Execute(cmd) => cmd.run_if.as_ref(),
+            Task::Plain(_) | Task::Custom(_) | Task::Alias(_) => None,
+        }
+    }
+
     /// If this task is a plain task, returns the task string
     pub fn as_plain(&self) -> Option<&String> {
         match self {
@@ -158,6 +178,23 @@ impl Task {
             Task::Alias(_) => false,
         }
     }
+
+    /// Returns the glob patterns of the environment variables that are passed
+    /// through to the task when it runs in a clean environment.
+    pub fn env_passthrough(&self) -> &[String] {
+        match self {
+            Task::Execute(execute) => &execute.env_passthrough,
+            Task::Plain(_) | Task::Custom(_) | Task::Alias(_) => &[],
+        }
+    }
+
+    /// True if the command of this task should be rendered as a template.
+    pub fn template(&self) -> bool {
+        match self {
+            Task::Execute(execute) => execute.template,
+            Task::Plain(_) | Task::Custom(_) | Task::Alias(_) => false,
+        }
+    }
 }
 
 /// A command script executes a single command from the environment
@@ -193,6 +230,26 @@ pub struct Execute {
     /// Isolate the task from the running machine
     #[serde(default)]
     pub clean_env: bool,
+
+    /// Glob patterns of environment variables from the current shell that
+    /// are passed to the task when it runs in a clean environment.
+    #[serde(default)]
+    #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
+    pub env_passthrough: Vec<String>,
+
+    /// The environment(s) the task should run in. If multiple environments
+    /// are specified, the task is run once in each of them.
+    #[serde(default)]
+    #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
+    pub environment: Vec<EnvironmentName>,
+
+    /// Conditions that must be met for the task to run, otherwise it is
+    /// skipped.
+    pub run_if: Option<RunIf>,
+
+    /// Render the command as a template before it is executed.
+    #[serde(default)]
+    pub template: bool,
 }
 
 impl From<Execute> for Task {
@@ -201,6 +258,27 @@ impl From<Execute> for Task {
     }
 }
 
+/// Conditions that must all be met for a task to run.
+#[serde_as]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RunIf {
+    /// The platforms on which the task runs
+    #[serde(default)]
+    #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
+    pub platform: Vec<Platform>,
+
+    /// Environment variables that must be set
+    #[serde(default)]
+    #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
+    pub env_var: Vec<String>,
+
+    /// Files, relative to the root of the project, that must exist
+    #[serde(default)]
+    #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
+    pub file_exists: Vec<PathBuf>,
+}
+
 /// A custom command script executes a single command in the environment
 #[derive(Debug, Clone)]
 pub struct Custom {
@@ -290,6 +368,15 @@ impl Display for Task {
             }
         }
 
+        let environments = self.environments();
+        if !environments.is_empty() {
+            if environments.len() == 1 {
+                write!(f, ", environment = '{}'", environments.iter().format(","))?;
+            } else {
+                write!(f, ", environment = [{}]", environments.iter().format(","))?;
+            }
+        }
+
         let env = self.env();
         if let Some(env) = env {
             if !env.is_empty() {
@@ -362,6 +449,23 @@ impl From<Task> for Item {
                 if let Some(description) = process.description {
                     table.insert("description", description.into());
                 }
+                match process.environment.as_slice() {
+                    [] => {}
+                    [environment] => {
+                        table.insert("environment", environment.as_str().into());
+                    }
+                    environments => {
+                        table.insert(
+                            "environment",
+                            Value::Array(Array::from_iter(
+                                environments.iter().map(|env| Value::from(env.as_str())),
+                            )),
+                        );
+                    }
+                }
+                if process.template {
+                    table.insert("template", true.into());
+                }
                 Item::Value(Value::InlineTable(table))
             }
             Task::Alias(alias) => {
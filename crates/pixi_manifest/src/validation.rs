@@ -132,6 +132,29 @@ impl ParsedManifest {
             }
         }
 
+        // Check that the environments tasks should run in are defined
+        for feature in self.features.values() {
+            for target in feature.targets.targets() {
+                for (task_name, task) in target.tasks.iter() {
+                    if let Some(env_name) = task
+                        .environments()
+                        .iter()
+                        .find(|env_name| self.environments.find(*env_name).is_none())
+                    {
+                        return Err(miette::miette!(
+                            help = format!(
+                                "Available environments are: {}",
+                                self.environments.iter().map(|env| &env.name).format(", ")
+                            ),
+                            "the task '{}' should run in the environment '{}' but that environment is not defined",
+                            task_name,
+                            env_name
+                        ));
+                    }
+                }
+            }
+        }
+
         // Warn on any unknown preview features
         if let Some(preview) = self.project.preview.as_ref() {
             let preview = preview.unknown_preview_features();
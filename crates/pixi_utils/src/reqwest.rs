@@ -1,5 +1,7 @@
 use std::{path::PathBuf, sync::Arc, time::Duration};
 
+use miette::{Context, IntoDiagnostic};
+
 use pixi_consts::consts;
 use rattler_networking::{
     authentication_storage::{self, backends::file::FileStorageError},
@@ -9,11 +11,13 @@ use rattler_networking::{
     OciMiddleware,
 };
 
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use http::Extensions;
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy, Request, Response};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
 use std::collections::HashMap;
+use url::Url;
 
-use pixi_config::Config;
+use pixi_config::{ClientCertificate, Config};
 
 /// The default retry policy employed by pixi.
 /// TODO: At some point we might want to make this configurable.
@@ -76,9 +80,187 @@ pub fn oci_middleware() -> OciMiddleware {
     OciMiddleware
 }
 
-pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMiddleware) {
-    let app_user_agent = format!("pixi/{}", consts::PIXI_VERSION);
+/// The error returned for every request made while pixi is offline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineError {
+    url: Url,
+}
+
+impl OfflineError {
+    /// Returns the URL of the request that was blocked.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the file name of the package that was requested, if the
+    /// request was for a conda package.
+    fn package_file_name(&self) -> Option<&str> {
+        self.url
+            .path_segments()?
+            .last()
+            .filter(|name| name.ends_with(".conda") || name.ends_with(".tar.bz2"))
+    }
+}
+
+impl std::fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.package_file_name() {
+            Some(name) => write!(
+                f,
+                "the package `{name}` is not in the package cache and pixi is offline"
+            ),
+            None => write!(
+                f,
+                "pixi is offline and `{}` is not available in the cache",
+                self.url
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OfflineError {}
+
+/// A middleware that fails every request, used when pixi runs offline so that
+/// anything that isn't cached results in an error instead of a download.
+pub struct OfflineMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for OfflineMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        Err(reqwest_middleware::Error::Middleware(
+            OfflineError {
+                url: req.url().clone(),
+            }
+            .into(),
+        ))
+    }
+}
+
+/// Returns the proxies from the configuration, an empty list means that the
+/// proxies from the environment are used.
+fn proxies(config: &Config) -> miette::Result<Vec<Proxy>> {
+    let proxy_config = config.proxy_config();
+    let no_proxy = NoProxy::from_string(&proxy_config.non_proxy_hosts.join(","));
+
+    let mut proxies = Vec::new();
+    if let Some(url) = &proxy_config.https {
+        proxies.push(Proxy::https(url.as_str()).into_diagnostic()?);
+    }
+    if let Some(url) = &proxy_config.http {
+        proxies.push(Proxy::http(url.as_str()).into_diagnostic()?);
+    }
+
+    Ok(proxies
+        .into_iter()
+        .map(|proxy| proxy.no_proxy(no_proxy.clone()))
+        .collect())
+}
+
+/// Loads the extra CA certificates from the configured PEM bundle.
+fn ca_certificates(config: &Config) -> miette::Result<Vec<Certificate>> {
+    let Some(path) = config.ca_certificates() else {
+        return Ok(Vec::new());
+    };
+    let pem = std::fs::read(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read CA certificates from {}", path.display()))?;
+    Certificate::from_pem_bundle(&pem)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to parse CA certificates from {}", path.display()))
+}
+
+/// Loads a client certificate and its private key.
+fn client_identity(certificate: &ClientCertificate) -> miette::Result<Identity> {
+    let read = |path: &PathBuf| {
+        std::fs::read(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read client certificate {}", path.display()))
+    };
+    let cert = read(&certificate.cert)?;
+    let key = certificate.key.as_ref().map(read).transpose()?;
+
+    #[cfg(feature = "rustls-tls")]
+    let identity = Identity::from_pem(&[cert, key.unwrap_or_default()].concat());
+    #[cfg(not(feature = "rustls-tls"))]
+    let identity = Identity::from_pkcs8_pem(&cert, key.as_deref().unwrap_or(&cert));
+
+    identity.into_diagnostic().wrap_err_with(|| {
+        format!(
+            "failed to load client certificate {}",
+            certificate.cert.display()
+        )
+    })
+}
+
+/// A middleware that sends the requests to hosts that require a client
+/// certificate through a client that is configured with that certificate.
+pub struct ClientCertificateMiddleware {
+    clients: HashMap<String, Client>,
+}
+
+impl ClientCertificateMiddleware {
+    fn client_for(&self, url: &Url) -> Option<&Client> {
+        lookup_host(&self.clients, url).map(|(_, client)| client)
+    }
+}
+
+/// Returns the entry for the host of the url, an entry for `host:port` takes
+/// precedence over an entry for the host alone.
+fn lookup_host<'a, T>(map: &'a HashMap<String, T>, url: &Url) -> Option<(&'a String, &'a T)> {
+    let host = url.host_str()?;
+    url.port()
+        .and_then(|port| map.get_key_value(&format!("{host}:{port}")))
+        .or_else(|| map.get_key_value(host))
+}
+
+#[async_trait::async_trait]
+impl Middleware for ClientCertificateMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        match self.client_for(req.url()) {
+            Some(client) => client
+                .execute(req)
+                .await
+                .map_err(reqwest_middleware::Error::Reqwest),
+            None => next.run(req, extensions).await,
+        }
+    }
+}
+
+/// Returns a builder for a client with the user agent, timeouts, proxies and
+/// CA certificates from the configuration.
+fn client_builder(config: &Config) -> miette::Result<reqwest::ClientBuilder> {
+    let timeout = 5 * 60;
+    let mut builder = Client::builder()
+        .pool_max_idle_per_host(20)
+        .user_agent(format!("pixi/{}", consts::PIXI_VERSION))
+        .danger_accept_invalid_certs(config.tls_no_verify())
+        .read_timeout(Duration::from_secs(timeout));
+    for proxy in proxies(config).wrap_err("failed to configure the proxy")? {
+        builder = builder.proxy(proxy);
+    }
+    for certificate in ca_certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    Ok(builder)
+}
 
+/// Builds a client without authentication, for public requests like the
+/// conda-pypi mapping, and an authenticated client for everything else.
+///
+/// When pixi is offline both clients fail every request before it is sent.
+pub fn build_reqwest_clients(
+    config: Option<&Config>,
+) -> miette::Result<(ClientWithMiddleware, ClientWithMiddleware)> {
     // If we do not have a config, we will just load the global default.
     let config = if let Some(config) = config {
         config.clone()
@@ -90,17 +272,29 @@ pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMidd
         tracing::warn!("TLS verification is disabled. This is insecure and should only be used for testing or internal networks.");
     }
 
-    let timeout = 5 * 60;
-    let client = Client::builder()
-        .pool_max_idle_per_host(20)
-        .user_agent(app_user_agent)
-        .danger_accept_invalid_certs(config.tls_no_verify())
-        .read_timeout(Duration::from_secs(timeout))
+    let client = client_builder(&config)?
         .build()
-        .expect("failed to create reqwest Client");
+        .into_diagnostic()
+        .wrap_err("failed to create the http client")?;
+
+    // reqwest only supports a single identity per client, so create a client
+    // for every host that requires a client certificate.
+    let mut certificate_clients = HashMap::new();
+    for (host, certificate) in config.client_certificates() {
+        let client = client_builder(&config)?
+            .identity(client_identity(certificate)?)
+            .build()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to create the http client for {host}"))?;
+        certificate_clients.insert(host.clone(), client);
+    }
 
     let mut client_builder = ClientBuilder::new(client.clone());
 
+    if config.offline() {
+        client_builder = client_builder.with(OfflineMiddleware);
+    }
+
     if !config.mirror_map().is_empty() {
         client_builder = client_builder
             .with(mirror_middleware(&config))
@@ -113,7 +307,147 @@ pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMidd
         auth_middleware(&config).expect("could not create auth middleware"),
     ));
 
+    if !certificate_clients.is_empty() {
+        client_builder = client_builder.with(ClientCertificateMiddleware {
+            clients: certificate_clients,
+        });
+    }
+
     let authenticated_client = client_builder.build();
 
-    (client, authenticated_client)
+    let mut client_builder = ClientBuilder::new(client);
+    if config.offline() {
+        client_builder = client_builder.with(OfflineMiddleware);
+    }
+
+    Ok((client_builder.build(), authenticated_client))
+}
+
+/// Builds the client that is passed to uv for the given package indexes.
+///
+/// uv doesn't use the middleware of pixi, so the client certificate of the
+/// indexes is configured on the client itself. reqwest supports a single
+/// identity per client, so all indexes that require a client certificate must
+/// use the same one.
+pub fn build_uv_client<'a>(
+    config: &Config,
+    index_urls: impl IntoIterator<Item = &'a Url>,
+) -> miette::Result<Client> {
+    let mut certificate: Option<(&String, &ClientCertificate)> = None;
+    for url in index_urls {
+        let Some((host, index_certificate)) = lookup_host(config.client_certificates(), url) else {
+            continue;
+        };
+        match certificate {
+            Some((other_host, other)) if other != index_certificate => miette::bail!(
+                help = "use the same client certificate for all PyPI indexes",
+                "the PyPI indexes on '{other_host}' and '{host}' require different client certificates"
+            ),
+            _ => certificate = Some((host, index_certificate)),
+        }
+    }
+
+    let mut builder = client_builder(config)?;
+    if let Some((_, certificate)) = certificate {
+        builder = builder.identity(client_identity(certificate)?);
+    }
+    builder
+        .build()
+        .into_diagnostic()
+        .wrap_err("failed to create the http client for uv")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_certificate_host() {
+        let middleware = ClientCertificateMiddleware {
+            clients: HashMap::from([
+                ("conda.example.com".to_string(), Client::new()),
+                ("pypi.example.com:8443".to_string(), Client::new()),
+            ]),
+        };
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(middleware
+            .client_for(&url(
+                "https://conda.example.com/channel/noarch/repodata.json"
+            ))
+            .is_some());
+        assert!(middleware
+            .client_for(&url("https://conda.example.com:8080/channel"))
+            .is_some());
+        assert!(middleware
+            .client_for(&url("https://pypi.example.com:8443/simple"))
+            .is_some());
+        assert!(middleware
+            .client_for(&url("https://pypi.example.com/simple"))
+            .is_none());
+        assert!(middleware
+            .client_for(&url("https://conda.anaconda.org/conda-forge"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_network_config_is_an_error() {
+        let mut config = Config::default();
+        config.ca_certificates = Some(PathBuf::from("/does/not/exist.pem"));
+        assert!(build_reqwest_clients(Some(&config)).is_err());
+
+        let mut config = Config::default();
+        config.client_certificate.insert(
+            "pypi.example.com".to_string(),
+            ClientCertificate {
+                cert: PathBuf::from("/does/not/exist.pem"),
+                key: None,
+            },
+        );
+        assert!(build_reqwest_clients(Some(&config)).is_err());
+
+        // Indexes without a client certificate don't need one
+        let url = Url::parse("https://pypi.org/simple").unwrap();
+        assert!(build_uv_client(&config, [&url]).is_ok());
+        let url = Url::parse("https://pypi.example.com/simple").unwrap();
+        assert!(build_uv_client(&config, [&url]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_offline_clients_send_no_requests() {
+        let mut config = Config::default();
+        config.offline = Some(true);
+        let (client, authenticated_client) = build_reqwest_clients(Some(&config)).unwrap();
+
+        // Nothing listens on this port, so any other error means that the
+        // request was sent
+        let url = Url::parse("http://127.0.0.1:9/mapping.json").unwrap();
+        for client in [client, authenticated_client] {
+            let error = client.get(url.clone()).send().await.unwrap_err();
+            let reqwest_middleware::Error::Middleware(error) = error else {
+                panic!("the request was sent: {error}");
+            };
+            assert_eq!(error.downcast_ref::<OfflineError>().unwrap().url(), &url);
+        }
+    }
+
+    #[test]
+    fn test_offline_error_names_package() {
+        let error = OfflineError {
+            url: "https://conda.anaconda.org/conda-forge/linux-64/python-3.12.0-h1_0.conda"
+                .parse()
+                .unwrap(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "the package `python-3.12.0-h1_0.conda` is not in the package cache and pixi is offline"
+        );
+
+        let error = OfflineError {
+            url: "https://pypi.org/simple/numpy/".parse().unwrap(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "pixi is offline and `https://pypi.org/simple/numpy/` is not available in the cache"
+        );
+    }
 }
@@ -4,6 +4,7 @@ use pixi_manifest::pypi::{
     pypi_options::{IndexStrategy, PypiOptions},
     GitRev,
 };
+use uv_configuration::{BuildOptions, NoBinary, NoBuild};
 use uv_distribution_types::{Index, IndexLocations, IndexUrl};
 use uv_git::GitReference;
 use uv_pep508::{InvalidNameError, PackageName, VerbatimUrl, VerbatimUrlError};
@@ -176,6 +177,26 @@ pub fn names_to_build_isolation<'a>(
     packages_to_build_isolation(names, env)
 }
 
+/// Convert the `no-build`, `no-binary` and `only-binary` pypi-options to uv
+/// build options
+pub fn pypi_options_to_build_options(
+    options: &PypiOptions,
+) -> Result<BuildOptions, InvalidNameError> {
+    let no_binary = match isolated_names_to_packages(options.no_binary.as_deref())? {
+        Some(packages) if !packages.is_empty() => NoBinary::Packages(packages),
+        _ => NoBinary::None,
+    };
+    let no_build = if options.no_build.unwrap_or(false) {
+        NoBuild::All
+    } else {
+        match isolated_names_to_packages(options.only_binary.as_deref())? {
+            Some(packages) if !packages.is_empty() => NoBuild::Packages(packages),
+            _ => NoBuild::None,
+        }
+    };
+    Ok(BuildOptions::new(no_binary, no_build))
+}
+
 /// Convert pixi `IndexStrategy` to `uv_types::IndexStrategy`
 pub fn to_index_strategy(
     index_strategy: Option<&IndexStrategy>,
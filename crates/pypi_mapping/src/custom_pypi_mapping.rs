@@ -10,8 +10,8 @@ use reqwest_middleware::ClientWithMiddleware;
 use url::Url;
 
 use super::{
-    build_pypi_purl_from_package_record, is_conda_forge_record, prefix_pypi_name_mapping,
-    CustomMapping, PurlSource, Reporter,
+    build_pypi_purl_from_package_record, is_conda_forge_record, package_contents_mapping,
+    prefix_pypi_name_mapping, CustomMapping, MappingLocation, PurlSource, Reporter,
 };
 
 pub type CompressedMapping = HashMap<String, Option<String>>;
@@ -61,8 +61,12 @@ pub fn fetch_mapping_from_path(path: &Path) -> miette::Result<CompressedMapping>
 }
 
 /// Amend the records with pypi purls if they are not present yet.
+///
+/// The conda packages of the channels that are mapped to their package
+/// contents are downloaded with the `package_client`.
 pub async fn amend_pypi_purls(
     client: &ClientWithMiddleware,
+    package_client: &ClientWithMiddleware,
     mapping_url: &CustomMapping,
     conda_packages: &mut [RepoDataRecord],
     reporter: Option<Arc<dyn Reporter>>,
@@ -73,14 +77,36 @@ pub async fn amend_pypi_purls(
         .filter(|package| !mapping_url.mapping.contains_key(&package.channel))
         .cloned()
         .collect();
+    let packages_for_contents_mapping: Vec<RepoDataRecord> = conda_packages
+        .iter()
+        .filter(|package| is_package_contents_channel(mapping_url, &package.channel))
+        .cloned()
+        .collect();
 
     let custom_mapping = mapping_url.fetch_custom_mapping(client).await?;
 
+    let contents_mapping = if packages_for_contents_mapping.is_empty() {
+        HashMap::new()
+    } else {
+        package_contents_mapping::conda_pypi_contents_mapping(
+            package_client,
+            &packages_for_contents_mapping,
+            reporter.clone(),
+        )
+        .await?
+    };
+
     // When all requested channels are present in the custom_mapping, we don't have
     // to request from the prefix_mapping. This will avoid fetching unwanted
     // URLs, e.g. behind corporate firewalls
     if packages_for_prefix_mapping.is_empty() {
-        _amend_only_custom_pypi_purls(conda_packages, &custom_mapping)?;
+        for record in conda_packages.iter_mut() {
+            if is_package_contents_channel(mapping_url, &record.channel) {
+                package_contents_mapping::amend_pypi_purls_for_record(record, &contents_mapping)?;
+            } else {
+                amend_pypi_purls_for_record(record, &custom_mapping)?;
+            }
+        }
     } else {
         let prefix_mapping = prefix_pypi_name_mapping::conda_pypi_name_mapping(
             client,
@@ -98,6 +124,8 @@ pub async fn amend_pypi_purls(
                     &prefix_mapping,
                     &compressed_mapping,
                 )?;
+            } else if is_package_contents_channel(mapping_url, &record.channel) {
+                package_contents_mapping::amend_pypi_purls_for_record(record, &contents_mapping)?;
             } else {
                 amend_pypi_purls_for_record(record, &custom_mapping)?;
             }
@@ -107,6 +135,12 @@ pub async fn amend_pypi_purls(
     Ok(())
 }
 
+/// Returns `true` if the mapping of the channel is derived from the contents
+/// of its packages.
+fn is_package_contents_channel(mapping_url: &CustomMapping, channel: &str) -> bool {
+    mapping_url.mapping.get(channel) == Some(&MappingLocation::PackageContents)
+}
+
 /// Updates the specified repodata record to include an optional PyPI package
 /// name if it is missing.
 ///
@@ -17,6 +17,7 @@ use url::Url;
 use crate::custom_pypi_mapping::fetch_mapping_from_url;
 
 pub mod custom_pypi_mapping;
+pub mod package_contents_mapping;
 pub mod prefix_pypi_name_mapping;
 
 pub trait Reporter: Send + Sync {
@@ -27,6 +28,10 @@ pub trait Reporter: Send + Sync {
 
 pub type ChannelName = String;
 
+/// The location in the `conda-pypi-map` of a channel to derive its mapping
+/// from the contents of the packages.
+pub const PACKAGE_CONTENTS_MAPPING: &str = "package-contents";
+
 pub type MappingMap = HashMap<ChannelName, MappingLocation>;
 pub type MappingByChannel = HashMap<String, HashMap<String, Option<String>>>;
 
@@ -34,6 +39,8 @@ pub type MappingByChannel = HashMap<String, HashMap<String, Option<String>>>;
 pub enum MappingLocation {
     Path(PathBuf),
     Url(Url),
+    /// Derive the mapping from the contents of the conda packages.
+    PackageContents,
 }
 
 #[derive(Debug)]
@@ -84,6 +91,9 @@ impl CustomMapping {
 
                             mapping_url_to_name.insert(name.to_string(), mapping_by_name);
                         }
+                        // There is no mapping file to fetch, the packages of the
+                        // channel are inspected when amending the purls.
+                        MappingLocation::PackageContents => {}
                     }
                 }
 
@@ -123,6 +133,7 @@ pub enum PurlSource {
     HashMapping,
     CompressedMapping,
     ProjectDefinedMapping,
+    PackageContents,
 }
 
 impl PurlSource {
@@ -131,12 +142,21 @@ impl PurlSource {
             PurlSource::HashMapping => "hash-mapping",
             PurlSource::CompressedMapping => "compressed-mapping",
             PurlSource::ProjectDefinedMapping => "project-defined-mapping",
+            PurlSource::PackageContents => "package-contents",
         }
     }
 }
 
+/// Amend the records with the purls of the PyPI packages they provide.
+///
+/// The `client` is used to fetch the mapping files, while `package_client` is
+/// only used to download the conda packages themselves when the mapping is
+/// derived from their contents, so it can carry the channel credentials. When
+/// pixi is offline both clients are expected to fail every request, cached
+/// mappings are then not used either.
 pub async fn amend_pypi_purls(
     client: ClientWithMiddleware,
+    package_client: ClientWithMiddleware,
     mapping_source: &MappingSource,
     conda_packages: &mut [RepoDataRecord],
     reporter: Option<Arc<dyn Reporter>>,
@@ -161,8 +181,14 @@ pub async fn amend_pypi_purls(
 
     match mapping_source {
         MappingSource::Custom(mapping) => {
-            custom_pypi_mapping::amend_pypi_purls(&client, mapping, conda_packages, reporter)
-                .await?;
+            custom_pypi_mapping::amend_pypi_purls(
+                &client,
+                &package_client,
+                mapping,
+                conda_packages,
+                reporter,
+            )
+            .await?;
         }
         MappingSource::Prefix => {
             prefix_pypi_name_mapping::amend_pypi_purls(&client, conda_packages, reporter).await?;
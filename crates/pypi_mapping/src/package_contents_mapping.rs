@@ -0,0 +1,395 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use pixi_config::get_cache_dir;
+use rattler_cache::package_cache::PackageCache;
+use rattler_conda_types::{package::PathsJson, PackageUrl, RepoDataRecord};
+use rattler_digest::Sha256Hash;
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use super::{prefix_pypi_name_mapping::assume_conda_is_pypi, PurlSource, Reporter};
+
+const CONTENTS_DIR: &str = "package-contents-v0";
+
+/// The PyPI packages that are installed by a conda package, as found in its
+/// `.dist-info` and `.egg-info` metadata.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PackageContents {
+    pypi_packages: Vec<DistInfo>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct DistInfo {
+    /// The normalized name of the PyPI package.
+    name: String,
+    version: String,
+}
+
+/// Reads the PyPI packages from the metadata files in an extracted conda
+/// package.
+fn read_package_contents(package_dir: &Path) -> miette::Result<PackageContents> {
+    let paths = PathsJson::from_package_directory_with_deprecated_fallback(package_dir)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read the paths of {}", package_dir.display()))?;
+
+    let mut pypi_packages = Vec::new();
+    for entry in paths.paths {
+        let path = &entry.relative_path;
+        let is_metadata = match (
+            path.file_name().and_then(|name| name.to_str()),
+            path.parent()
+                .and_then(|parent| parent.extension())
+                .and_then(|extension| extension.to_str()),
+        ) {
+            (Some("METADATA"), Some("dist-info")) => true,
+            (Some("PKG-INFO"), Some("egg-info")) => true,
+            _ => false,
+        };
+        if !is_metadata {
+            continue;
+        }
+
+        if let Some(dist_info) = read_dist_info(&package_dir.join(path))? {
+            pypi_packages.push(dist_info);
+        }
+    }
+
+    Ok(PackageContents { pypi_packages })
+}
+
+/// Reads the name and version from the headers of a `METADATA` or `PKG-INFO`
+/// file.
+fn read_dist_info(path: &Path) -> miette::Result<Option<DistInfo>> {
+    let file = std::fs::File::open(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to open {}", path.display()))?;
+    let mut name = None;
+    let mut version = None;
+    for line in BufReader::new(file).lines() {
+        let line = line.into_diagnostic()?;
+        // The headers end at the first empty line, the description follows.
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Name:") {
+            name = pep508_rs::PackageName::from_str(value.trim())
+                .ok()
+                .map(|name| name.to_string());
+        } else if let Some(value) = line.strip_prefix("Version:") {
+            version = pep440_rs::Version::from_str(value.trim())
+                .ok()
+                .map(|version| version.to_string());
+        }
+    }
+
+    Ok(name
+        .zip(version)
+        .map(|(name, version)| DistInfo { name, version }))
+}
+
+/// Returns the cached contents of a package, or reads them from the package
+/// cache and caches them by the hash of the package.
+async fn package_contents(
+    client: &ClientWithMiddleware,
+    package_cache: &PackageCache,
+    contents_dir: &Path,
+    record: &RepoDataRecord,
+    sha256: &Sha256Hash,
+) -> miette::Result<PackageContents> {
+    let cache_path = contents_dir.join(format!("{:x}.json", sha256));
+    if let Ok(contents) = std::fs::read_to_string(&cache_path) {
+        if let Ok(contents) = serde_json::from_str(&contents) {
+            return Ok(contents);
+        }
+    }
+
+    // Make sure the package is extracted in the package cache, this is a no-op
+    // for packages that are already installed.
+    let cache_lock = package_cache
+        .get_or_fetch_from_url(
+            &record.package_record,
+            record.url.clone(),
+            client.clone(),
+            None,
+        )
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to fetch {}", record.file_name))?;
+    let package_dir = cache_lock.path().to_path_buf();
+    let contents = tokio::task::spawn_blocking(move || read_package_contents(&package_dir))
+        .await
+        .into_diagnostic()??;
+
+    // The cache is only an optimization, failing to write it is not an error.
+    let _ = std::fs::create_dir_all(contents_dir).and_then(|_| {
+        std::fs::write(
+            &cache_path,
+            serde_json::to_vec(&contents).expect("contents are serializable"),
+        )
+    });
+
+    Ok(contents)
+}
+
+/// Derives the conda to PyPI name mapping from the contents of the conda
+/// packages, keyed by the hash of the packages.
+pub async fn conda_pypi_contents_mapping(
+    client: &ClientWithMiddleware,
+    conda_packages: &[RepoDataRecord],
+    reporter: Option<Arc<dyn Reporter>>,
+) -> miette::Result<HashMap<Sha256Hash, PackageContents>> {
+    let filtered_packages = conda_packages
+        .iter()
+        // because we later skip adding purls for packages
+        // that have purls
+        // here we only filter packages that don't have them
+        .filter(|package| package.package_record.purls.is_none())
+        .filter_map(|package| {
+            package
+                .package_record
+                .sha256
+                .as_ref()
+                .map(|hash| (package, *hash))
+        })
+        .collect_vec();
+
+    let cache_dir = get_cache_dir()?;
+    let package_cache =
+        PackageCache::new(cache_dir.join(pixi_consts::consts::CONDA_PACKAGE_CACHE_DIR));
+    let contents_dir: PathBuf = cache_dir
+        .join(pixi_consts::consts::CONDA_PYPI_MAPPING_CACHE_DIR)
+        .join(CONTENTS_DIR);
+
+    let total_records = filtered_packages.len();
+    let mut pending_futures = FuturesUnordered::new();
+    let concurrency_limit = Arc::new(Semaphore::new(50));
+    for (record, hash) in filtered_packages {
+        if let Some(reporter) = &reporter {
+            reporter.download_started(record, total_records);
+        }
+
+        let reporter = reporter.clone();
+        let concurrency_limit = concurrency_limit.clone();
+        let package_cache = &package_cache;
+        let contents_dir = &contents_dir;
+
+        pending_futures.push(async move {
+            let _permit = concurrency_limit
+                .acquire_owned()
+                .await
+                .expect("semaphore error");
+
+            let result = package_contents(client, package_cache, contents_dir, record, &hash).await;
+
+            if let Some(reporter) = reporter {
+                match &result {
+                    Ok(_) => reporter.download_finished(record, total_records),
+                    Err(_) => reporter.download_failed(record, total_records),
+                }
+            }
+
+            result
+                .map(|contents| (hash, contents))
+                .map_err(|err| (record, err))
+        });
+    }
+
+    let mut result_map = HashMap::with_capacity(total_records);
+    while let Some(result) = pending_futures.next().await {
+        match result {
+            Ok((hash, contents)) => {
+                result_map.insert(hash, contents);
+            }
+            // A package that can't be read is left out of the mapping, its
+            // purls are then derived from its name instead.
+            Err((record, err)) => tracing::warn!(
+                "failed to read the contents of {}, assuming the same name on PyPI: {:?}",
+                record.file_name,
+                err
+            ),
+        }
+    }
+
+    Ok(result_map)
+}
+
+/// Updates the specified repodata record to include the PyPI packages found
+/// in the contents of the package, if the purls are missing.
+///
+/// Records without a hash, or whose contents couldn't be read, are assumed to
+/// be a PyPI package with the same name if they are conda-forge records.
+pub fn amend_pypi_purls_for_record(
+    record: &mut RepoDataRecord,
+    contents_mapping: &HashMap<Sha256Hash, PackageContents>,
+) -> miette::Result<()> {
+    // If we already figured out the pypi purls, we can skip this record.
+    if record.package_record.purls.is_some() {
+        return Ok(());
+    }
+
+    let contents = record
+        .package_record
+        .sha256
+        .and_then(|sha256| contents_mapping.get(&sha256));
+    let Some(contents) = contents else {
+        if let Some(purl) = assume_conda_is_pypi(None, record) {
+            record.package_record.purls = Some(BTreeSet::from([purl]));
+        }
+        return Ok(());
+    };
+
+    // An empty set of purls records that the package is not a PyPI package.
+    let mut purls = BTreeSet::new();
+    for dist_info in &contents.pypi_packages {
+        let purl = PackageUrl::builder(String::from("pypi"), dist_info.name.clone())
+            .with_version(dist_info.version.clone())
+            .with_qualifier("source", PurlSource::PackageContents.as_str())
+            .expect("valid qualifier");
+        purls.insert(purl.build().expect("valid pypi package url"));
+    }
+    record.package_record.purls = Some(purls);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rattler_conda_types::{PackageRecord, VersionWithSource};
+    use url::Url;
+
+    use super::*;
+
+    const METADATA: &str = "lib/python3.12/site-packages/foo_bar-1.0.dist-info/METADATA";
+
+    /// Writes an extracted package with the given files and a `paths.json`
+    /// that lists them.
+    fn write_package(dir: &Path, files: &[(&str, &str)]) {
+        let mut paths = Vec::new();
+        for (path, contents) in files {
+            let full_path = dir.join(path);
+            std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            std::fs::write(&full_path, contents).unwrap();
+            let sha256 = rattler_digest::compute_bytes_digest::<rattler_digest::Sha256>(contents);
+            paths.push(serde_json::json!({
+                "_path": path,
+                "path_type": "hardlink",
+                "sha256": format!("{:x}", sha256),
+                "size_in_bytes": contents.len(),
+            }));
+        }
+        std::fs::create_dir_all(dir.join("info")).unwrap();
+        std::fs::write(
+            dir.join("info/paths.json"),
+            serde_json::json!({ "paths": paths, "paths_version": 1 }).to_string(),
+        )
+        .unwrap();
+    }
+
+    fn conda_forge_record(name: &str, sha256: Sha256Hash) -> RepoDataRecord {
+        let mut package_record = PackageRecord::new(
+            name.parse().unwrap(),
+            VersionWithSource::from_str("1.0").unwrap(),
+            "pyhd8ed1ab_0".to_string(),
+        );
+        package_record.sha256 = Some(sha256);
+        RepoDataRecord {
+            package_record,
+            file_name: format!("{name}-1.0-pyhd8ed1ab_0.conda"),
+            url: Url::parse(&format!(
+                "https://conda.anaconda.org/conda-forge/noarch/{name}-1.0-pyhd8ed1ab_0.conda"
+            ))
+            .unwrap(),
+            channel: "https://conda.anaconda.org/conda-forge/".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_read_dist_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("METADATA");
+        std::fs::write(
+            &path,
+            "Metadata-Version: 2.1\nName: Foo_Bar\nVersion: 1.0\n\nName: not-a-header\n",
+        )
+        .unwrap();
+
+        let dist_info = read_dist_info(&path).unwrap().unwrap();
+        assert_eq!(dist_info.name, "foo-bar");
+        assert_eq!(dist_info.version, "1.0");
+
+        // Without a version the file doesn't describe a package.
+        std::fs::write(&path, "Metadata-Version: 2.1\nName: Foo_Bar\n").unwrap();
+        assert!(read_dist_info(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_package_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        write_package(
+            dir.path(),
+            &[
+                (
+                    METADATA,
+                    "Metadata-Version: 2.1\nName: Foo_Bar\nVersion: 1.0\n\nbody\n",
+                ),
+                (
+                    "lib/python3.12/site-packages/foo_bar-1.0.dist-info/RECORD",
+                    "Name: not-metadata\nVersion: 1.0\n",
+                ),
+                (
+                    "lib/python3.12/site-packages/METADATA",
+                    "Name: not-metadata\nVersion: 1.0\n",
+                ),
+            ],
+        );
+
+        let contents = read_package_contents(dir.path()).unwrap();
+        let packages = contents
+            .pypi_packages
+            .iter()
+            .map(|dist_info| (dist_info.name.as_str(), dist_info.version.as_str()))
+            .collect_vec();
+        assert_eq!(packages, vec![("foo-bar", "1.0")]);
+    }
+
+    #[test]
+    fn test_amend_pypi_purls_for_record() {
+        let dir = tempfile::tempdir().unwrap();
+        write_package(
+            dir.path(),
+            &[(
+                METADATA,
+                "Metadata-Version: 2.1\nName: Foo_Bar\nVersion: 1.0\n",
+            )],
+        );
+        let read_hash = Sha256Hash::from([1; 32]);
+        let contents_mapping =
+            HashMap::from([(read_hash, read_package_contents(dir.path()).unwrap())]);
+
+        // The purls of a package that was read come from its contents.
+        let mut record = conda_forge_record("foo-bar-python", read_hash);
+        amend_pypi_purls_for_record(&mut record, &contents_mapping).unwrap();
+        let purls = record.package_record.purls.unwrap();
+        assert_eq!(purls.len(), 1);
+        let purl = purls.first().unwrap();
+        assert_eq!(purl.name(), "foo-bar");
+        assert_eq!(purl.version(), Some("1.0"));
+
+        // A package that couldn't be read is assumed to have the same name on PyPI.
+        let mut record = conda_forge_record("unreadable", Sha256Hash::from([2; 32]));
+        amend_pypi_purls_for_record(&mut record, &contents_mapping).unwrap();
+        let purls = record.package_record.purls.unwrap();
+        assert_eq!(purls.len(), 1);
+        assert_eq!(purls.first().unwrap().name(), "unreadable");
+    }
+}
@@ -29,6 +29,7 @@ Md5Sum = Annotated[str, StringConstraints(pattern=r"^[a-fA-F0-9]{32}$")]
 Sha256Sum = Annotated[str, StringConstraints(pattern=r"^[a-fA-F0-9]{64}$")]
 PathNoBackslash = Annotated[str, StringConstraints(pattern=r"^[^\\]+$")]
 Glob = NonEmptyStr
+IsoDate = Annotated[str, StringConstraints(pattern=r"^\d{4}-\d{2}-\d{2}$")]
 UnsignedInt = Annotated[int, Field(strict=True, ge=0)]
 GitUrl = Annotated[
     str, StringConstraints(pattern=r"((git|ssh|http(s)?)|(git@[\w\.]+))(:(\/\/)?)([\w\.@:\/\\-~]+)")
@@ -88,6 +89,15 @@ class ChannelPriority(str, Enum):
     strict = "strict"
 
 
+class PyPIClobberPolicy(str, Enum):
+    """What to do when a PyPI package overwrites the files of a conda package."""
+
+    warn = "warn"
+    error = "error"
+    prefer_conda = "prefer-conda"
+    prefer_pypi = "prefer-pypi"
+
+
 class KnownPreviewFeature(str, Enum):
     """The preview features of the project."""
 
@@ -140,6 +150,16 @@ class Project(StrictBaseModel):
     conda_pypi_map: dict[ChannelName, AnyHttpUrl | NonEmptyStr] | None = Field(
         None, alias="conda-pypi-map", description="The `conda` to PyPI mapping configuration"
     )
+    pypi_clobber_policy: PyPIClobberPolicy | None = Field(
+        None,
+        alias="pypi-clobber-policy",
+        examples=["warn", "error", "prefer-conda", "prefer-pypi"],
+        description="What to do when a PyPI package overwrites the files of a conda package."
+        "- 'warn': install the PyPI package and warn about the overlapping packages."
+        "- 'error': fail when a PyPI package overwrites the files of a conda package."
+        "- 'prefer-conda': don't install the PyPI package if the conda package provides it according to the mapping."
+        "- 'prefer-pypi': install the PyPI package without warning.",
+    )
     pypi_options: PyPIOptions | None = Field(
         None, alias="pypi-options", description="Options related to PyPI indexes for this project"
     )
@@ -288,6 +308,26 @@ Dependencies = dict[CondaPackageName, MatchSpec] | None
 TaskName = Annotated[str, Field(pattern=r"^[^\s\$]+$", description="A valid task name.")]
 
 
+class TaskRunIf(StrictBaseModel):
+    """Conditions that must all be met for a task to run, otherwise the task is skipped."""
+
+    platform: list[Platform] | Platform | None = Field(
+        None, description="The platforms on which the task runs"
+    )
+    env_var: list[NonEmptyStr] | NonEmptyStr | None = Field(
+        None,
+        alias="env-var",
+        description="The environment variables that must be set",
+        examples=["CI"],
+    )
+    file_exists: list[PathNoBackslash] | PathNoBackslash | None = Field(
+        None,
+        alias="file-exists",
+        description="The files, relative to the root of the project, that must exist",
+        examples=["setup.cfg"],
+    )
+
+
 class TaskInlineTable(StrictBaseModel):
     """A precise definition of a task."""
 
@@ -330,6 +370,26 @@ class TaskInlineTable(StrictBaseModel):
         alias="clean-env",
         description="Whether to run in a clean environment, removing all environment variables except those defined in `env` and by pixi itself.",
     )
+    env_passthrough: list[NonEmptyStr] | NonEmptyStr | None = Field(
+        None,
+        alias="env-passthrough",
+        description="Glob patterns of environment variables that are passed through from the current shell when the task runs in a clean environment.",
+        examples=[["AWS_*", "HTTP_PROXY"]],
+    )
+    environment: list[EnvironmentName] | EnvironmentName | None = Field(
+        None,
+        description="The environment(s) to run the task in. When multiple environments are given, the task runs once in each of them.",
+        examples=["docs", ["py311", "py312"]],
+    )
+    run_if: TaskRunIf | None = Field(
+        None,
+        alias="run-if",
+        description="Conditions that must be met for the task to run, otherwise the task is skipped",
+    )
+    template: bool | None = Field(
+        None,
+        description="Whether to render the command as a Jinja template before running it",
+    )
 
 
 #######################
@@ -403,6 +463,12 @@ class Activation(StrictBaseModel):
         description="A map of environment variables to values, used in the activation of the environment. These will be set in the shell. Thus these variables are shell specific. Using '$' might not expand to a value in different shells.",
         examples=[{"key": "value"}, {"ARGUMENT": "value"}],
     )
+    env_file: list[NonEmptyStr] | None = Field(
+        None,
+        alias="env-file",
+        description="The dotenv files to read environment variables from, relative to the project root. Variables from `env` take precedence over the variables from these files.",
+        examples=[[".env", ".env.local"]],
+    )
 
 
 ##################
@@ -533,6 +599,51 @@ class PyPIOptions(StrictBaseModel):
         description="The strategy to use when resolving packages from multiple indexes",
         examples=["first-index", "unsafe-first-match", "unsafe-best-match"],
     )
+    no_build: bool | None = Field(
+        None,
+        alias="no-build",
+        description="Don't build source distributions, only install wheels",
+    )
+    no_binary: list[PyPIPackageName] = Field(
+        None,
+        alias="no-binary",
+        description="Packages that are always built from source, their wheels are not used",
+        examples=[["numpy"]],
+    )
+    only_binary: list[PyPIPackageName] = Field(
+        None,
+        alias="only-binary",
+        description="Packages that are only installed from wheels, they are never built from source",
+        examples=[["numpy"]],
+    )
+
+
+#########
+# Audit #
+#########
+
+
+class IgnoredVulnerability(StrictBaseModel):
+    """A vulnerability that is not reported by `pixi audit`, until it expires"""
+
+    id: NonEmptyStr = Field(
+        description="The id of the vulnerability or one of its aliases",
+        examples=["GHSA-h5c8-rqwp-cp95", "CVE-2024-22195"],
+    )
+    expires: IsoDate | None = Field(
+        None,
+        description="The date from which the vulnerability is reported again",
+        examples=["2025-01-31"],
+    )
+    reason: NonEmptyStr | None = Field(None, description="Why the vulnerability is ignored")
+
+
+class Audit(StrictBaseModel):
+    """The configuration of the audits of the locked packages"""
+
+    ignore: list[IgnoredVulnerability] | None = Field(
+        None, description="The vulnerabilities that are not reported"
+    )
 
 
 #######################
@@ -597,6 +708,9 @@ class BaseManifest(StrictBaseModel):
         alias="pypi-options",
         description="Options related to PyPI indexes, on the default feature",
     )
+    audit: Audit | None = Field(
+        None, description="The configuration of the audits of the locked packages"
+    )
 
 
 #########################
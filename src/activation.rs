@@ -17,6 +17,7 @@ use rattler_shell::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
 
 // Setting a base prefix for the pixi package
@@ -347,6 +348,90 @@ pub async fn run_activation(
     Ok(activator_result)
 }
 
+/// The source that set an environment variable during the activation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActivationSource {
+    /// Set by activating the prefix itself, e.g. `PATH` and `CONDA_PREFIX`.
+    Prefix,
+    /// Set by pixi to describe the project and the environment.
+    Pixi,
+    /// Set by an entry of `[activation.env]` in the manifest.
+    ActivationEnv,
+    /// Set by an activation script, either from the manifest or from a package.
+    Script(PathBuf),
+}
+
+impl Display for ActivationSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActivationSource::Prefix => write!(f, "environment prefix"),
+            ActivationSource::Pixi => write!(f, "pixi"),
+            ActivationSource::ActivationEnv => write!(f, "[activation.env]"),
+            ActivationSource::Script(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Runs the activation of the environment one activation script at a time to
+/// find out which source set each of the environment variables. The source of
+/// a variable is the last step of the activation that changed its value.
+///
+/// This runs the activation once for every activation script, so unlike
+/// [`run_activation`] it never uses the activation cache.
+pub(crate) async fn run_activation_with_sources(
+    environment: &Environment<'_>,
+) -> miette::Result<IndexMap<String, (String, ActivationSource)>> {
+    let scripts = get_activator(environment, ShellEnum::default())
+        .into_diagnostic()?
+        .activation_scripts;
+    let static_env = get_static_environment_variables(environment);
+    let activation_env = environment.activation_env(Some(Platform::current()));
+
+    let mut result: IndexMap<String, (String, ActivationSource)> = IndexMap::new();
+    for step in 0..=scripts.len() {
+        let mut activator = Activator::from_path(
+            environment.dir().as_path(),
+            ShellEnum::default(),
+            Platform::current(),
+        )
+        .into_diagnostic()?;
+        activator.activation_scripts = scripts[..step].to_vec();
+        activator.env_vars.extend(static_env.clone());
+
+        let variables = tokio::task::spawn_blocking(move || {
+            activator.run_activation(
+                ActivationVariables {
+                    path: Default::default(),
+                    conda_prefix: None,
+                    path_modification_behavior: PathModificationBehavior::Prepend,
+                },
+                None,
+            )
+        })
+        .await
+        .into_diagnostic()?
+        .into_diagnostic()?;
+
+        for (key, value) in variables.into_iter().sorted() {
+            if result
+                .get(&key)
+                .is_some_and(|(previous, _)| previous == &value)
+            {
+                continue;
+            }
+            let source = match step.checked_sub(1) {
+                Some(script) => ActivationSource::Script(scripts[script].clone()),
+                None if activation_env.contains_key(&key) => ActivationSource::ActivationEnv,
+                None if static_env.contains_key(&key) => ActivationSource::Pixi,
+                None => ActivationSource::Prefix,
+            };
+            result.insert(key, (value, source));
+        }
+    }
+
+    Ok(result)
+}
+
 /// Get the environment variables that are statically generated from the project and the environment.
 /// Returns IndexMap to stay sorted, as pixi should export the metadata before exporting variables that could depend on it.
 pub(crate) fn get_static_environment_variables<'p>(
@@ -374,9 +459,63 @@ pub(crate) fn get_static_environment_variables<'p>(
         .collect()
 }
 
+/// Returns true if the name of an environment variable matches the glob
+/// `pattern`, where `*` matches any sequence of characters and `?` matches a
+/// single character. On Windows the comparison is case-insensitive.
+fn matches_env_pattern(pattern: &str, name: &str) -> bool {
+    let normalize = |s: &str| -> Vec<char> {
+        if cfg!(windows) {
+            s.to_uppercase().chars().collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+    let (pattern, name) = (normalize(pattern), normalize(name));
+
+    let (mut p, mut n) = (0, 0);
+    let mut last_star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            last_star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = last_star {
+            // Let the last `*` match one more character and try again
+            p = star_p + 1;
+            n = star_n + 1;
+            last_star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Get the environment variables that are set in the current shell whose name
+/// matches any of the glob `patterns`.
+pub(crate) fn get_passthrough_environment_variables(
+    patterns: &[String],
+) -> HashMap<String, String> {
+    if patterns.is_empty() {
+        return HashMap::new();
+    }
+    std::env::vars()
+        .filter(|(key, _)| {
+            patterns
+                .iter()
+                .any(|pattern| matches_env_pattern(pattern, key))
+        })
+        .collect()
+}
+
 /// Get the environment variables that are set in the current shell
 /// and strip them down to the minimal set required to run a command.
-pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
+///
+/// The variables matching any of the `passthrough` glob patterns are kept as
+/// well.
+pub(crate) fn get_clean_environment_variables(passthrough: &[String]) -> HashMap<String, String> {
     let env = std::env::vars().collect::<HashMap<_, _>>();
 
     let unix_keys = if cfg!(unix) {
@@ -412,6 +551,7 @@ pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
 
     env.into_iter()
         .filter(|(key, _)| keys.contains(&key.to_string().to_uppercase()))
+        .chain(get_passthrough_environment_variables(passthrough))
         .collect::<HashMap<String, String>>()
 }
 
@@ -445,7 +585,9 @@ pub(crate) async fn initialize_env_variables(
                 "Currently it's not possible to run a `clean-env` option on Windows."
             ));
         }
-        CurrentEnvVarBehavior::Clean => get_clean_environment_variables(),
+        CurrentEnvVarBehavior::Clean => {
+            get_clean_environment_variables(environment.project().config().env_passthrough())
+        }
         CurrentEnvVarBehavior::Include => std::env::vars().collect(),
         CurrentEnvVarBehavior::Exclude => HashMap::new(),
     };
@@ -559,7 +701,7 @@ mod tests {
     #[test]
     #[cfg(target_os = "linux")]
     fn test_get_linux_clean_environment_variables() {
-        let env = get_clean_environment_variables();
+        let env = get_clean_environment_variables(&[]);
         // Make sure that the environment variables are set.
         assert_eq!(
             env.get("USER").unwrap(),
@@ -567,6 +709,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_matches_env_pattern() {
+        assert!(matches_env_pattern("AWS_*", "AWS_ACCESS_KEY_ID"));
+        assert!(matches_env_pattern("AWS_*", "AWS_"));
+        assert!(!matches_env_pattern("AWS_*", "MY_AWS_KEY"));
+        assert!(matches_env_pattern("*_PROXY", "HTTPS_PROXY"));
+        assert!(matches_env_pattern("HTTP?_PROXY", "HTTPS_PROXY"));
+        assert!(!matches_env_pattern("HTTP?_PROXY", "HTTP_PROXY"));
+        assert!(matches_env_pattern("SSH_AUTH_SOCK", "SSH_AUTH_SOCK"));
+        assert!(matches_env_pattern("*A*B*", "XAXXBX"));
+        assert!(!matches_env_pattern("*A*B", "XAXXBX"));
+    }
+
+    #[test]
+    fn test_get_passthrough_environment_variables() {
+        std::env::set_var("PIXI_TEST_PASSTHROUGH_VAR", "1");
+        let env = get_passthrough_environment_variables(&["PIXI_TEST_PASSTHROUGH_*".to_string()]);
+        assert_eq!(env.get("PIXI_TEST_PASSTHROUGH_VAR").unwrap(), "1");
+        assert!(get_passthrough_environment_variables(&[]).is_empty());
+    }
+
     /// Test that the activation cache is created and used correctly based on the lockfile.
     ///
     /// This test will validate the cache usages by running the activation script and checking if the cache is created.
@@ -0,0 +1,376 @@
+//! Matches the locked packages against a local database of vulnerabilities in
+//! the [OSV format](https://ossf.github.io/osv-schema/), e.g. an extracted
+//! export of the PyPI ecosystem of osv.dev. Matching never touches the
+//! network.
+
+use std::{collections::HashMap, path::Path, str::FromStr, sync::Arc};
+
+use fs_err as fs;
+use miette::{Context, IntoDiagnostic};
+use pep440_rs::Version;
+use pep508_rs::PackageName;
+use serde::Deserialize;
+
+/// The OSV ecosystem of the packages on PyPI.
+const PYPI_ECOSYSTEM: &str = "PyPI";
+
+/// The prefix of the package urls of the packages on PyPI.
+const PYPI_PURL_PREFIX: &str = "pkg:pypi/";
+
+#[derive(Debug, Deserialize)]
+struct OsvRecord {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    summary: Option<String>,
+    withdrawn: Option<String>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvAffected {
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+    purl: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OsvEvent {
+    Introduced(String),
+    Fixed(String),
+    LastAffected(String),
+    Limit(String),
+}
+
+/// The id and description of a vulnerability.
+#[derive(Debug)]
+pub(crate) struct Advisory {
+    pub(crate) id: String,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) summary: Option<String>,
+}
+
+impl Advisory {
+    /// Returns the id followed by the aliases of the vulnerability.
+    pub(crate) fn ids(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.id.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+}
+
+/// The versions of a package that are affected by a vulnerability.
+#[derive(Debug)]
+struct AffectedVersions {
+    advisory: Arc<Advisory>,
+    versions: Vec<Version>,
+    /// The events of the ranges, sorted by version. `None` is the version
+    /// `0`, which is before all other versions.
+    ranges: Vec<Vec<(Option<Version>, EventKind)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventKind {
+    Introduced,
+    Fixed,
+    LastAffected,
+    Limit,
+}
+
+/// A vulnerability that affects a locked package.
+#[derive(Debug)]
+pub(crate) struct Vulnerability {
+    pub(crate) advisory: Arc<Advisory>,
+    /// The versions in which the vulnerability is fixed, empty if no fix is
+    /// known.
+    pub(crate) fixed: Vec<Version>,
+}
+
+/// The vulnerabilities of the packages on PyPI, by package name.
+#[derive(Debug, Default)]
+pub(crate) struct VulnerabilityDatabase {
+    packages: HashMap<PackageName, Vec<AffectedVersions>>,
+    advisories: usize,
+}
+
+impl VulnerabilityDatabase {
+    /// Reads all the `.json` files in a directory and its subdirectories.
+    pub(crate) fn from_dir(dir: &Path) -> miette::Result<Self> {
+        if !dir.is_dir() {
+            miette::bail!(
+                "the vulnerability database '{}' is not a directory",
+                dir.display()
+            );
+        }
+
+        let mut database = Self::default();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir).into_diagnostic()? {
+                let path = entry.into_diagnostic()?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "json") {
+                    database.add_file(&path)?;
+                }
+            }
+        }
+
+        if database.advisories == 0 {
+            miette::bail!(
+                help = "extract an export of the PyPI ecosystem of osv.dev in the directory",
+                "the vulnerability database '{}' doesn't contain any vulnerabilities",
+                dir.display()
+            );
+        }
+        Ok(database)
+    }
+
+    fn add_file(&mut self, path: &Path) -> miette::Result<()> {
+        let record: OsvRecord = serde_json::from_str(&fs::read_to_string(path).into_diagnostic()?)
+            .into_diagnostic()
+            .with_context(|| format!("failed to parse the vulnerability '{}'", path.display()))?;
+        self.add_record(record);
+        Ok(())
+    }
+
+    fn add_record(&mut self, record: OsvRecord) {
+        if record.withdrawn.is_some() {
+            return;
+        }
+
+        let advisory = Arc::new(Advisory {
+            id: record.id,
+            aliases: record.aliases,
+            summary: record.summary,
+        });
+        let mut added = false;
+        for affected in record.affected {
+            let Some(name) = affected.package.as_ref().and_then(pypi_package_name) else {
+                continue;
+            };
+            let versions = affected
+                .versions
+                .iter()
+                .filter_map(|version| Version::from_str(version).ok())
+                .collect();
+            let ranges = affected
+                .ranges
+                .into_iter()
+                .filter(|range| range.kind == "ECOSYSTEM" || range.kind == "SEMVER")
+                .map(|range| sorted_events(range.events))
+                .collect();
+            self.packages
+                .entry(name)
+                .or_default()
+                .push(AffectedVersions {
+                    advisory: advisory.clone(),
+                    versions,
+                    ranges,
+                });
+            added = true;
+        }
+        if added {
+            self.advisories += 1;
+        }
+    }
+
+    /// Returns the number of vulnerabilities in the database.
+    pub(crate) fn advisory_count(&self) -> usize {
+        self.advisories
+    }
+
+    /// Returns the vulnerabilities that affect the given version of a PyPI
+    /// package.
+    pub(crate) fn vulnerabilities(
+        &self,
+        name: &PackageName,
+        version: &Version,
+    ) -> Vec<Vulnerability> {
+        let mut vulnerabilities: Vec<Vulnerability> = Vec::new();
+        for affected in self.packages.get(name).into_iter().flatten() {
+            let mut is_affected = affected.versions.contains(version);
+            let mut fixed = Vec::new();
+            for events in &affected.ranges {
+                if range_contains(events, version) {
+                    is_affected = true;
+                    fixed.extend(
+                        events
+                            .iter()
+                            .filter(|(_, kind)| *kind == EventKind::Fixed)
+                            .filter_map(|(fixed, _)| fixed.clone())
+                            .filter(|fixed| fixed > version),
+                    );
+                }
+            }
+            if !is_affected {
+                continue;
+            }
+
+            // A vulnerability can be listed more than once for a package.
+            match vulnerabilities
+                .iter_mut()
+                .find(|vulnerability| vulnerability.advisory.id == affected.advisory.id)
+            {
+                Some(vulnerability) => vulnerability.fixed.extend(fixed),
+                None => vulnerabilities.push(Vulnerability {
+                    advisory: affected.advisory.clone(),
+                    fixed,
+                }),
+            }
+        }
+        for vulnerability in &mut vulnerabilities {
+            vulnerability.fixed.sort();
+            vulnerability.fixed.dedup();
+        }
+        vulnerabilities
+    }
+}
+
+/// Returns the normalized name of the package if it is a PyPI package.
+fn pypi_package_name(package: &OsvPackage) -> Option<PackageName> {
+    if package.ecosystem == PYPI_ECOSYSTEM {
+        return PackageName::from_str(&package.name).ok();
+    }
+    let name = package.purl.as_deref()?.strip_prefix(PYPI_PURL_PREFIX)?;
+    let name = name.split(['@', '?', '#']).next().unwrap_or(name);
+    PackageName::from_str(name).ok()
+}
+
+fn sorted_events(events: Vec<OsvEvent>) -> Vec<(Option<Version>, EventKind)> {
+    let mut events: Vec<_> = events
+        .into_iter()
+        .filter_map(|event| {
+            let (version, kind) = match event {
+                OsvEvent::Introduced(version) => (version, EventKind::Introduced),
+                OsvEvent::Fixed(version) => (version, EventKind::Fixed),
+                OsvEvent::LastAffected(version) => (version, EventKind::LastAffected),
+                OsvEvent::Limit(version) => (version, EventKind::Limit),
+            };
+            if version == "0" {
+                return Some((None, kind));
+            }
+            Version::from_str(&version)
+                .ok()
+                .map(|version| (Some(version), kind))
+        })
+        .collect();
+    events.sort_by(|(a, _), (b, _)| a.cmp(b));
+    events
+}
+
+/// Evaluates the sorted events of a range, as described by the OSV format.
+fn range_contains(events: &[(Option<Version>, EventKind)], version: &Version) -> bool {
+    let mut affected = false;
+    for (event_version, kind) in events {
+        let reached = match event_version {
+            Some(event_version) => event_version <= version,
+            None => true,
+        };
+        match kind {
+            EventKind::Introduced if reached => affected = true,
+            EventKind::Fixed | EventKind::Limit if reached => affected = false,
+            EventKind::LastAffected if reached && event_version.as_ref() != Some(version) => {
+                affected = false
+            }
+            _ => {}
+        }
+    }
+    affected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> VulnerabilityDatabase {
+        let mut database = VulnerabilityDatabase::default();
+        let records = [
+            r#"{
+                "id": "GHSA-h5c8-rqwp-cp95",
+                "aliases": ["CVE-2024-22195"],
+                "summary": "Jinja vulnerable to HTML attribute injection",
+                "affected": [{
+                    "package": {"ecosystem": "PyPI", "name": "Jinja2", "purl": "pkg:pypi/jinja2"},
+                    "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "3.1.3"}]}]
+                }]
+            }"#,
+            r#"{
+                "id": "PYSEC-2023-1",
+                "affected": [{
+                    "package": {"ecosystem": "PyPI", "name": "requests"},
+                    "ranges": [{"type": "ECOSYSTEM", "events": [
+                        {"introduced": "2.3.0"}, {"last_affected": "2.30.0"},
+                        {"introduced": "2.32.0"}, {"fixed": "2.32.2"}
+                    ]}],
+                    "versions": ["1.0.0"]
+                }]
+            }"#,
+            r#"{
+                "id": "PYSEC-2023-2",
+                "withdrawn": "2023-06-01T00:00:00Z",
+                "affected": [{"package": {"ecosystem": "PyPI", "name": "requests"}, "versions": ["2.31.0"]}]
+            }"#,
+            r#"{
+                "id": "RUSTSEC-2023-1",
+                "affected": [{"package": {"ecosystem": "crates.io", "name": "requests"}, "versions": ["2.31.0"]}]
+            }"#,
+        ];
+        for record in records {
+            database.add_record(serde_json::from_str(record).unwrap());
+        }
+        database
+    }
+
+    fn ids(name: &str, version: &str) -> Vec<String> {
+        database()
+            .vulnerabilities(
+                &PackageName::from_str(name).unwrap(),
+                &Version::from_str(version).unwrap(),
+            )
+            .into_iter()
+            .map(|vulnerability| vulnerability.advisory.id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_vulnerabilities() {
+        assert_eq!(database().advisory_count(), 2);
+
+        let jinja = database().vulnerabilities(
+            &PackageName::from_str("jinja2").unwrap(),
+            &Version::from_str("3.1.2").unwrap(),
+        );
+        assert_eq!(jinja.len(), 1);
+        assert_eq!(
+            jinja[0].advisory.ids().collect::<Vec<_>>(),
+            ["GHSA-h5c8-rqwp-cp95", "CVE-2024-22195"]
+        );
+        assert_eq!(jinja[0].fixed, [Version::from_str("3.1.3").unwrap()]);
+        assert!(ids("jinja2", "3.1.3").is_empty());
+
+        assert_eq!(ids("requests", "1.0.0"), ["PYSEC-2023-1"]);
+        assert!(ids("requests", "2.2.0").is_empty());
+        assert_eq!(ids("requests", "2.30.0"), ["PYSEC-2023-1"]);
+        assert!(ids("requests", "2.31.0").is_empty());
+        assert_eq!(ids("requests", "2.32.1"), ["PYSEC-2023-1"]);
+        assert!(ids("requests", "2.32.2").is_empty());
+    }
+}
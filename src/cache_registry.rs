@@ -0,0 +1,204 @@
+//! Keeps track of the lock-files of the projects that use the cache, so that
+//! the cache entries that none of them references can be removed.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use fs_err as fs;
+use miette::{Context, IntoDiagnostic};
+use pixi_config::get_cache_dir;
+use pixi_consts::consts;
+use rattler_conda_types::package::ArchiveType;
+use rattler_lock::{LockFile, Package};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CacheRegistry {
+    lock_files: BTreeSet<PathBuf>,
+}
+
+impl CacheRegistry {
+    fn path() -> miette::Result<PathBuf> {
+        Ok(get_cache_dir()?.join(consts::CACHE_REGISTRY_FILE))
+    }
+
+    fn load(path: &Path) -> miette::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .into_diagnostic()
+                .with_context(|| format!("failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).into_diagnostic(),
+        }
+    }
+
+    /// Writes the registry to a temporary file first so concurrent readers
+    /// never see a partially written file.
+    fn save(&self, path: &Path) -> miette::Result<()> {
+        let dir = path
+            .parent()
+            .expect("the registry is in the cache directory");
+        fs::create_dir_all(dir).into_diagnostic()?;
+        let mut file = tempfile::NamedTempFile::new_in(dir).into_diagnostic()?;
+        file.write_all(&serde_json::to_vec_pretty(self).into_diagnostic()?)
+            .into_diagnostic()?;
+        file.persist(path).into_diagnostic()?;
+        Ok(())
+    }
+
+    /// Loads the registry, applies `f` and saves it if `f` returns `true`.
+    /// An exclusive lock is held meanwhile so concurrent pixi processes don't
+    /// overwrite each other's changes.
+    fn update(path: &Path, f: impl FnOnce(&mut Self) -> bool) -> miette::Result<Self> {
+        let dir = path
+            .parent()
+            .expect("the registry is in the cache directory");
+        fs::create_dir_all(dir).into_diagnostic()?;
+        let lock_path = path.with_extension("lock");
+        let mut lock = fd_lock::RwLock::new(
+            std::fs::File::options()
+                .write(true)
+                .read(true)
+                .create(true)
+                .truncate(false)
+                .open(&lock_path)
+                .into_diagnostic()
+                .with_context(|| format!("failed to open {}", lock_path.display()))?,
+        );
+        let _guard = lock
+            .write()
+            .into_diagnostic()
+            .with_context(|| format!("failed to lock {}", lock_path.display()))?;
+
+        let mut registry = Self::load(path)?;
+        if f(&mut registry) {
+            registry.save(path)?;
+        }
+        Ok(registry)
+    }
+}
+
+/// Registers the lock-file of a project as a user of the cache. The registry
+/// is only locked and written when the lock-file is not registered yet.
+/// Failures are only logged because the registry is not needed to use the
+/// cache.
+pub(crate) fn register_lock_file(lock_file: &Path) {
+    let register = || -> miette::Result<()> {
+        let path = CacheRegistry::path()?;
+        if CacheRegistry::load(&path).is_ok_and(|registry| registry.lock_files.contains(lock_file))
+        {
+            return Ok(());
+        }
+        CacheRegistry::update(&path, |registry| {
+            registry.lock_files.insert(lock_file.to_path_buf())
+        })?;
+        Ok(())
+    };
+    if let Err(e) = register() {
+        tracing::debug!(
+            "failed to register {} as a user of the cache: {e}",
+            lock_file.display()
+        );
+    }
+}
+
+/// Returns the registered lock-files that still exist, the others are removed
+/// from the registry.
+pub(crate) fn registered_lock_files() -> miette::Result<Vec<PathBuf>> {
+    let registry = CacheRegistry::update(&CacheRegistry::path()?, |registry| {
+        let registered = registry.lock_files.len();
+        registry.lock_files.retain(|lock_file| lock_file.is_file());
+        registry.lock_files.len() != registered
+    })?;
+    Ok(registry.lock_files.into_iter().collect())
+}
+
+/// The cache entries that are referenced by a set of lock-files.
+#[derive(Debug, Default)]
+pub(crate) struct CacheReferences {
+    /// The file names of the conda packages without the archive extension.
+    packages: HashSet<String>,
+    /// The urls of the channel subdirectories, without a trailing slash.
+    subdirs: HashSet<String>,
+}
+
+impl CacheReferences {
+    pub(crate) fn from_lock_files(lock_files: &[PathBuf]) -> miette::Result<Self> {
+        let mut references = Self::default();
+        for path in lock_files {
+            let lock_file = LockFile::from_path(path)
+                .into_diagnostic()
+                .with_context(|| format!("failed to load lock file from `{}`", path.display()))?;
+            references.add_lock_file(&lock_file);
+        }
+        Ok(references)
+    }
+
+    fn add_lock_file(&mut self, lock_file: &LockFile) {
+        for (_, environment) in lock_file.environments() {
+            for (platform, packages) in environment.packages_by_platform() {
+                for channel in environment.channels() {
+                    let channel = channel.url.trim_end_matches('/');
+                    self.subdirs.insert(format!("{channel}/{platform}"));
+                    self.subdirs.insert(format!("{channel}/noarch"));
+                }
+                for package in packages {
+                    let Package::Conda(package) = package else {
+                        continue;
+                    };
+                    let Some(file_name) = package.url().path_segments().and_then(|s| s.last())
+                    else {
+                        continue;
+                    };
+                    if let Some((stem, _)) = ArchiveType::split_str(file_name) {
+                        self.packages.insert(stem.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns true if the package cache entry with the given name, the file
+    /// name of the package without the archive extension, is referenced.
+    pub(crate) fn uses_package(&self, name: &str) -> bool {
+        self.packages.contains(name)
+    }
+
+    /// Returns true if the repodata with the given url is referenced.
+    pub(crate) fn uses_repodata(&self, url: &str) -> bool {
+        url.rsplit_once('/')
+            .is_some_and(|(subdir, _)| self.subdirs.contains(subdir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_cache_references() {
+        let lock_file =
+            LockFile::from_str(include_str!("./global/test_data/lockfiles/ripgrep.lock")).unwrap();
+        let mut references = CacheReferences::default();
+        references.add_lock_file(&lock_file);
+
+        assert!(references.uses_package("ripgrep-14.1.0-he8a937b_0"));
+        assert!(references.uses_package("_libgcc_mutex-0.1-conda_forge"));
+        assert!(!references.uses_package("ripgrep-14.1.0-he8a937b_1"));
+        assert!(references
+            .uses_repodata("https://conda.anaconda.org/conda-forge/linux-64/repodata.json"));
+        assert!(
+            references.uses_repodata("https://conda.anaconda.org/conda-forge/noarch/repodata.json")
+        );
+        assert!(!references
+            .uses_repodata("https://conda.anaconda.org/conda-forge/osx-64/repodata.json"));
+        assert!(
+            !references.uses_repodata("https://conda.anaconda.org/bioconda/linux-64/repodata.json")
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use pixi_manifest::IgnoredVulnerability;
+use rattler_conda_types::Platform;
+
+use crate::{
+    audit::{Vulnerability, VulnerabilityDatabase},
+    cli::cli_config::ProjectConfig,
+    lock_file::{load_lock_file, PypiPackageIdentifier},
+    Project,
+};
+
+/// Audit the locked packages of the project
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Parser, Debug)]
+enum Command {
+    /// Match the locked conda and PyPI packages against a local database of
+    /// vulnerabilities in the OSV format
+    Vulnerabilities(VulnerabilitiesArgs),
+}
+
+#[derive(Parser, Debug)]
+struct VulnerabilitiesArgs {
+    #[clap(flatten)]
+    project_config: ProjectConfig,
+
+    /// The directory with the vulnerabilities in the OSV format, e.g. an
+    /// extracted export of the PyPI ecosystem of osv.dev
+    #[arg(long)]
+    db: PathBuf,
+
+    /// The environment to audit, defaults to all environments
+    #[arg(long, short)]
+    environment: Option<String>,
+
+    /// The platform to audit, defaults to all platforms of the environment
+    #[arg(long, short)]
+    platform: Option<Platform>,
+}
+
+/// A vulnerability of a locked package.
+struct Finding {
+    package: String,
+    version: String,
+    kind: &'static str,
+    vulnerability: Vulnerability,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Command::Vulnerabilities(args) => execute_vulnerabilities(args).await,
+    }
+}
+
+async fn execute_vulnerabilities(args: VulnerabilitiesArgs) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?;
+    if !project.lock_file_path().is_file() {
+        miette::bail!(
+            help = "create it with `pixi install`",
+            "the project doesn't have a lock file to audit"
+        );
+    }
+    let lock_file = load_lock_file(&project).await?;
+    let database = VulnerabilityDatabase::from_dir(&args.db)?;
+
+    let environments = match args.environment {
+        Some(name) => vec![project.environment_from_name_or_env_var(Some(name))?],
+        None => project.environments(),
+    };
+
+    let audit = &project.manifest().parsed.audit;
+    let today = chrono::Local::now().date_naive();
+    let mut packages = 0;
+    let mut reported = 0;
+    let mut ignored = 0;
+    let mut expired: Vec<&IgnoredVulnerability> = Vec::new();
+    for environment in environments {
+        let Some(locked_environment) = lock_file.environment(environment.name().as_str()) else {
+            miette::bail!(
+                help = "update the lock file with `pixi install`",
+                "the environment {} is not in the lock file",
+                environment.name().fancy_display()
+            );
+        };
+
+        for platform in environment
+            .platforms()
+            .into_iter()
+            .sorted_by_key(|platform| platform.as_str())
+        {
+            if args.platform.is_some_and(|p| p != platform) {
+                continue;
+            }
+
+            let mut findings = Vec::new();
+            for record in locked_environment
+                .conda_repodata_records_for_platform(platform)
+                .into_diagnostic()?
+                .unwrap_or_default()
+            {
+                packages += 1;
+                // Conda packages are matched by the PyPI packages they provide.
+                for identifier in PypiPackageIdentifier::from_record(&record).into_diagnostic()? {
+                    for vulnerability in database
+                        .vulnerabilities(identifier.name.as_normalized(), &identifier.version)
+                    {
+                        findings.push(Finding {
+                            package: record.package_record.name.as_normalized().to_string(),
+                            version: record.package_record.version.to_string(),
+                            kind: "conda",
+                            vulnerability,
+                        });
+                    }
+                }
+            }
+            for (package, _) in locked_environment
+                .pypi_packages_for_platform(platform)
+                .unwrap_or_default()
+            {
+                packages += 1;
+                for vulnerability in database.vulnerabilities(&package.name, &package.version) {
+                    findings.push(Finding {
+                        package: package.name.to_string(),
+                        version: package.version.to_string(),
+                        kind: "pypi",
+                        vulnerability,
+                    });
+                }
+            }
+
+            findings.retain(
+                |finding| match audit.ignored(finding.vulnerability.advisory.ids()) {
+                    Some(entry) if entry.is_expired(today) => {
+                        if !expired.contains(&entry) {
+                            expired.push(entry);
+                        }
+                        true
+                    }
+                    Some(_) => {
+                        ignored += 1;
+                        false
+                    }
+                    None => true,
+                },
+            );
+            if findings.is_empty() {
+                continue;
+            }
+
+            reported += findings.len();
+            report_findings(
+                &environment.name().fancy_display().to_string(),
+                platform,
+                &findings,
+            );
+        }
+    }
+
+    for entry in expired {
+        tracing::warn!(
+            "the ignore entry of {} expired on {}, it is reported again",
+            entry.id,
+            entry
+                .expires
+                .map(|expires| expires.to_string())
+                .unwrap_or_default()
+        );
+    }
+    if ignored > 0 {
+        eprintln!(
+            "Ignored {ignored} {} listed in the manifest",
+            if ignored == 1 {
+                "vulnerability"
+            } else {
+                "vulnerabilities"
+            }
+        );
+    }
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    if reported > 0 {
+        miette::bail!(
+            help = "update the affected packages or ignore the vulnerabilities in the `[audit]` table of the manifest",
+            "found {reported} {} in the locked packages",
+            if reported == 1 { "vulnerability" } else { "vulnerabilities" }
+        );
+    }
+
+    eprintln!(
+        "{}No known vulnerabilities in {packages} locked packages, checked against {} vulnerabilities",
+        console::style(console::Emoji("✔ ", "")).green(),
+        database.advisory_count()
+    );
+    Ok(())
+}
+
+fn report_findings(environment: &str, platform: Platform, findings: &[Finding]) {
+    eprintln!(
+        "{}{} {} in {environment} ({platform}):",
+        console::style(console::Emoji("✘ ", "")).red(),
+        findings.len(),
+        if findings.len() == 1 {
+            "vulnerability"
+        } else {
+            "vulnerabilities"
+        }
+    );
+    for finding in findings {
+        let advisory = &finding.vulnerability.advisory;
+        eprintln!(
+            "  {} {} ({}): {}{}",
+            console::style(&finding.package).bold(),
+            finding.version,
+            finding.kind,
+            console::style(&advisory.id).red(),
+            advisory
+                .summary
+                .as_deref()
+                .map(|summary| format!(" {summary}"))
+                .unwrap_or_default()
+        );
+        if !advisory.aliases.is_empty() {
+            eprintln!("    aliases: {}", advisory.aliases.iter().join(", "));
+        }
+        if finding.vulnerability.fixed.is_empty() {
+            eprintln!("    no fixed version is known");
+        } else {
+            eprintln!(
+                "    fixed in: {}",
+                finding.vulnerability.fixed.iter().join(", ")
+            );
+        }
+    }
+}
@@ -3,16 +3,21 @@ use crate::Project;
 use pixi_config;
 use pixi_consts::consts;
 use pixi_manifest::EnvironmentName;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
+use crate::cache_registry::{self, CacheReferences};
 use crate::cli::cli_config::ProjectConfig;
+use crate::environment_store::EnvironmentStore;
 use clap::Parser;
 use fancy_display::FancyDisplay;
 use fs_err::tokio as tokio_fs;
+use human_bytes::human_bytes;
 use indicatif::ProgressBar;
 use miette::IntoDiagnostic;
 use pixi_progress::{global_multi_progress, long_running_progress_style};
+use rattler_conda_types::package::ArchiveType;
 use std::str::FromStr;
 
 #[derive(Parser, Debug)]
@@ -20,6 +25,8 @@ use std::str::FromStr;
 pub enum Command {
     #[clap(name = "cache")]
     Cache(CacheArgs),
+    #[clap(name = "store")]
+    Store(StoreArgs),
 }
 
 /// Clean the parts of your system which are touched by pixi.
@@ -65,18 +72,61 @@ pub struct CacheArgs {
     #[arg(long)]
     pub repodata: bool,
 
+    /// Only remove the conda packages and repodata that are not used by the
+    /// lock-file of any project that used the cache. Can be combined with
+    /// `--conda` and `--repodata`.
+    #[arg(long, conflicts_with_all = ["pypi", "mapping", "exec"])]
+    pub unused: bool,
+
+    /// Only remove unused cache entries that were not modified for at least
+    /// this long, e.g. `30d`, `12h` or `2w`.
+    #[arg(long, requires = "unused", value_parser = parse_duration)]
+    pub older_than: Option<Duration>,
+
+    /// Only show which unused cache entries would be removed and how much
+    /// space that would reclaim.
+    #[arg(long, requires = "unused")]
+    pub dry_run: bool,
+
     /// Answer yes to all questions.
     #[clap(short = 'y', long = "yes", alias = "assume-yes")]
     assume_yes: bool,
-    // TODO: Would be amazing to have a --unused flag to clean only the unused cache.
-    //       By searching the inode count of the packages and removing based on that.
-    // #[arg(long)]
-    // pub unused: bool,
+}
+
+/// Remove the environments in the shared environment store that are no longer
+/// linked from any project.
+#[derive(Parser, Debug)]
+pub struct StoreArgs {
+    /// Only show which environments would be removed and how much space that
+    /// would reclaim.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Parses a duration like `30d`, `12h`, `45m`, `90s` or `2w`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("'{value}' is missing a unit, e.g. `30d`"))?;
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("'{value}' doesn't start with a number"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{unit}', use one of s, m, h, d or w")),
+    };
+    Ok(Duration::from_secs(amount * seconds))
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
     match args.command {
         Some(Command::Cache(args)) => clean_cache(args).await?,
+        Some(Command::Store(args)) => clean_store(args).await?,
         None => {
             let project =
                 Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?; // Extract the passed in environment name.
@@ -136,6 +186,10 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
 /// Clean the pixi cache folders.
 async fn clean_cache(args: CacheArgs) -> miette::Result<()> {
+    if args.unused {
+        return clean_unused_cache(args).await;
+    }
+
     let cache_dir = pixi_config::get_cache_dir()?;
     let mut dirs = vec![];
 
@@ -174,6 +228,243 @@ async fn clean_cache(args: CacheArgs) -> miette::Result<()> {
     Ok(())
 }
 
+/// An entry in the cache that can be removed, the files of a cache entry share
+/// the same name up to the first `.`.
+struct UnusedCacheEntry {
+    paths: Vec<PathBuf>,
+    size: u64,
+}
+
+/// Removes the conda packages and repodata that are not referenced by any of
+/// the lock-files that are registered as users of the cache.
+async fn clean_unused_cache(args: CacheArgs) -> miette::Result<()> {
+    let cache_dir = pixi_config::get_cache_dir()?;
+    let lock_files = cache_registry::registered_lock_files()?;
+    if lock_files.is_empty() {
+        miette::bail!(
+            help = "projects are registered when their lock-file is updated or installed, e.g. by `pixi install`",
+            "no projects are registered as users of the cache, so all cache entries would be unused"
+        );
+    }
+
+    let cutoff = args
+        .older_than
+        .map(|older_than| SystemTime::now() - older_than);
+    let clean_all = !args.conda && !args.repodata;
+    let (packages, repodata) = tokio::task::spawn_blocking(move || {
+        let references = CacheReferences::from_lock_files(&lock_files)?;
+        let packages = if args.conda || clean_all {
+            unused_cache_entries(
+                &cache_dir.join(consts::CONDA_PACKAGE_CACHE_DIR),
+                cutoff,
+                |name, _| references.uses_package(name),
+            )?
+        } else {
+            Vec::new()
+        };
+        let repodata = if args.repodata || clean_all {
+            unused_cache_entries(
+                &cache_dir.join(consts::CONDA_REPODATA_CACHE_DIR),
+                cutoff,
+                // Keep the entries of which the url is unknown.
+                |_, paths| match repodata_url(paths) {
+                    Some(url) => references.uses_repodata(&url),
+                    None => true,
+                },
+            )?
+        } else {
+            Vec::new()
+        };
+        Ok::<_, miette::Report>((packages, repodata))
+    })
+    .await
+    .into_diagnostic()??;
+
+    let package_size: u64 = packages.iter().map(|entry| entry.size).sum();
+    let repodata_size: u64 = repodata.iter().map(|entry| entry.size).sum();
+    let summary = format!(
+        "{} unused package{} ({}) and {} unused repodata entr{} ({})",
+        packages.len(),
+        if packages.len() == 1 { "" } else { "s" },
+        human_bytes(package_size as f64),
+        repodata.len(),
+        if repodata.len() == 1 { "y" } else { "ies" },
+        human_bytes(repodata_size as f64),
+    );
+
+    if args.dry_run {
+        for path in packages
+            .iter()
+            .chain(repodata.iter())
+            .flat_map(|entry| entry.paths.iter())
+        {
+            println!("{}", path.display());
+        }
+        eprintln!(
+            "Would remove {summary}, reclaiming {}",
+            human_bytes((package_size + repodata_size) as f64)
+        );
+        return Ok(());
+    }
+
+    for path in packages
+        .iter()
+        .chain(repodata.iter())
+        .flat_map(|entry| entry.paths.iter())
+    {
+        let result = if path.is_dir() {
+            tokio_fs::remove_dir_all(path).await
+        } else {
+            tokio_fs::remove_file(path).await
+        };
+        if let Err(e) = result {
+            tracing::info!("Failed to remove {:?}: {}", path, e);
+        }
+    }
+
+    eprintln!(
+        "{}Removed {summary}, reclaiming {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        human_bytes((package_size + repodata_size) as f64)
+    );
+    Ok(())
+}
+
+async fn clean_store(args: StoreArgs) -> miette::Result<()> {
+    let config = pixi_config::Config::load_global();
+    let store = EnvironmentStore::new(config.detached_environments().store_path()?);
+    let unused = store
+        .environments()?
+        .into_iter()
+        .filter(|environment| environment.references.is_empty())
+        .collect::<Vec<_>>();
+
+    if unused.is_empty() {
+        eprintln!(
+            "{}",
+            console::style(format!(
+                "No unused environments in the store at {}",
+                store.root().display()
+            ))
+            .yellow()
+        );
+        return Ok(());
+    }
+
+    if args.dry_run {
+        let size: u64 = unused
+            .iter()
+            .map(|environment| disk_size(&environment.path))
+            .sum();
+        for environment in &unused {
+            eprintln!("  {}", environment.path.display());
+        }
+        eprintln!(
+            "Would remove {} unused environment{}, reclaiming {}",
+            unused.len(),
+            if unused.len() == 1 { "" } else { "s" },
+            human_bytes(size as f64)
+        );
+        return Ok(());
+    }
+
+    let mut removed = 0;
+    let mut reclaimed = 0;
+    for environment in &unused {
+        let environment_size = disk_size(&environment.path);
+        match store.remove(environment) {
+            Ok(()) => {
+                removed += 1;
+                reclaimed += environment_size;
+            }
+            Err(e) => tracing::warn!("Failed to remove {:?}: {}", environment.path, e),
+        }
+    }
+
+    eprintln!(
+        "{}Removed {removed} unused environment{}, reclaiming {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        if removed == 1 { "" } else { "s" },
+        human_bytes(reclaimed as f64)
+    );
+    Ok(())
+}
+
+/// Groups the files in a cache directory by their name up to the first `.`
+/// and returns the groups that are not used and, if a cutoff is given, were
+/// not modified after it.
+fn unused_cache_entries(
+    dir: &Path,
+    cutoff: Option<SystemTime>,
+    is_used: impl Fn(&str, &[PathBuf]) -> bool,
+) -> miette::Result<Vec<UnusedCacheEntry>> {
+    let mut entries: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for entry in fs_err::read_dir(dir).into_iter().flatten() {
+        let path = entry.into_diagnostic()?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        // Package directories contain the version, which contains dots.
+        let name = if path.is_dir() {
+            file_name
+        } else {
+            ArchiveType::split_str(file_name)
+                .map(|(stem, _)| stem)
+                .or_else(|| file_name.strip_suffix(".lock"))
+                .or_else(|| file_name.split_once('.').map(|(stem, _)| stem))
+                .unwrap_or(file_name)
+        };
+        entries.entry(name.to_string()).or_default().push(path);
+    }
+
+    let mut unused = Vec::new();
+    for (name, paths) in entries {
+        if is_used(&name, &paths) {
+            continue;
+        }
+        if let Some(cutoff) = cutoff {
+            let modified_after_cutoff = paths.iter().any(|path| {
+                fs_err::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .map_or(true, |modified| modified > cutoff)
+            });
+            if modified_after_cutoff {
+                continue;
+            }
+        }
+        let size = paths.iter().map(|path| disk_size(path)).sum();
+        unused.push(UnusedCacheEntry { paths, size });
+    }
+    Ok(unused)
+}
+
+/// Reads the url of the cached repodata from its `.info.json` file.
+fn repodata_url(paths: &[PathBuf]) -> Option<String> {
+    let info = paths.iter().find(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(".info.json"))
+    })?;
+    let info: serde_json::Value = serde_json::from_str(&fs_err::read_to_string(info).ok()?).ok()?;
+    info.get("url")?.as_str().map(str::to_string)
+}
+
+/// Returns the size of a file, or of all the files in a directory.
+fn disk_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs_err::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs_err::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| disk_size(&entry.path()))
+        .sum()
+}
+
 async fn remove_folder_with_progress(
     folder: PathBuf,
     warning_non_existent: bool,
@@ -230,3 +521,25 @@ async fn remove_file(file: PathBuf, warning_non_existent: bool) -> miette::Resul
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(
+            parse_duration("30d"),
+            Ok(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("2w"),
+            Ok(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("30y").is_err());
+    }
+}
@@ -3,10 +3,13 @@ use crate::Project;
 use clap::Parser;
 use miette::{IntoDiagnostic, WrapErr};
 use pixi_config;
-use pixi_config::Config;
+use pixi_config::{Config, ConfigOrigin};
 use pixi_consts::consts;
 use rattler_conda_types::NamedChannelOrUrl;
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 #[derive(Parser, Debug)]
 enum Subcommand {
@@ -83,6 +86,10 @@ struct ListArgs {
     #[arg(long)]
     json: bool,
 
+    /// Show where each configuration value was loaded from
+    #[arg(long)]
+    show_origin: bool,
+
     #[clap(flatten)]
     common: CommonArgs,
 }
@@ -164,13 +171,16 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             child.wait().into_diagnostic()?;
         }
         Subcommand::List(args) => {
-            let mut config = load_config(&args.common)?;
+            let layers = load_config_layers(&args.common)?;
+            let mut config = Config::merge_layers(layers.clone());
 
             if let Some(key) = args.key {
                 partial_config(&mut config, &key)?;
             }
 
-            let out = if args.json {
+            let out = if args.show_origin {
+                format_with_origin(&config, &layers, args.json)?
+            } else if args.json {
                 serde_json::to_string_pretty(&config).into_diagnostic()?
             } else {
                 toml_edit::ser::to_string_pretty(&config).into_diagnostic()?
@@ -210,18 +220,89 @@ fn determine_project_root(common_args: &CommonArgs) -> miette::Result<Option<Pat
     }
 }
 
-fn load_config(common_args: &CommonArgs) -> miette::Result<Config> {
-    let ret = if common_args.system {
-        Config::load_system()
-    } else if common_args.global {
-        Config::load_global()
-    } else if let Some(root) = determine_project_root(common_args)? {
-        Config::load(&root)
+fn load_config_layers(common_args: &CommonArgs) -> miette::Result<Vec<(ConfigOrigin, Config)>> {
+    if common_args.system {
+        return Ok(Config::try_load_system()
+            .ok()
+            .map(|config| {
+                (
+                    ConfigOrigin::System(pixi_config::config_path_system()),
+                    config,
+                )
+            })
+            .into_iter()
+            .collect());
+    }
+
+    let project_root = if common_args.global {
+        None
     } else {
-        Config::load_global()
+        determine_project_root(common_args)?
     };
+    Config::try_load_layers(project_root.as_deref())
+}
 
-    Ok(ret)
+/// Loads only the config file that is altered, so that the values of the other
+/// layers and the environment variables aren't written to it.
+fn load_config(path: &Path) -> miette::Result<Config> {
+    if path.is_file() {
+        Config::from_path(path)
+    } else {
+        Ok(Config::default())
+    }
+}
+
+/// Formats every key of the config together with the layers that set it, the
+/// last layer takes precedence.
+fn format_with_origin(
+    config: &Config,
+    layers: &[(ConfigOrigin, Config)],
+    json: bool,
+) -> miette::Result<String> {
+    let to_map = |config: &Config| match serde_json::to_value(config) {
+        Ok(serde_json::Value::Object(map)) => Ok(map),
+        Ok(_) => unreachable!("the config is always serialized as a map"),
+        Err(e) => Err(e).into_diagnostic(),
+    };
+    let layer_maps = layers
+        .iter()
+        .map(|(origin, config)| to_map(config).map(|map| (origin, map)))
+        .collect::<miette::Result<Vec<_>>>()?;
+
+    let mut json_out = serde_json::Map::new();
+    let mut toml_out = Vec::new();
+    for (key, value) in to_map(config)? {
+        let origins = layer_maps
+            .iter()
+            .filter(|(_, map)| map.contains_key(&key))
+            .map(|(origin, _)| origin.to_string())
+            .collect::<Vec<_>>();
+        let origins = if origins.is_empty() {
+            vec!["default".to_string()]
+        } else {
+            origins
+        };
+
+        if json {
+            json_out.insert(
+                key,
+                serde_json::json!({ "value": value, "origin": origins }),
+            );
+        } else {
+            let entry = serde_json::Map::from_iter([(key, value)]);
+            toml_out.push(format!(
+                "# from {}\n{}",
+                origins.join(", "),
+                toml_edit::ser::to_string_pretty(&entry).into_diagnostic()?
+            ));
+        }
+    }
+
+    if json {
+        serde_json::to_string_pretty(&json_out).into_diagnostic()
+    } else {
+        Ok(toml_out.join("\n"))
+    }
 }
 
 fn determine_config_write_path(common_args: &CommonArgs) -> miette::Result<PathBuf> {
@@ -258,8 +339,8 @@ fn alter_config(
     value: Option<String>,
     mode: AlterMode,
 ) -> miette::Result<()> {
-    let mut config = load_config(common_args)?;
     let to = determine_config_write_path(common_args)?;
+    let mut config = load_config(&to)?;
 
     match mode {
         AlterMode::Prepend | AlterMode::Append => {
@@ -279,6 +360,14 @@ fn alter_config(
                     }
                     config.default_channels = new_channels;
                 }
+                "env-passthrough" => {
+                    let input = value.expect("value must be provided");
+                    if is_prepend {
+                        config.env_passthrough.insert(0, input);
+                    } else {
+                        config.env_passthrough.push(input);
+                    }
+                }
                 "pypi-config.extra-index-urls" => {
                     let input = url::Url::parse(&value.expect("value must be provided"))
                         .map_err(|e| miette::miette!("Invalid URL: {}", e))?;
@@ -291,7 +380,11 @@ fn alter_config(
                     config.pypi_config.extra_index_urls = new_urls;
                 }
                 _ => {
-                    let list_keys = ["default-channels", "pypi-config.extra-index-urls"];
+                    let list_keys = [
+                        "default-channels",
+                        "env-passthrough",
+                        "pypi-config.extra-index-urls",
+                    ];
                     let msg_cmd = if is_prepend { "prepend" } else { "append" };
                     return Err(miette::miette!(
                         "{} is only supported for list keys: {}",
@@ -321,6 +414,12 @@ fn partial_config(config: &mut Config, key: &str) -> miette::Result<()> {
             new.authentication_override_file = config.authentication_override_file.clone()
         }
         "mirrors" => new.mirrors = config.mirrors.clone(),
+        "proxy" => new.proxy = config.proxy.clone(),
+        "ca-certificates" => new.ca_certificates = config.ca_certificates.clone(),
+        "client-certificate" => new.client_certificate = config.client_certificate.clone(),
+        "shared-environments" => new.shared_environments = config.shared_environments,
+        "offline" => new.offline = config.offline,
+        "env-passthrough" => new.env_passthrough = config.env_passthrough.clone(),
         "repodata-config" => new.repodata_config = config.repodata_config.clone(),
         "pypi-config" => new.pypi_config = config.pypi_config.clone(),
         _ => {
@@ -330,6 +429,12 @@ fn partial_config(config: &mut Config, key: &str) -> miette::Result<()> {
                 "tls-no-verify",
                 "authentication-override-file",
                 "mirrors",
+                "proxy",
+                "ca-certificates",
+                "client-certificate",
+                "shared-environments",
+                "offline",
+                "env-passthrough",
                 "repodata-config",
                 "pypi-config",
             ];
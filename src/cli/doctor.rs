@@ -0,0 +1,170 @@
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use pixi_config::ConfigCli;
+
+use crate::{
+    cli::cli_config::ProjectConfig,
+    environment::{get_update_lock_file_and_filtered_prefix, LockFileUsage},
+    lock_file::{InstallFilter, UpdateMode},
+    prefix::Prefix,
+    project::Environment,
+    verify::{remove_packages, verify_prefix, PackageDrift},
+    Project,
+};
+
+/// The number of drifted files that are listed per package.
+const MAX_LISTED_FILES: usize = 5;
+
+/// Diagnose problems with the project and its environments
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Parser, Debug)]
+enum Command {
+    /// Verify the files of the packages installed in an environment against
+    /// their metadata
+    Env(EnvArgs),
+}
+
+#[derive(Parser, Debug)]
+struct EnvArgs {
+    #[clap(flatten)]
+    project_config: ProjectConfig,
+
+    #[clap(flatten)]
+    lock_file_usage: super::LockFileUsageArgs,
+
+    /// The environment to verify
+    #[arg(long, short)]
+    environment: Option<String>,
+
+    /// Reinstall the packages of which the files don't match their metadata
+    #[arg(long)]
+    repair: bool,
+
+    #[clap(flatten)]
+    config: ConfigCli,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Command::Env(args) => execute_env(args).await,
+    }
+}
+
+async fn execute_env(args: EnvArgs) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
+        .with_cli_config(args.config);
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+
+    if !environment.dir().exists() {
+        miette::bail!(
+            help = "install it with `pixi install`",
+            "environment {} is not installed",
+            environment.name().fancy_display()
+        );
+    }
+
+    verify_environment(
+        &environment,
+        args.lock_file_usage.into(),
+        InstallFilter::default(),
+        args.repair,
+    )
+    .await?;
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Verifies the installed packages of an environment and reports the ones of
+/// which the files don't match their metadata. With `repair` these packages
+/// are reinstalled, otherwise an error is returned.
+pub(crate) async fn verify_environment(
+    environment: &Environment<'_>,
+    lock_file_usage: LockFileUsage,
+    filter: InstallFilter,
+    repair: bool,
+) -> miette::Result<()> {
+    let prefix = Prefix::new(environment.dir());
+    let drift = verify_prefix(&prefix).await?;
+    if drift.is_empty() {
+        eprintln!(
+            "{}The files of the {} environment match the package metadata.",
+            console::style(console::Emoji("✔ ", "")).green(),
+            environment.name().fancy_display()
+        );
+        return Ok(());
+    }
+
+    report_drift(environment, &drift);
+    if !repair {
+        miette::bail!(
+            help = "reinstall the affected packages with `--repair`",
+            "the files of {} package{} in the {} environment don't match their metadata",
+            drift.len(),
+            if drift.len() == 1 { "" } else { "s" },
+            environment.name().fancy_display()
+        );
+    }
+
+    // Remove the affected packages and let the update of the prefix install
+    // them again.
+    remove_packages(&prefix, &drift).await?;
+    get_update_lock_file_and_filtered_prefix(
+        environment,
+        lock_file_usage,
+        false,
+        UpdateMode::Revalidate,
+        filter,
+    )
+    .await?;
+
+    let remaining = verify_prefix(&prefix).await?;
+    if !remaining.is_empty() {
+        report_drift(environment, &remaining);
+        miette::bail!(
+            "the files of {} package{} in the {} environment still don't match their metadata after reinstalling",
+            remaining.len(),
+            if remaining.len() == 1 { "" } else { "s" },
+            environment.name().fancy_display()
+        );
+    }
+
+    eprintln!(
+        "{}Reinstalled {} package{} in the {} environment.",
+        console::style(console::Emoji("✔ ", "")).green(),
+        drift.len(),
+        if drift.len() == 1 { "" } else { "s" },
+        environment.name().fancy_display()
+    );
+    Ok(())
+}
+
+fn report_drift(environment: &Environment<'_>, drift: &[PackageDrift]) {
+    eprintln!(
+        "{}The files of the following packages in the {} environment don't match their metadata:",
+        console::style(console::Emoji("✘ ", "")).red(),
+        environment.name().fancy_display()
+    );
+    for package in drift {
+        eprintln!(
+            "  {} {} ({})",
+            console::style(&package.name).bold(),
+            package.version,
+            package.kind
+        );
+        for file in package.files.iter().take(MAX_LISTED_FILES) {
+            eprintln!("    {file}");
+        }
+        if package.files.len() > MAX_LISTED_FILES {
+            eprintln!(
+                "    ... and {} more",
+                package.files.len() - MAX_LISTED_FILES
+            );
+        }
+    }
+}
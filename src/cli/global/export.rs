@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use fs_err::tokio as tokio_fs;
+use miette::IntoDiagnostic;
+use pixi_config::{Config, ConfigCli};
+
+use crate::global::{EnvironmentName, GlobalExport, Project};
+
+/// Export the global environments to a portable file.
+///
+/// The file can be imported on another machine with `pixi global import`.
+///
+/// Example:
+/// - pixi global export tools.toml
+/// - pixi global export --locked --environment python tools.toml
+#[derive(Parser, Debug)]
+#[clap(verbatim_doc_comment)]
+pub struct Args {
+    /// The file to write the export to, prints to stdout if not given.
+    output: Option<PathBuf>,
+
+    /// The environments to export, defaults to all environments.
+    #[clap(short, long)]
+    environment: Vec<EnvironmentName>,
+
+    /// Also record the exact packages that are installed in the environments.
+    #[clap(long)]
+    locked: bool,
+
+    #[clap(flatten)]
+    config: ConfigCli,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let config = Config::with_cli_config(&args.config);
+    let project = Project::discover_or_create()
+        .await?
+        .with_cli_config(config.clone());
+
+    let export = GlobalExport::from_project(&project, &args.environment, args.locked).await?;
+    let contents = export.to_toml_string()?;
+
+    match args.output {
+        Some(output) => {
+            tokio_fs::write(&output, contents).await.into_diagnostic()?;
+            eprintln!(
+                "{}Exported {} global environment(s) to {}",
+                console::style(console::Emoji("✔ ", "")).green(),
+                export.envs.len(),
+                output.display()
+            );
+        }
+        None => print!("{contents}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use fs_err::tokio as tokio_fs;
+use miette::{Context, IntoDiagnostic};
+use pixi_config::{Config, ConfigCli};
+
+use crate::cli::global::revert_environment_after_error;
+use crate::global::{ConflictStrategy, GlobalExport, Project, StateChanges};
+
+/// Import global environments from a file created by `pixi global export`.
+///
+/// The environments are merged into the global manifest and installed.
+///
+/// Example:
+/// - pixi global import tools.toml
+/// - pixi global import --on-conflict overwrite --locked tools.toml
+#[derive(Parser, Debug)]
+#[clap(arg_required_else_help = true, verbatim_doc_comment)]
+pub struct Args {
+    /// The file to import.
+    input: PathBuf,
+
+    /// How to handle environments and exposed names that already exist.
+    #[arg(long, value_enum, default_value_t)]
+    on_conflict: ConflictStrategy,
+
+    /// Pin the dependencies to the packages recorded with `pixi global export --locked`.
+    #[clap(long)]
+    locked: bool,
+
+    #[clap(flatten)]
+    config: ConfigCli,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let config = Config::with_cli_config(&args.config);
+    let project_original = Project::discover_or_create()
+        .await?
+        .with_cli_config(config.clone());
+
+    let contents = tokio_fs::read_to_string(&args.input)
+        .await
+        .into_diagnostic()?;
+    let export = GlobalExport::from_str(&args.input, &contents)?;
+
+    async fn apply_changes(project: &mut Project) -> miette::Result<StateChanges> {
+        // The exposed names of other environments might have changed as well,
+        // so sync the whole installation instead of only the imported environments
+        let state_changes = project.sync().await?;
+
+        project.manifest.save().await?;
+
+        Ok(state_changes)
+    }
+
+    let mut project_modified = project_original.clone();
+    let channel_config = project_modified.config().global_channel_config().clone();
+    let env_names = export.merge_into(
+        &mut project_modified.manifest,
+        args.on_conflict,
+        args.locked,
+        &channel_config,
+    )?;
+
+    if env_names.is_empty() {
+        eprintln!(
+            "{}Nothing to import.",
+            console::style(console::Emoji("✔ ", "")).green()
+        );
+        return Ok(());
+    }
+
+    match apply_changes(&mut project_modified).await {
+        Ok(state_changes) => {
+            state_changes.report();
+            Ok(())
+        }
+        Err(err) => {
+            for env_name in &env_names {
+                revert_environment_after_error(env_name, &project_original)
+                    .await
+                    .wrap_err_with(|| {
+                        format!(
+                            "Couldn't import {}. Reverting also failed.",
+                            args.input.display()
+                        )
+                    })?;
+            }
+            Err(err)
+        }
+    }
+}
@@ -4,7 +4,9 @@ use crate::global::{self, EnvironmentName};
 
 mod add;
 mod edit;
+mod export;
 mod expose;
+mod import;
 mod install;
 mod list;
 mod remove;
@@ -31,6 +33,8 @@ pub enum Command {
     #[clap(visible_alias = "e")]
     #[command(subcommand)]
     Expose(expose::SubCommand),
+    Export(export::Args),
+    Import(import::Args),
     Update(update::Args),
     #[command(hide = true)]
     Upgrade(upgrade::Args),
@@ -61,6 +65,8 @@ pub async fn execute(cmd: Args) -> miette::Result<()> {
         Command::List(args) => list::execute(args).await?,
         Command::Sync(args) => sync::execute(args).await?,
         Command::Expose(subcommand) => expose::execute(subcommand).await?,
+        Command::Export(args) => export::execute(args).await?,
+        Command::Import(args) => import::execute(args).await?,
         Command::Update(args) => update::execute(args).await?,
         Command::Upgrade(args) => upgrade::execute(args).await?,
         Command::UpgradeAll(args) => upgrade_all::execute(args).await?,
@@ -1,6 +1,7 @@
 use crate::cli::cli_config::ProjectConfig;
-use crate::environment::get_update_lock_file_and_prefix;
-use crate::lock_file::UpdateMode;
+use crate::cli::doctor::verify_environment;
+use crate::environment::get_update_lock_file_and_filtered_prefix;
+use crate::lock_file::{InstallFilter, UpdateMode};
 use crate::Project;
 use clap::Parser;
 use fancy_display::FancyDisplay;
@@ -25,6 +26,30 @@ pub struct Args {
 
     #[arg(long, short, conflicts_with = "environment")]
     pub all: bool,
+
+    /// Only install the conda packages, the PyPI packages can be installed
+    /// later with `--only-pypi`
+    #[arg(long, conflicts_with = "only_pypi")]
+    pub skip_pypi: bool,
+
+    /// Only install the PyPI packages, the conda packages must already be
+    /// installed with `--skip-pypi`
+    #[arg(long)]
+    pub only_pypi: bool,
+
+    /// Don't install the editable PyPI packages, editable packages that are
+    /// already installed are kept
+    #[arg(long, conflicts_with = "skip_pypi")]
+    pub skip_editable: bool,
+
+    /// Verify the files of the installed packages against their metadata
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Reinstall the packages of which the files don't match their metadata,
+    /// implies `--verify`
+    #[arg(long)]
+    pub repair: bool,
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
@@ -48,19 +73,36 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         vec![project.default_environment().name().to_string()]
     };
 
+    let filter = InstallFilter {
+        skip_pypi: args.skip_pypi,
+        only_pypi: args.only_pypi,
+        skip_editable: args.skip_editable,
+    };
+
     let mut installed_envs = Vec::with_capacity(envs.len());
     for env in envs {
         let environment = project.environment_from_name_or_env_var(Some(env))?;
 
         // Update the prefix by installing all packages
-        get_update_lock_file_and_prefix(
+        get_update_lock_file_and_filtered_prefix(
             &environment,
             args.lock_file_usage.into(),
             false,
             UpdateMode::Revalidate,
+            filter,
         )
         .await?;
 
+        if args.verify || args.repair {
+            verify_environment(
+                &environment,
+                args.lock_file_usage.into(),
+                filter,
+                args.repair,
+            )
+            .await?;
+        }
+
         installed_envs.push(environment.name().clone());
     }
 
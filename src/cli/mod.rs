@@ -14,10 +14,12 @@ use pixi_progress::global_multi_progress;
 use pixi_utils::indicatif::IndicatifWriter;
 
 pub mod add;
+pub mod audit;
 pub mod clean;
 pub mod cli_config;
 pub mod completion;
 pub mod config;
+pub mod doctor;
 mod exec;
 pub mod global;
 pub mod has_specs;
@@ -25,6 +27,7 @@ pub mod info;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod outdated;
 pub mod project;
 pub mod remove;
 pub mod run;
@@ -37,6 +40,7 @@ pub mod tree;
 pub mod update;
 pub mod upgrade;
 pub mod upload;
+pub mod why;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -83,6 +87,10 @@ struct Args {
     /// Hide all progress bars, always turned on if stderr is not a terminal.
     #[clap(long, default_value = "false", global = true, env = "PIXI_NO_PROGRESS")]
     no_progress: bool,
+
+    /// Don't access the network, only use cached repodata and packages.
+    #[clap(long, default_value = "false", global = true, env = "PIXI_OFFLINE")]
+    offline: bool,
 }
 impl Args {
     /// Whether to show progress bars or not, based on the terminal and the user's preference.
@@ -108,6 +116,7 @@ pub enum Command {
     Install(install::Args),
     Update(update::Args),
     Upgrade(upgrade::Args),
+    Outdated(outdated::Args),
 
     #[clap(visible_alias = "r")]
     Run(run::Args),
@@ -126,6 +135,9 @@ pub enum Command {
     List(list::Args),
     #[clap(visible_alias = "t")]
     Tree(tree::Args),
+    Why(why::Args),
+    Doctor(doctor::Args),
+    Audit(audit::Args),
 
     // Global level commands
     #[clap(visible_alias = "g")]
@@ -194,6 +206,12 @@ pub async fn execute() -> miette::Result<()> {
     console::set_colors_enabled(use_colors);
     console::set_colors_enabled_stderr(use_colors);
 
+    // The config is loaded in many places, so pass the flag on through the
+    // environment where `Config::load_global` picks it up.
+    if args.offline {
+        env::set_var("PIXI_OFFLINE", "true");
+    }
+
     // Hide all progress bars if the user requested it.
     if args.no_progress() {
         global_multi_progress().set_draw_target(ProgressDrawTarget::hidden());
@@ -273,6 +291,8 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::Init(cmd) => init::execute(cmd).await,
         Command::Add(cmd) => add::execute(cmd).await,
         Command::Clean(cmd) => clean::execute(cmd).await,
+        Command::Doctor(cmd) => doctor::execute(cmd).await,
+        Command::Audit(cmd) => audit::execute(cmd).await,
         Command::Run(cmd) => run::execute(cmd).await,
         Command::Global(cmd) => global::execute(cmd).await,
         Command::Auth(cmd) => rattler::cli::auth::execute(cmd).await.into_diagnostic(),
@@ -291,8 +311,10 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::SelfUpdate(cmd) => self_update::execute_stub(cmd).await,
         Command::List(cmd) => list::execute(cmd).await,
         Command::Tree(cmd) => tree::execute(cmd).await,
+        Command::Why(cmd) => why::execute(cmd).await,
         Command::Update(cmd) => update::execute(cmd).await,
         Command::Upgrade(cmd) => upgrade::execute(cmd).await,
+        Command::Outdated(cmd) => outdated::execute(cmd).await,
         Command::Exec(args) => exec::execute(args).await,
     }
 }
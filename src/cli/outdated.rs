@@ -0,0 +1,488 @@
+use std::{
+    collections::HashMap,
+    io::{self, stdout, Write},
+    str::FromStr,
+};
+
+use clap::Parser;
+use console::Color;
+use fancy_display::FancyDisplay;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use pixi_config::ConfigCli;
+use pixi_consts::consts;
+use pixi_manifest::{EnvironmentName, FeaturesExt};
+use pixi_uv_conversions::{pypi_options_to_index_locations, to_uv_normalize};
+use rattler_conda_types::{Channel, MatchSpec, NamelessMatchSpec, PackageName, Platform, Version};
+use rattler_lock::{LockFile, Package, UrlOrPath};
+use serde::Serialize;
+use uv_client::{OwnedArchive, RegistryClientBuilder};
+
+use crate::{
+    cli::cli_config::ProjectConfig,
+    load_lock_file,
+    lock_file::{filter_lock_file, UpdateContext, UvResolutionContext},
+    project::Environment,
+    repodata::Repodata,
+    Project,
+};
+
+/// Show the locked packages for which a newer version is available
+///
+/// Packages that are direct dependencies are highlighted. The compatible
+/// version is the newest version allowed by the specs in the manifest, the
+/// latest version is the newest version in the channels or indexes. Neither
+/// the manifest nor the lock file is modified.
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[clap(flatten)]
+    pub config: ConfigCli,
+
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// The environments to check. If none is specified, all environments are
+    /// checked.
+    #[clap(long = "environment", short = 'e')]
+    pub environments: Option<Vec<EnvironmentName>>,
+
+    /// The platform to check. Defaults to the best platform of each
+    /// environment for the current system.
+    #[arg(long, short)]
+    pub platform: Option<Platform>,
+
+    /// Don't create the temporary (solve) environments needed for
+    /// pypi-dependencies solving. The environments of the project are never
+    /// modified.
+    #[arg(long)]
+    pub no_install: bool,
+
+    /// Also list the packages that are up-to-date.
+    #[arg(long)]
+    pub all: bool,
+
+    /// Output the report in JSON format.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Serialize)]
+struct EnvironmentReport {
+    environment: String,
+    platform: Platform,
+    packages: Vec<OutdatedPackage>,
+}
+
+#[derive(Serialize)]
+struct OutdatedPackage {
+    name: String,
+    kind: &'static str,
+    is_direct: bool,
+    locked: String,
+    /// The newest version allowed by the specs in the manifest, `None` if the
+    /// package is no longer part of the environment.
+    compatible: Option<String>,
+    /// The newest version available, `None` if it couldn't be determined.
+    latest: Option<String>,
+    #[serde(skip)]
+    is_outdated: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
+        .with_cli_config(args.config);
+
+    let environments = match &args.environments {
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                project.environment(name).ok_or_else(|| {
+                    miette::miette!(
+                        "could not find an environment named {}",
+                        name.fancy_display()
+                    )
+                })
+            })
+            .collect::<miette::Result<Vec<_>>>()?,
+        None => project.environments(),
+    };
+    let platforms: HashMap<EnvironmentName, Platform> = environments
+        .iter()
+        .map(|environment| {
+            (
+                environment.name().clone(),
+                args.platform.unwrap_or_else(|| environment.best_platform()),
+            )
+        })
+        .collect();
+
+    if !project.lock_file_path().is_file() {
+        miette::bail!(
+            help = "create it with `pixi install`",
+            "the project doesn't have a lock file"
+        );
+    }
+    let locked = load_lock_file(&project).await?;
+
+    // Solve the environments again without the locked versions, in memory
+    // only, to find the newest versions the specs allow. The python
+    // environments needed to solve pypi dependencies are created in a
+    // temporary directory.
+    let relaxed = filter_lock_file(&project, &locked, |environment, platform, _| {
+        platforms.get(environment.name()) != Some(&platform)
+    });
+    let solve_prefix_dir = tempfile::tempdir().into_diagnostic()?;
+    let compatible = UpdateContext::builder(&project)
+        .with_lock_file(relaxed)
+        .with_no_install(args.no_install)
+        .with_solve_prefix_dir(solve_prefix_dir.path().to_path_buf())
+        .finish()?
+        .update()
+        .await?
+        .lock_file;
+
+    let mut reports = Vec::new();
+    for environment in &environments {
+        let platform = platforms[environment.name()];
+        let mut packages =
+            outdated_packages(&project, environment, platform, &locked, &compatible).await?;
+        if !args.all {
+            packages.retain(|package| package.is_outdated);
+        }
+        reports.push(EnvironmentReport {
+            environment: environment.name().to_string(),
+            platform,
+            packages,
+        });
+    }
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&reports).into_diagnostic()?;
+        println!("{json}");
+    } else {
+        for (environment, report) in environments.iter().zip(&reports) {
+            if report.packages.is_empty() {
+                eprintln!(
+                    "{}The packages of {} ({}) are up-to-date",
+                    console::style(console::Emoji("✔ ", "")).green(),
+                    environment.name().fancy_display(),
+                    consts::PLATFORM_STYLE.apply_to(report.platform)
+                );
+                continue;
+            }
+            eprintln!(
+                "Environment: {} ({})",
+                environment.name().fancy_display(),
+                consts::PLATFORM_STYLE.apply_to(report.platform)
+            );
+            print_packages_as_table(&report.packages).into_diagnostic()?;
+        }
+    }
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Compares the locked packages of an environment with the packages of the
+/// relaxed solve and with the newest available versions.
+async fn outdated_packages(
+    project: &Project,
+    environment: &Environment<'_>,
+    platform: Platform,
+    locked: &LockFile,
+    compatible: &LockFile,
+) -> miette::Result<Vec<OutdatedPackage>> {
+    let locked_packages = locked
+        .environment(environment.name().as_str())
+        .and_then(|env| env.packages(platform).map(Vec::from_iter))
+        .unwrap_or_default();
+    // Conda and PyPI packages can have the same name.
+    let compatible_packages: HashMap<(bool, String), Package> = compatible
+        .environment(environment.name().as_str())
+        .and_then(|env| env.packages(platform).map(Vec::from_iter))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|package| {
+            (
+                (package.as_conda().is_some(), package.name().to_string()),
+                package,
+            )
+        })
+        .collect();
+
+    let conda_dependencies = environment.dependencies(None, Some(platform));
+    let pypi_dependencies = environment.pypi_dependencies(Some(platform));
+    let latest_conda =
+        latest_conda_versions(project, environment, platform, &locked_packages).await?;
+    let latest_pypi = latest_pypi_versions(project, environment, &locked_packages).await?;
+
+    let mut packages = Vec::new();
+    for package in &locked_packages {
+        let compatible =
+            compatible_packages.get(&(package.as_conda().is_some(), package.name().to_string()));
+        let package = match package {
+            Package::Conda(package) => {
+                let record = package.package_record();
+                let locked = &record.version;
+                let compatible = compatible
+                    .and_then(Package::as_conda)
+                    .map(|package| package.package_record().version.version().clone());
+                let latest = latest_conda.get(&record.name);
+                OutdatedPackage {
+                    is_outdated: compatible
+                        .as_ref()
+                        .is_some_and(|compatible| compatible > locked.version())
+                        || latest.is_some_and(|latest| latest > locked.version()),
+                    name: record.name.as_normalized().to_string(),
+                    kind: "conda",
+                    is_direct: conda_dependencies.contains_key(&record.name),
+                    locked: locked.to_string(),
+                    compatible: compatible.map(|version| version.to_string()),
+                    latest: latest.map(Version::to_string),
+                }
+            }
+            Package::Pypi(package) => {
+                let data = &package.data().package;
+                let compatible = compatible
+                    .and_then(Package::as_pypi)
+                    .map(|package| package.data().package.version.clone());
+                let latest = latest_pypi.get(&data.name);
+                OutdatedPackage {
+                    is_outdated: compatible
+                        .as_ref()
+                        .is_some_and(|compatible| compatible > &data.version)
+                        || latest.is_some_and(|latest| latest > &data.version),
+                    name: data.name.to_string(),
+                    kind: "pypi",
+                    is_direct: pypi_dependencies
+                        .names()
+                        .any(|dependency| dependency.as_normalized() == &data.name),
+                    locked: data.version.to_string(),
+                    compatible: compatible.map(|version| version.to_string()),
+                    latest: latest.map(ToString::to_string),
+                }
+            }
+        };
+        packages.push(package);
+    }
+
+    packages.sort_by(|a, b| (!a.is_direct, &a.name).cmp(&(!b.is_direct, &b.name)));
+    Ok(packages)
+}
+
+/// Returns the newest version of each locked conda package in the channels
+/// of the environment.
+async fn latest_conda_versions(
+    project: &Project,
+    environment: &Environment<'_>,
+    platform: Platform,
+    locked_packages: &[Package],
+) -> miette::Result<HashMap<PackageName, Version>> {
+    let specs = locked_packages
+        .iter()
+        .filter_map(Package::as_conda)
+        .map(|package| {
+            MatchSpec::from_nameless(
+                NamelessMatchSpec::default(),
+                Some(package.package_record().name.clone()),
+            )
+        })
+        .collect_vec();
+    if specs.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let channel_config = project.channel_config();
+    let channels: Vec<Channel> = environment
+        .channels()
+        .into_iter()
+        .map(|channel| channel.clone().into_channel(&channel_config))
+        .try_collect()
+        .into_diagnostic()?;
+    let repodata = project
+        .repodata_gateway()?
+        .query(channels, [platform, Platform::NoArch], specs)
+        .recursive(false)
+        .await
+        .into_diagnostic()?;
+
+    let mut latest: HashMap<PackageName, Version> = HashMap::new();
+    for record in repodata.iter().flat_map(|repodata| repodata.iter()) {
+        let record = &record.package_record;
+        let version = record.version.version();
+        match latest.get(&record.name) {
+            Some(latest) if latest >= version => {}
+            _ => {
+                latest.insert(record.name.clone(), version.clone());
+            }
+        }
+    }
+    Ok(latest)
+}
+
+/// Returns the newest final release of each locked PyPI package on the
+/// indexes of the environment. Packages from a path or git repository are
+/// skipped.
+async fn latest_pypi_versions(
+    project: &Project,
+    environment: &Environment<'_>,
+    locked_packages: &[Package],
+) -> miette::Result<HashMap<pep508_rs::PackageName, pep440_rs::Version>> {
+    let names = locked_packages
+        .iter()
+        .filter_map(Package::as_pypi)
+        .filter(|package| match &package.data().package.url_or_path {
+            UrlOrPath::Url(url) => !url.scheme().starts_with("git+"),
+            UrlOrPath::Path(_) => false,
+        })
+        .map(|package| package.data().package.name.clone())
+        .collect_vec();
+    if names.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let uv_context = UvResolutionContext::from_project(project)?;
+    let index_locations =
+        pypi_options_to_index_locations(&environment.pypi_options(), project.root())
+            .into_diagnostic()?;
+    let registry_client = RegistryClientBuilder::new(uv_context.cache.clone())
+        .client(uv_context.client.clone())
+        .index_urls(index_locations.index_urls())
+        .keyring(uv_context.keyring_provider)
+        .connectivity(uv_context.connectivity)
+        .build();
+
+    let mut latest = HashMap::new();
+    for name in names {
+        let uv_name = to_uv_normalize(&name).into_diagnostic()?;
+        let metadata = match registry_client
+            .simple(&uv_name, None, &uv_context.capabilities)
+            .await
+        {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                tracing::debug!("failed to find the versions of {name} on the indexes: {err}");
+                continue;
+            }
+        };
+        let newest = metadata
+            .iter()
+            .flat_map(|(_, archive)| {
+                OwnedArchive::deserialize(archive)
+                    .iter()
+                    .map(|datum| datum.version.clone())
+                    .collect_vec()
+            })
+            .filter(|version| !version.any_prerelease())
+            .max();
+        if let Some(version) =
+            newest.and_then(|version| pep440_rs::Version::from_str(&version.to_string()).ok())
+        {
+            latest.insert(name, version);
+        }
+    }
+    Ok(latest)
+}
+
+fn print_packages_as_table(packages: &[OutdatedPackage]) -> io::Result<()> {
+    let mut writer = tabwriter::TabWriter::new(stdout());
+
+    let header_style = console::Style::new().bold();
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}\t{}",
+        header_style.apply_to("Package"),
+        header_style.apply_to("Kind"),
+        header_style.apply_to("Dependency"),
+        header_style.apply_to("Locked"),
+        header_style.apply_to("Compatible"),
+        header_style.apply_to("Latest"),
+    )?;
+
+    for package in packages {
+        if package.is_direct {
+            write!(
+                writer,
+                "{}",
+                console::style(&package.name).fg(Color::Green).bold()
+            )?
+        } else {
+            write!(writer, "{}", &package.name)?;
+        }
+
+        writeln!(
+            writer,
+            "\t{}\t{}\t{}\t{}\t{}",
+            package.kind,
+            if package.is_direct {
+                "direct"
+            } else {
+                "transitive"
+            },
+            package.locked,
+            package.compatible.as_deref().unwrap_or("removed"),
+            package.latest.as_deref().unwrap_or("unknown"),
+        )?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_report() {
+        let reports = vec![EnvironmentReport {
+            environment: "default".to_string(),
+            platform: Platform::Linux64,
+            packages: vec![
+                OutdatedPackage {
+                    name: "python".to_string(),
+                    kind: "conda",
+                    is_direct: true,
+                    locked: "3.11.9".to_string(),
+                    compatible: Some("3.11.10".to_string()),
+                    latest: Some("3.13.0".to_string()),
+                    is_outdated: true,
+                },
+                OutdatedPackage {
+                    name: "flask".to_string(),
+                    kind: "pypi",
+                    is_direct: false,
+                    locked: "2.3.0".to_string(),
+                    compatible: None,
+                    latest: None,
+                    is_outdated: false,
+                },
+            ],
+        }];
+
+        assert_eq!(
+            serde_json::to_value(&reports).unwrap(),
+            serde_json::json!([{
+                "environment": "default",
+                "platform": "linux-64",
+                "packages": [
+                    {
+                        "name": "python",
+                        "kind": "conda",
+                        "is_direct": true,
+                        "locked": "3.11.9",
+                        "compatible": "3.11.10",
+                        "latest": "3.13.0",
+                    },
+                    {
+                        "name": "flask",
+                        "kind": "pypi",
+                        "is_direct": false,
+                        "locked": "2.3.0",
+                        "compatible": null,
+                        "latest": null,
+                    },
+                ],
+            }])
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 pub mod conda_environment;
 pub mod conda_explicit_spec;
+pub mod pack;
 
 use crate::Project;
 use clap::Parser;
@@ -23,13 +24,27 @@ pub enum Command {
     CondaExplicitSpec(conda_explicit_spec::Args),
     /// Export project environment to a conda environment.yaml file
     CondaEnvironment(conda_environment::Args),
+    /// Pack a locked environment into an archive that can be installed
+    /// without pixi or network access
+    Pack(pack::Args),
+    /// Install an unpacked archive into a prefix, used by the unpack scripts
+    #[clap(hide = true)]
+    Unpack(pack::UnpackArgs),
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
+    // Unpacking doesn't need a project.
+    let command = match args.command {
+        Command::Unpack(args) => return pack::unpack(args).await,
+        command => command,
+    };
+
     let project = Project::load_or_else_discover(args.manifest_path.as_deref())?;
-    match args.command {
+    match command {
         Command::CondaExplicitSpec(args) => conda_explicit_spec::execute(project, args).await?,
         Command::CondaEnvironment(args) => conda_environment::execute(project, args).await?,
+        Command::Pack(args) => pack::execute(project, args).await?,
+        Command::Unpack(_) => unreachable!("handled above"),
     };
     Ok(())
 }
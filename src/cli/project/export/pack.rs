@@ -0,0 +1,563 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use flate2::{write::GzEncoder, Compression};
+use miette::{Context, IntoDiagnostic};
+use pixi_config::{get_cache_dir, Config};
+use pixi_consts::consts;
+use pixi_manifest::{PypiClobberPolicy, SystemRequirements};
+use rattler::package_cache::PackageCache;
+use rattler_conda_types::{Platform, RepoDataRecord};
+use rattler_digest::{compute_file_digest, Sha256};
+use rattler_lock::{PypiPackageData, PypiPackageEnvironmentData, UrlOrPath};
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+use crate::{
+    environment, install_pypi,
+    lock_file::{IoConcurrencyLimit, UpdateLockFileOptions, UvResolutionContext},
+    prefix::Prefix,
+    Project,
+};
+
+/// The name of the file in the archive that describes its contents.
+const PACK_METADATA_FILE: &str = "pixi-pack.json";
+
+/// The directory in the archive that contains the extracted conda packages.
+const PACKAGES_DIR: &str = "pkgs";
+
+/// The directory in the archive that contains the PyPI wheels.
+const WHEELS_DIR: &str = "wheels";
+
+const UNPACK_SH: &str = r#"#!/bin/sh
+set -e
+DIR="$(cd "$(dirname "$0")" && pwd)"
+if [ -z "$1" ]; then
+    echo "usage: $0 <prefix>" >&2
+    exit 1
+fi
+exec "$DIR/pixi" project export unpack "$DIR" "$1"
+"#;
+
+const UNPACK_BAT: &str = "@echo off\r\n\
+if \"%~1\"==\"\" (\r\n\
+    echo usage: %~nx0 ^<prefix^> 1>&2\r\n\
+    exit /b 1\r\n\
+)\r\n\
+\"%~dp0pixi.exe\" project export unpack \"%~dp0.\" \"%~1\"\r\n";
+
+#[derive(Debug, Parser)]
+#[clap(arg_required_else_help = false)]
+pub struct Args {
+    /// The environment to pack, defaults to the default environment
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    /// The platform to pack, defaults to the current platform
+    #[arg(short, long)]
+    pub platform: Option<Platform>,
+
+    /// The archive to create, defaults to `<environment>-<platform>.tar.gz`
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// The pixi executable to include in the archive to unpack it, defaults
+    /// to the running executable when packing for the current platform
+    #[arg(long)]
+    pub pixi_binary: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub lock_file_usage: crate::cli::LockFileUsageArgs,
+}
+
+/// Installs the contents of an unpacked archive into a prefix, this is what
+/// the unpack scripts in the archive call.
+#[derive(Debug, Parser)]
+pub struct UnpackArgs {
+    /// The directory the archive was extracted to
+    pub directory: PathBuf,
+
+    /// The prefix to install the environment into
+    pub prefix: PathBuf,
+}
+
+/// Describes the contents of an archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct PackMetadata {
+    pixi_version: String,
+    environment: String,
+    platform: Platform,
+    conda_packages: Vec<RepoDataRecord>,
+    pypi_packages: Vec<PackedWheel>,
+    /// The `pypi-clobber-policy` of the project, applied when the wheels are
+    /// installed.
+    #[serde(default)]
+    pypi_clobber_policy: PypiClobberPolicy,
+}
+
+/// A wheel in the archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct PackedWheel {
+    name: pep508_rs::PackageName,
+    version: pep440_rs::Version,
+    /// The path of the wheel relative to the root of the archive.
+    path: PathBuf,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+
+    let pixi_binary = match args.pixi_binary {
+        Some(pixi_binary) => pixi_binary,
+        None if platform == Platform::current() => std::env::current_exe()
+            .into_diagnostic()
+            .context("failed to determine the path of the pixi executable")?,
+        None => miette::bail!(
+            help = "pass `--pixi-binary` with the path to a pixi executable for {platform}",
+            "cannot include the running pixi executable in an archive for {platform}"
+        ),
+    };
+
+    let output = args.output.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{}-{}.tar.gz",
+            environment.name().as_str(),
+            platform
+        ))
+    });
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.lock_file_usage.into(),
+            no_install: true,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+
+    let locked_environment = lock_file
+        .environment(environment.name().as_str())
+        .ok_or_else(|| {
+            miette::miette!(
+                "environment {} is not in the lock-file",
+                environment.name().fancy_display()
+            )
+        })?;
+    let conda_records = locked_environment
+        .conda_repodata_records_for_platform(platform)
+        .into_diagnostic()?
+        .ok_or_else(|| {
+            miette::miette!(
+                "environment {} is not locked for {platform}",
+                environment.name().fancy_display()
+            )
+        })?;
+    let pypi_records = locked_environment
+        .pypi_packages_for_platform(platform)
+        .unwrap_or_default();
+
+    // Only wheels can be installed without building them first.
+    let non_wheels = pypi_records
+        .iter()
+        .filter(|(data, _)| wheel_file_name(data).is_none())
+        .map(|(data, _)| data.name.to_string())
+        .collect::<Vec<_>>();
+    if !non_wheels.is_empty() {
+        miette::bail!(
+            help = "only PyPI packages that are locked as wheels can be packed",
+            "the following PyPI packages are not wheels: {}",
+            non_wheels.join(", ")
+        );
+    }
+
+    // Make sure all the conda packages are extracted in the package cache.
+    let package_cache = PackageCache::new(get_cache_dir()?.join(consts::CONDA_PACKAGE_CACHE_DIR));
+    let mut package_dirs = Vec::with_capacity(conda_records.len());
+    for record in &conda_records {
+        let cache_lock = package_cache
+            .get_or_fetch_from_url(
+                &record.package_record,
+                record.url.clone(),
+                project.authenticated_client()?.clone(),
+                None,
+            )
+            .await
+            .into_diagnostic()
+            .with_context(|| format!("failed to fetch {}", record.file_name))?;
+        package_dirs.push(cache_lock.path().to_path_buf());
+    }
+
+    // Download the wheels.
+    let mut wheels = Vec::with_capacity(pypi_records.len());
+    let mut packed_wheels = Vec::with_capacity(pypi_records.len());
+    for (data, _) in &pypi_records {
+        let file_name = wheel_file_name(data).expect("non wheels are rejected above");
+        let wheel = fetch_wheel(data, project.root(), project.authenticated_client()?).await?;
+        let path = Path::new(WHEELS_DIR).join(&file_name);
+        wheels.push((path.clone(), wheel));
+        packed_wheels.push(PackedWheel {
+            name: data.name.clone(),
+            version: data.version.clone(),
+            path,
+        });
+    }
+
+    let metadata = PackMetadata {
+        pixi_version: consts::PIXI_VERSION.to_string(),
+        environment: environment.name().as_str().to_string(),
+        platform,
+        conda_packages: conda_records,
+        pypi_packages: packed_wheels,
+        pypi_clobber_policy: project.pypi_clobber_policy(),
+    };
+    let metadata = serde_json::to_vec_pretty(&metadata).into_diagnostic()?;
+
+    let archive = output.clone();
+    tokio::task::spawn_blocking(move || {
+        write_archive(
+            &archive,
+            &metadata,
+            &package_dirs,
+            &wheels,
+            &pixi_binary,
+            platform,
+        )
+    })
+    .await
+    .into_diagnostic()??;
+
+    eprintln!(
+        "{}Packed environment {} for {platform} into {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        environment.name().fancy_display(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Installs the environment from an extracted archive into a prefix.
+pub async fn unpack(args: UnpackArgs) -> miette::Result<()> {
+    let current_dir = std::env::current_dir().into_diagnostic()?;
+    let directory = current_dir.join(&args.directory);
+    let metadata_path = directory.join(PACK_METADATA_FILE);
+    let metadata: PackMetadata =
+        serde_json::from_str(&fs_err::read_to_string(&metadata_path).into_diagnostic()?)
+            .into_diagnostic()
+            .with_context(|| format!("failed to parse {}", metadata_path.display()))?;
+
+    if metadata.platform != Platform::current() {
+        miette::bail!(
+            "the archive contains an environment for {}, but this is {}",
+            metadata.platform,
+            Platform::current()
+        );
+    }
+
+    // Everything that is needed is in the archive, so never hit the network.
+    let mut config = Config::load_global();
+    config.offline = Some(true);
+    let (client, authenticated_client) = pixi_utils::reqwest::build_reqwest_clients(Some(&config))?;
+
+    let prefix_path = current_dir.join(&args.prefix);
+    fs_err::create_dir_all(&prefix_path).into_diagnostic()?;
+    let prefix = Prefix::new(prefix_path);
+    let installed_packages = prefix.find_installed_packages(None).await?;
+
+    let python_status = environment::update_prefix_conda(
+        &prefix,
+        PackageCache::new(directory.join(PACKAGES_DIR)),
+        authenticated_client,
+        installed_packages,
+        metadata.conda_packages.clone(),
+        metadata.platform,
+        &format!("unpacking environment '{}'", metadata.environment),
+        "",
+        IoConcurrencyLimit::default().into(),
+    )
+    .await?;
+
+    if !metadata.pypi_packages.is_empty() {
+        let Some(python_interpreter) = python_status.location() else {
+            miette::bail!("the archive contains PyPI packages but no python interpreter");
+        };
+        let pypi_records = metadata
+            .pypi_packages
+            .into_iter()
+            .map(|wheel| {
+                (
+                    PypiPackageData {
+                        name: wheel.name,
+                        version: wheel.version,
+                        url_or_path: UrlOrPath::Path(wheel.path),
+                        hash: None,
+                        requires_dist: vec![],
+                        requires_python: None,
+                        editable: false,
+                    },
+                    PypiPackageEnvironmentData::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let uv_context = UvResolutionContext::from_config(&config, client)?;
+        install_pypi::update_python_distributions(
+            &directory,
+            &prefix,
+            &metadata.conda_packages,
+            &pypi_records,
+            python_interpreter,
+            &SystemRequirements::default(),
+            &uv_context,
+            None,
+            &HashMap::new(),
+            metadata.platform,
+            None,
+            metadata.pypi_clobber_policy,
+            false,
+        )
+        .await?;
+    }
+
+    eprintln!(
+        "{}Unpacked environment '{}' into {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        metadata.environment,
+        prefix.root().display()
+    );
+
+    Ok(())
+}
+
+/// Returns the file name of the wheel of a locked PyPI package, or `None` if
+/// the package is not locked as a wheel.
+fn wheel_file_name(data: &PypiPackageData) -> Option<String> {
+    let file_name = match &data.url_or_path {
+        UrlOrPath::Url(url) => percent_encoding::percent_decode_str(url.path_segments()?.last()?)
+            .decode_utf8_lossy()
+            .into_owned(),
+        UrlOrPath::Path(path) => path.file_name()?.to_string_lossy().into_owned(),
+    };
+    file_name.ends_with(".whl").then_some(file_name)
+}
+
+/// A wheel on disk that is ready to be added to the archive.
+enum WheelFile {
+    /// A wheel that is referenced by path in the lock-file.
+    Local(PathBuf),
+    /// A wheel that was downloaded to a temporary file, which is removed when
+    /// this is dropped.
+    Downloaded(tempfile::NamedTempFile),
+}
+
+impl WheelFile {
+    fn path(&self) -> &Path {
+        match self {
+            WheelFile::Local(path) => path,
+            WheelFile::Downloaded(file) => file.path(),
+        }
+    }
+}
+
+/// Downloads a wheel to a temporary file, or locates it on disk, and verifies
+/// its hash if it is locked. The wheel is streamed to disk so it is never held
+/// in memory as a whole.
+async fn fetch_wheel(
+    data: &PypiPackageData,
+    project_root: &Path,
+    client: &ClientWithMiddleware,
+) -> miette::Result<WheelFile> {
+    let wheel = match &data.url_or_path {
+        UrlOrPath::Url(url) => {
+            let url = url
+                .as_str()
+                .strip_prefix("direct+")
+                .and_then(|url| Url::parse(url).ok())
+                .unwrap_or_else(|| url.clone());
+            let mut response = client
+                .get(url.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status().map_err(Into::into))
+                .into_diagnostic()
+                .with_context(|| format!("failed to download {url}"))?;
+
+            let temp_file = tempfile::NamedTempFile::new().into_diagnostic()?;
+            let mut file = tokio::fs::File::from_std(temp_file.reopen().into_diagnostic()?);
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .into_diagnostic()
+                .with_context(|| format!("failed to download {url}"))?
+            {
+                file.write_all(&chunk).await.into_diagnostic()?;
+            }
+            file.flush().await.into_diagnostic()?;
+            WheelFile::Downloaded(temp_file)
+        }
+        UrlOrPath::Path(path) => WheelFile::Local(project_root.join(path)),
+    };
+
+    if let Some(expected) = data.hash.as_ref().and_then(|hash| hash.sha256()) {
+        let path = wheel.path().to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || compute_file_digest::<Sha256>(&path))
+            .await
+            .into_diagnostic()?
+            .into_diagnostic()
+            .with_context(|| format!("failed to read the wheel for {}", data.name))?;
+        if &actual != expected {
+            miette::bail!(
+                "the sha256 of the wheel for {} is {:x}, but the lock-file expects {:x}",
+                data.name,
+                actual,
+                expected
+            );
+        }
+    }
+
+    Ok(wheel)
+}
+
+/// Writes the archive with the metadata, the extracted conda packages, the
+/// wheels, the pixi executable and the unpack scripts.
+fn write_archive(
+    output: &Path,
+    metadata: &[u8],
+    package_dirs: &[PathBuf],
+    wheels: &[(PathBuf, WheelFile)],
+    pixi_binary: &Path,
+    platform: Platform,
+) -> miette::Result<()> {
+    let file = fs_err::File::create(output).into_diagnostic()?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+
+    append_file(&mut builder, Path::new(PACK_METADATA_FILE), metadata, 0o644)?;
+
+    for package_dir in package_dirs {
+        let name = package_dir
+            .file_name()
+            .expect("package cache entries have a name");
+        builder
+            .append_dir_all(Path::new(PACKAGES_DIR).join(name), package_dir)
+            .into_diagnostic()
+            .with_context(|| format!("failed to add {} to the archive", package_dir.display()))?;
+    }
+
+    for (path, wheel) in wheels {
+        append_path(&mut builder, path, wheel.path(), 0o644)?;
+    }
+
+    if platform.is_windows() {
+        append_path(&mut builder, Path::new("pixi.exe"), pixi_binary, 0o755)?;
+        append_file(
+            &mut builder,
+            Path::new("unpack.bat"),
+            UNPACK_BAT.as_bytes(),
+            0o755,
+        )?;
+    } else {
+        append_path(&mut builder, Path::new("pixi"), pixi_binary, 0o755)?;
+        append_file(
+            &mut builder,
+            Path::new("unpack.sh"),
+            UNPACK_SH.as_bytes(),
+            0o755,
+        )?;
+    }
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .and_then(|mut file| file.flush())
+        .into_diagnostic()
+        .with_context(|| format!("failed to write {}", output.display()))
+}
+
+fn append_file<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &Path,
+    contents: &[u8],
+    mode: u32,
+) -> miette::Result<()> {
+    append_data(builder, path, contents, contents.len() as u64, mode)
+}
+
+/// Streams the file at `source` into the archive at `path`.
+fn append_path<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &Path,
+    source: &Path,
+    mode: u32,
+) -> miette::Result<()> {
+    let file = fs_err::File::open(source).into_diagnostic()?;
+    let size = file.metadata().into_diagnostic()?.len();
+    append_data(builder, path, file, size, mode)
+}
+
+fn append_data<W: Write, R: Read>(
+    builder: &mut tar::Builder<W>,
+    path: &Path,
+    contents: R,
+    size: u64,
+    mode: u32,
+) -> miette::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(mode);
+    header.set_mtime(0);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, contents)
+        .into_diagnostic()
+        .with_context(|| format!("failed to add {} to the archive", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn package(url_or_path: UrlOrPath) -> PypiPackageData {
+        PypiPackageData {
+            name: "numpy".parse().unwrap(),
+            version: pep440_rs::Version::from_str("2.1.0").unwrap(),
+            url_or_path,
+            hash: None,
+            requires_dist: vec![],
+            requires_python: None,
+            editable: false,
+        }
+    }
+
+    #[test]
+    fn test_wheel_file_name() {
+        let url = Url::parse("https://files.pythonhosted.org/packages/numpy-2.1.0%2Blocal-cp312-cp312-manylinux_2_17_x86_64.whl").unwrap();
+        assert_eq!(
+            wheel_file_name(&package(UrlOrPath::Url(url))).as_deref(),
+            Some("numpy-2.1.0+local-cp312-cp312-manylinux_2_17_x86_64.whl")
+        );
+
+        let sdist =
+            Url::parse("https://files.pythonhosted.org/packages/numpy-2.1.0.tar.gz").unwrap();
+        assert_eq!(wheel_file_name(&package(UrlOrPath::Url(sdist))), None);
+
+        let path = UrlOrPath::Path(PathBuf::from("dist/numpy-2.1.0-py3-none-any.whl"));
+        assert_eq!(
+            wheel_file_name(&package(path)).as_deref(),
+            Some("numpy-2.1.0-py3-none-any.whl")
+        );
+        assert_eq!(
+            wheel_file_name(&package(UrlOrPath::Path(PathBuf::from("./my-package")))),
+            None
+        );
+    }
+}
@@ -1,12 +1,21 @@
 use clap::Parser;
+use deno_task_shell::{ShellPipeReader, ShellPipeWriter, ShellState};
 use dialoguer::theme::ColorfulTheme;
 use itertools::Itertools;
-use miette::{Diagnostic, IntoDiagnostic};
+use miette::{Context, Diagnostic, IntoDiagnostic};
+use parking_lot::Mutex;
+use std::borrow::Cow;
 use std::collections::hash_map::Entry;
 use std::collections::HashSet;
 use std::convert::identity;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 use std::{collections::HashMap, string::String};
+use tokio::task::JoinHandle;
 
+use crate::activation::get_passthrough_environment_variables;
 use crate::cli::cli_config::{PrefixUpdateConfig, ProjectConfig};
 use crate::environment::verify_prefix_location_unchanged;
 use crate::lock_file::UpdateLockFileOptions;
@@ -14,8 +23,8 @@ use crate::project::errors::UnsupportedPlatformError;
 use crate::project::virtual_packages::verify_current_platform_has_required_virtual_packages;
 use crate::project::Environment;
 use crate::task::{
-    get_task_env, AmbiguousTask, CanSkip, ExecutableTask, FailedToParseShellScript,
-    InvalidWorkingDirectory, SearchEnvironments, TaskAndEnvironment, TaskGraph,
+    get_task_env, AmbiguousTask, CanSkip, ExecutableTask, InvalidWorkingDirectory, ReportFormat,
+    RunReport, SearchEnvironments, TaskAndEnvironment, TaskGraph, TaskReport, TaskStatus,
 };
 use crate::Project;
 use fancy_display::FancyDisplay;
@@ -49,6 +58,22 @@ pub struct Args {
     /// Using this flag will ignore your current shell environment and use bare minimum environment to activate the pixi environment in.
     #[arg(long)]
     pub clean_env: bool,
+
+    /// Write the output of each task to a log file in this directory
+    ///
+    /// The output is still shown in the terminal as well.
+    #[arg(long)]
+    pub log_dir: Option<PathBuf>,
+
+    /// Write a report of the tasks that were part of the run in the given format
+    #[arg(long, value_enum)]
+    pub report: Option<ReportFormat>,
+
+    /// The path to write the report to
+    ///
+    /// Defaults to `pixi-run-report.json` or `pixi-run-report.xml` in the log directory, or in the current directory if no log directory is specified.
+    #[arg(long, requires = "report")]
+    pub report_path: Option<PathBuf>,
 }
 
 /// CLI entry point for `pixi run`
@@ -109,118 +134,233 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
     tracing::info!("Task graph: {}", task_graph);
 
-    // Traverse the task graph in topological order and execute each individual task.
-    let mut task_idx = 0;
-    let mut task_envs = HashMap::new();
-    for task_id in task_graph.topological_order() {
-        let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id);
-
-        // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
-        // instantiate a prefix for an alias.
-        if !executable_task.task().is_executable() {
-            continue;
-        }
-
-        // Showing which command is being run if the level and type allows it.
-        if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
-            if task_idx > 0 {
-                // Add a newline between task outputs
-                eprintln!();
+    if let Some(log_dir) = &args.log_dir {
+        fs_err::create_dir_all(log_dir).into_diagnostic()?;
+    }
+    let report_path = args.report.map(|format| {
+        args.report_path
+            .clone()
+            .unwrap_or_else(|| match &args.log_dir {
+                Some(log_dir) => log_dir.join(format.default_file_name()),
+                None => PathBuf::from(format.default_file_name()),
+            })
+    });
+    let mut report = RunReport::default();
+
+    // Execute the tasks in a block so the report is written regardless of how
+    // the run ends.
+    let result: miette::Result<()> = async {
+        // Traverse the task graph in topological order and execute each individual task.
+        let mut task_idx = 0;
+        let mut task_envs = HashMap::new();
+        for task_id in task_graph.topological_order() {
+            let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id);
+
+            // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
+            // instantiate a prefix for an alias.
+            if !executable_task.task().is_executable() {
+                continue;
             }
-            eprintln!(
-                "{}{}{}{}{}{}{}",
-                console::Emoji("✨ ", ""),
-                console::style("Pixi task (").bold(),
-                console::style(executable_task.name().unwrap_or("unnamed"))
-                    .green()
-                    .bold(),
-                // Only print environment if multiple environments are available
-                if project.environments().len() > 1 {
-                    format!(
-                        " in {}",
-                        executable_task.run_environment.name().fancy_display()
-                    )
-                } else {
-                    "".to_string()
-                },
-                console::style("): ").bold(),
-                executable_task.display_command(),
-                if let Some(description) = executable_task.task().description() {
-                    console::style(format!(": ({})", description)).yellow()
-                } else {
-                    console::style("".to_string()).yellow()
-                }
-            );
-        }
 
-        // check task cache
-        let task_cache = match executable_task
-            .can_skip(&lock_file.lock_file)
-            .await
-            .into_diagnostic()?
-        {
-            CanSkip::No(cache) => cache,
-            CanSkip::Yes => {
+            // If the conditions of the task are not met, we skip it without instantiating a prefix.
+            if let Some(reason) = &task_graph[task_id].skip_reason {
                 eprintln!(
-                    "Task '{}' can be skipped (cache hit) 🚀",
-                    console::style(executable_task.name().unwrap_or("")).bold()
+                    "Task '{}' is skipped because {} ⏭️",
+                    console::style(executable_task.name().unwrap_or("")).bold(),
+                    reason
                 );
-                task_idx += 1;
+                report.tasks.push(TaskReport {
+                    skip_reason: Some(reason.to_string()),
+                    ..TaskReport::new(&executable_task, TaskStatus::Skipped)
+                });
                 continue;
             }
-        };
 
-        // If we don't have a command environment yet, we need to compute it. We lazily compute the
-        // task environment because we only need the environment if a task is actually executed.
-        let task_env: &_ = match task_envs.entry(executable_task.run_environment.clone()) {
-            Entry::Occupied(env) => env.into_mut(),
-            Entry::Vacant(entry) => {
-                // Ensure there is a valid prefix
-                lock_file
-                    .prefix(
+            // Showing which command is being run if the level and type allows it.
+            if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
+                if task_idx > 0 {
+                    // Add a newline between task outputs
+                    eprintln!();
+                }
+                eprintln!(
+                    "{}{}{}{}{}{}{}",
+                    console::Emoji("✨ ", ""),
+                    console::style("Pixi task (").bold(),
+                    console::style(executable_task.name().unwrap_or("unnamed"))
+                        .green()
+                        .bold(),
+                    // Only print environment if multiple environments are available
+                    if project.environments().len() > 1 {
+                        format!(
+                            " in {}",
+                            executable_task.run_environment.name().fancy_display()
+                        )
+                    } else {
+                        "".to_string()
+                    },
+                    console::style("): ").bold(),
+                    executable_task.display_command(),
+                    if let Some(description) = executable_task.task().description() {
+                        console::style(format!(": ({})", description)).yellow()
+                    } else {
+                        console::style("".to_string()).yellow()
+                    }
+                );
+            }
+
+            // The task runs in a clean environment if either the task or the
+            // command line requests it, which also determines its hash.
+            let clean_env = args.clean_env || executable_task.task().clean_env();
+
+            // check task cache
+            let task_cache = match executable_task
+                .can_skip(&lock_file.lock_file, clean_env)
+                .await
+                .into_diagnostic()?
+            {
+                CanSkip::No(cache) => cache,
+                CanSkip::Yes => {
+                    eprintln!(
+                        "Task '{}' can be skipped (cache hit) 🚀",
+                        console::style(executable_task.name().unwrap_or("")).bold()
+                    );
+                    report
+                        .tasks
+                        .push(TaskReport::new(&executable_task, TaskStatus::CacheHit));
+                    task_idx += 1;
+                    continue;
+                }
+            };
+
+            // If we don't have a command environment yet, we need to compute it. We lazily compute the
+            // task environment because we only need the environment if a task is actually executed.
+            let task_env: &_ = match task_envs.entry(executable_task.run_environment.clone()) {
+                Entry::Occupied(env) => env.into_mut(),
+                Entry::Vacant(entry) => {
+                    // Ensure there is a valid prefix
+                    lock_file
+                        .prefix(
+                            &executable_task.run_environment,
+                            args.prefix_update_config.update_mode(),
+                        )
+                        .await?;
+
+                    let command_env = get_task_env(
                         &executable_task.run_environment,
-                        args.prefix_update_config.update_mode(),
+                        clean_env,
+                        Some(&lock_file.lock_file),
+                        project.config().force_activate(),
+                        project.config().experimental_activation_cache_usage(),
                     )
                     .await?;
-
-                let command_env = get_task_env(
-                    &executable_task.run_environment,
-                    args.clean_env || executable_task.task().clean_env(),
-                    Some(&lock_file.lock_file),
-                    project.config().force_activate(),
-                    project.config().experimental_activation_cache_usage(),
-                )
-                .await?;
-                entry.insert(command_env)
-            }
-        };
-
-        // Execute the task itself within the command environment. If one of the tasks failed with
-        // a non-zero exit code, we exit this parent process with the same code.
-        match execute_task(&executable_task, task_env).await {
-            Ok(_) => {
-                task_idx += 1;
-            }
-            Err(TaskExecutionError::NonZeroExitCode(code)) => {
-                if code == 127 {
-                    command_not_found(&project, explicit_environment);
+                    entry.insert(command_env)
+                }
+            };
+
+            // Pass the variables matching the `env-passthrough` patterns of the task, the
+            // variables of the activation take precedence.
+            let passthrough_env =
+                get_passthrough_environment_variables(executable_task.task().env_passthrough());
+            let task_env = if passthrough_env.is_empty() {
+                Cow::Borrowed(task_env)
+            } else {
+                let mut task_env = task_env.clone();
+                for (key, value) in passthrough_env {
+                    task_env.entry(key).or_insert(value);
+                }
+                Cow::Owned(task_env)
+            };
+
+            let log_file = args
+                .log_dir
+                .as_ref()
+                .map(|log_dir| log_dir.join(log_file_name(&executable_task)));
+            let mut task_report = TaskReport {
+                log_file: log_file.clone(),
+                ..TaskReport::new(&executable_task, TaskStatus::Success)
+            };
+            let log_file = log_file
+                .map(fs_err::File::create)
+                .transpose()
+                .into_diagnostic()?;
+
+            // Execute the task itself within the command environment. If one of the tasks failed with
+            // a non-zero exit code, we exit this parent process with the same code.
+            let start = Instant::now();
+            let result = execute_task(&executable_task, &task_env, log_file).await;
+            task_report.duration = start.elapsed();
+            match result {
+                Ok(_) => {
+                    task_report.exit_code = Some(0);
+                    report.tasks.push(task_report);
+                    task_idx += 1;
+                }
+                Err(TaskExecutionError::NonZeroExitCode(code)) => {
+                    task_report.exit_code = Some(code);
+                    task_report.status = TaskStatus::Failed;
+                    report.tasks.push(task_report);
+                    if let (Some(format), Some(path)) = (args.report, &report_path) {
+                        write_report(&report, format, path)?;
+                    }
+                    if code == 127 {
+                        command_not_found(&project, explicit_environment);
+                    }
+                    std::process::exit(code);
+                }
+                Err(err) => {
+                    task_report.status = TaskStatus::Failed;
+                    report.tasks.push(task_report);
+                    return Err(err.into());
                 }
-                std::process::exit(code);
             }
-            Err(err) => return Err(err.into()),
-        }
 
-        // Update the task cache with the new hash
-        executable_task
-            .save_cache(&lock_file, task_cache)
-            .await
-            .into_diagnostic()?;
+            // Update the task cache with the new hash
+            executable_task
+                .save_cache(&lock_file, task_cache, clean_env)
+                .await
+                .into_diagnostic()?;
+        }
+        Ok(())
     }
+    .await;
+
+    let written = match (args.report, &report_path) {
+        (Some(format), Some(path)) => write_report(&report, format, path),
+        _ => Ok(()),
+    };
+    // An error of a task takes precedence over an error writing the report
+    result.and(written)?;
 
     Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
     Ok(())
 }
 
+/// Writes the report of the run to the given path.
+fn write_report(report: &RunReport, format: ReportFormat, path: &Path) -> miette::Result<()> {
+    fs_err::write(path, report.render(format))
+        .into_diagnostic()
+        .wrap_err("failed to write the report of the run")?;
+    tracing::info!("Wrote the report of the run to {}", path.display());
+    Ok(())
+}
+
+/// Returns the name of the log file of a task. Characters that are not
+/// allowed in file names, like path separators, are replaced by `_`.
+fn log_file_name(task: &ExecutableTask<'_>) -> String {
+    format!(
+        "{}-{}.log",
+        task.run_environment.name(),
+        task.name().unwrap_or("unnamed")
+    )
+    .chars()
+    .map(|c| match c {
+        '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+        c if c.is_control() => '_',
+        c => c,
+    })
+    .collect()
+}
+
 /// Called when a command was not found.
 fn command_not_found<'p>(project: &'p Project, explicit_environment: Option<Environment<'p>>) {
     let available_tasks: HashSet<TaskName> =
@@ -254,21 +394,28 @@ enum TaskExecutionError {
     NonZeroExitCode(i32),
 
     #[error(transparent)]
-    FailedToParseShellScript(#[from] FailedToParseShellScript),
+    #[diagnostic(transparent)]
+    InvalidScript(#[from] crate::task::TaskExecutionError),
 
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
 
     #[error(transparent)]
     UnsupportedPlatformError(#[from] UnsupportedPlatformError),
+
+    #[error("failed to write the output of the task to the log file")]
+    FailedToWriteLog(#[source] io::Error),
 }
 
 /// Called to execute a single command.
 ///
 /// This function is called from [`execute`].
+/// If a `log_file` is given the output of the task is written to it as well
+/// as to the terminal.
 async fn execute_task<'p>(
     task: &ExecutableTask<'p>,
     command_env: &HashMap<String, String>,
+    log_file: Option<fs_err::File>,
 ) -> Result<(), TaskExecutionError> {
     let Some(script) = task.as_deno_script()? else {
         return Ok(());
@@ -282,14 +429,38 @@ async fn execute_task<'p>(
     // some other command we might want to revaluate this.
     let ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
 
-    let execute_future =
-        deno_task_shell::execute(script, command_env.clone(), &cwd, Default::default());
+    let (stdout, stderr, log_handles) = match log_file {
+        Some(log_file) => {
+            let log_file = Arc::new(Mutex::new(log_file));
+            let (stdout, stdout_handle) = tee_to_log_file(io::stdout, log_file.clone());
+            let (stderr, stderr_handle) = tee_to_log_file(io::stderr, log_file);
+            (stdout, stderr, vec![stdout_handle, stderr_handle])
+        }
+        None => (ShellPipeWriter::stdout(), ShellPipeWriter::stderr(), vec![]),
+    };
+
+    let state = ShellState::new(command_env.clone(), &cwd, Default::default());
+    let execute_future = deno_task_shell::execute_with_pipes(
+        script,
+        state,
+        ShellPipeReader::stdin(),
+        stdout,
+        stderr,
+    );
     let status_code = tokio::select! {
         code = execute_future => code,
         // This should never exit
         _ = ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
     };
 
+    // Wait until all the output is written to the log file.
+    for handle in log_handles {
+        handle
+            .await
+            .expect("writing the log file should not panic")
+            .map_err(TaskExecutionError::FailedToWriteLog)?;
+    }
+
     if status_code != 0 {
         return Err(TaskExecutionError::NonZeroExitCode(status_code));
     }
@@ -297,6 +468,45 @@ async fn execute_task<'p>(
     Ok(())
 }
 
+/// Returns a pipe of which everything that is written to it is written to the
+/// terminal and to the log file.
+fn tee_to_log_file<W: Write>(
+    terminal: fn() -> W,
+    log_file: Arc<Mutex<fs_err::File>>,
+) -> (ShellPipeWriter, JoinHandle<io::Result<()>>) {
+    let (reader, writer) = deno_task_shell::pipe();
+    let handle = tokio::task::spawn_blocking(move || {
+        reader
+            .pipe_to(&mut TeeWriter {
+                terminal: terminal(),
+                log_file,
+            })
+            .map_err(io::Error::other)
+    });
+    (writer, handle)
+}
+
+/// A writer that writes to the terminal and a log file at the same time.
+struct TeeWriter<W> {
+    terminal: W,
+    log_file: Arc<Mutex<fs_err::File>>,
+}
+
+impl<W: Write> Write for TeeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.terminal.write_all(buf)?;
+        // Flush to show the output directly, like the shell does without a log file.
+        self.terminal.flush()?;
+        self.log_file.lock().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.terminal.flush()?;
+        self.log_file.lock().flush()
+    }
+}
+
 /// Called to disambiguate between environments to run a task in.
 fn disambiguate_task_interactive<'p>(
     problem: &AmbiguousTask<'p>,
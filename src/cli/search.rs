@@ -121,10 +121,10 @@ pub async fn execute_impl<W: Write>(
 
     let package_name_filter = args.package;
 
-    let client = project
-        .as_ref()
-        .map(|p| p.authenticated_client().clone())
-        .unwrap_or_else(|| build_reqwest_clients(None).1);
+    let client = match &project {
+        Some(project) => project.authenticated_client()?.clone(),
+        None => build_reqwest_clients(None)?.1,
+    };
 
     let config = Config::load_global();
 
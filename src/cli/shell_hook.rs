@@ -1,18 +1,30 @@
-use std::{collections::HashMap, default::Default};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    default::Default,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
+use indexmap::IndexMap;
 use miette::IntoDiagnostic;
-use pixi_config::{ConfigCliActivation, ConfigCliPrompt};
+use pixi_config::{Config, ConfigCliActivation, ConfigCliPrompt};
+use rattler_conda_types::Platform;
 use rattler_lock::LockFile;
 use rattler_shell::{
     activation::{ActivationVariables, PathModificationBehavior},
-    shell::ShellEnum,
+    shell::{NuShell, Shell, ShellEnum, ShellScript},
 };
 use serde::Serialize;
 use serde_json;
+use xxhash_rust::xxh3::xxh3_64;
 
-use crate::activation::CurrentEnvVarBehavior;
+use crate::activation::{
+    run_activation, run_activation_with_sources, ActivationSource, CurrentEnvVarBehavior,
+};
 use crate::environment::get_update_lock_file_and_prefix;
+use crate::lock_file::load_lock_file;
+use crate::project::find_project_manifest;
+use crate::prompt::get_auto_hook;
 use crate::{
     activation::get_activator,
     cli::cli_config::{PrefixUpdateConfig, ProjectConfig},
@@ -20,6 +32,19 @@ use crate::{
     Project,
 };
 
+/// The environment variable that holds the manifest of the project that was
+/// activated by the `--auto` hook.
+const AUTO_MANIFEST_ENV: &str = "PIXI_AUTO_MANIFEST";
+
+/// The environment variable that holds the path to the file with the values of
+/// the variables before they were overwritten by an activation, used to
+/// deactivate the environment again.
+const ACTIVATION_BACKUP_ENV: &str = "PIXI_ACTIVATION_BACKUP";
+
+/// The directory in the `.pixi` directory of a project that holds the
+/// activation backups.
+const ACTIVATION_BACKUP_DIR: &str = "activation-backups";
+
 /// Print the pixi environment activation script.
 ///
 /// You can source the script to activate the environment without needing pixi
@@ -50,6 +75,28 @@ pub struct Args {
 
     #[clap(flatten)]
     prompt_config: ConfigCliPrompt,
+
+    /// Print a hook that activates the environment of the project you `cd`
+    /// into and restores the previous environment when you leave it, e.g.
+    /// `eval "$(pixi shell-hook --auto)"`. Supports bash, zsh, fish and nushell
+    #[arg(long, conflicts_with_all = ["json", "environment"])]
+    auto: bool,
+
+    /// Print the changes to the environment after the working directory
+    /// changed, this is called by the `--auto` hook
+    #[arg(long, hide = true, conflicts_with_all = ["json", "environment", "auto"])]
+    auto_update: bool,
+
+    /// Print a script that restores the environment variables of the current
+    /// shell to their values before the environment was activated
+    #[arg(long, conflicts_with_all = ["json", "environment", "auto", "auto_update"])]
+    deactivate: bool,
+
+    /// Print the environment variables that are added, modified or removed
+    /// by activating the environment, together with the activation script or
+    /// `[activation.env]` entry that set them
+    #[arg(long, conflicts_with_all = ["json", "auto", "auto_update", "deactivate"])]
+    diff: bool,
 }
 
 #[derive(Serialize)]
@@ -57,19 +104,23 @@ struct ShellEnv<'a> {
     environment_variables: &'a HashMap<String, String>,
 }
 
+/// The activation script of an environment together with the names of the
+/// variables it sets.
+struct ActivationScript {
+    script: String,
+    variables: Vec<String>,
+}
+
 /// Generates the activation script.
 async fn generate_activation_script(
     shell: Option<ShellEnum>,
     environment: &Environment<'_>,
-) -> miette::Result<String> {
+) -> miette::Result<ActivationScript> {
     // Get shell from the arguments or from the current process or use default if
     // all fails
-    let shell = shell.unwrap_or_else(|| {
-        ShellEnum::from_parent_process()
-            .unwrap_or_else(|| ShellEnum::from_env().unwrap_or_default())
-    });
+    let shell = shell_or_default(shell);
 
-    let activator = get_activator(environment, shell).into_diagnostic()?;
+    let activator = get_activator(environment, shell.clone()).into_diagnostic()?;
 
     let path = std::env::var("PATH")
         .ok()
@@ -86,7 +137,47 @@ async fn generate_activation_script(
         })
         .into_diagnostic()?;
 
-    result.script.contents().into_diagnostic()
+    // Variables exported by the activation scripts themselves are only known
+    // after running them, see [`activated_variables`].
+    let variables = activator
+        .env_vars
+        .keys()
+        .cloned()
+        .chain([
+            shell.path_var(&Platform::current()).to_string(),
+            "CONDA_PREFIX".to_string(),
+            "CONDA_SHLVL".to_string(),
+        ])
+        .collect();
+
+    Ok(ActivationScript {
+        script: result.script.contents().into_diagnostic()?,
+        variables,
+    })
+}
+
+/// Returns the names of the variables that are changed by the `activation`,
+/// including the variables exported by the activation scripts of the
+/// environment. Those are only known after running the scripts, so the
+/// activation is run once to find them.
+async fn activated_variables(
+    environment: &Environment<'_>,
+    activation: &ActivationScript,
+) -> miette::Result<BTreeSet<String>> {
+    let scripted = run_activation(
+        environment,
+        &CurrentEnvVarBehavior::Include,
+        None,
+        true,
+        false,
+    )
+    .await?;
+    Ok(activation
+        .variables
+        .iter()
+        .cloned()
+        .chain(scripted.into_keys())
+        .collect())
 }
 
 /// Generates a JSON object describing the changes to the shell environment when
@@ -115,12 +206,372 @@ async fn generate_environment_json(
     serde_json::to_string(&shell_env).into_diagnostic()
 }
 
+#[derive(Serialize)]
+struct ShellEnvChanges {
+    set: IndexMap<String, serde_json::Value>,
+    unset: Vec<String>,
+}
+
+/// A change to an environment variable of the current shell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EnvVarChange {
+    Added(String),
+    Modified { old: String, new: String },
+    Removed(String),
+}
+
+/// Writes the previous values of the variables that are overwritten by an
+/// activation to a file in `backup_dir` and returns the path of the file.
+///
+/// Only the path is stored in the environment, the values include the `PATH`
+/// which would easily exceed the maximum length of a command in `cmd.exe`. The
+/// file is named after the hash of its contents, so activating the same
+/// environment again reuses the file.
+fn write_activation_backup(
+    backup_dir: &Path,
+    backup: &BTreeMap<String, Option<String>>,
+) -> miette::Result<PathBuf> {
+    let contents = serde_json::to_string(backup).into_diagnostic()?;
+    let path = backup_dir.join(format!("{:016x}.json", xxh3_64(contents.as_bytes())));
+    fs_err::create_dir_all(backup_dir).into_diagnostic()?;
+    fs_err::write(&path, contents).into_diagnostic()?;
+    Ok(path)
+}
+
+/// Reads the previous values of the variables from a file created by
+/// [`write_activation_backup`].
+fn read_activation_backup(path: &Path) -> Option<BTreeMap<String, Option<String>>> {
+    let contents = fs_err::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Returns the activation backup of the current shell, if any.
+fn activation_backup() -> Option<BTreeMap<String, Option<String>>> {
+    std::env::var_os(ACTIVATION_BACKUP_ENV)
+        .and_then(|path| read_activation_backup(Path::new(&path)))
+}
+
+/// Returns true if `pixi shell-hook --deactivate` supports the shell, `cmd.exe`
+/// can't evaluate the output of a command.
+fn supports_deactivation(shell: &ShellEnum) -> bool {
+    !matches!(shell, ShellEnum::CmdExe(_))
+}
+
+/// Restores the variables of the activation backup in the process environment,
+/// such that an activation starts from the environment before the previous
+/// activation. Returns the restored backup.
+fn restore_activation_backup() -> BTreeMap<String, Option<String>> {
+    let backup = activation_backup().unwrap_or_default();
+    for (key, value) in &backup {
+        match value {
+            Some(value) => std::env::set_var(key, value),
+            None => std::env::remove_var(key),
+        }
+    }
+    backup
+}
+
+/// Records the current values of the variables that are about to be set by an
+/// activation. Variables that are already part of the `existing` backup keep
+/// their recorded value, so the backup always describes the environment before
+/// the first activation.
+fn create_activation_backup<'a>(
+    existing: BTreeMap<String, Option<String>>,
+    keys: impl IntoIterator<Item = &'a String>,
+) -> BTreeMap<String, Option<String>> {
+    let mut backup = existing;
+    for key in keys {
+        backup
+            .entry(key.clone())
+            .or_insert_with(|| std::env::var(key).ok());
+    }
+    backup
+}
+
+/// Determines the changes to the environment variables of the shell after the
+/// working directory changed to `current_dir`. A value of `None` means that the
+/// variable should be unset.
+///
+/// When the directory belongs to a different project than the one that is
+/// currently activated, the variables of the previous activation are restored
+/// first and the default environment of the new project is activated, using
+/// the activation cache.
+async fn auto_update_environment(
+    current_dir: PathBuf,
+    config: Config,
+) -> miette::Result<IndexMap<String, Option<String>>> {
+    let manifest_path = find_project_manifest(current_dir);
+    let active_manifest_path = std::env::var_os(AUTO_MANIFEST_ENV).map(PathBuf::from);
+    if manifest_path == active_manifest_path {
+        return Ok(IndexMap::new());
+    }
+
+    // Restore the variables that were overwritten by the previous activation.
+    // The process environment is restored as well, so the activation below
+    // starts from the original environment.
+    let mut changes: IndexMap<String, Option<String>> =
+        restore_activation_backup().into_iter().collect();
+    changes.insert(AUTO_MANIFEST_ENV.to_string(), None);
+    changes.insert(ACTIVATION_BACKUP_ENV.to_string(), None);
+
+    let Some(manifest_path) = manifest_path else {
+        return Ok(changes);
+    };
+
+    let project = Project::from_path(&manifest_path)?.with_cli_config(config);
+    let environment = project.environment_from_name_or_env_var(None)?;
+    if !environment.dir().is_dir() {
+        eprintln!(
+            "{}The environment '{}' of '{}' is not installed, run `pixi install` to activate it",
+            console::style(console::Emoji("⚠️ ", "")).yellow(),
+            environment.name(),
+            project.name(),
+        );
+        return Ok(changes);
+    }
+
+    let lock_file = load_lock_file(&project).await?;
+    let environment_variables = project
+        .get_activated_environment_variables(
+            &environment,
+            CurrentEnvVarBehavior::Exclude,
+            Some(&lock_file),
+            project.config().force_activate(),
+            true,
+        )
+        .await?;
+
+    let backup = create_activation_backup(BTreeMap::new(), environment_variables.keys());
+    for (key, value) in environment_variables {
+        changes.insert(key.clone(), Some(value.clone()));
+    }
+    changes.insert(
+        AUTO_MANIFEST_ENV.to_string(),
+        Some(manifest_path.to_string_lossy().into_owned()),
+    );
+    let backup_path =
+        write_activation_backup(&project.pixi_dir().join(ACTIVATION_BACKUP_DIR), &backup)?;
+    changes.insert(
+        ACTIVATION_BACKUP_ENV.to_string(),
+        Some(backup_path.to_string_lossy().into_owned()),
+    );
+
+    Ok(changes)
+}
+
+/// Renders the changes to the environment variables as a JSON object that the
+/// nushell hook loads with `load-env` and `hide-env`.
+fn render_environment_changes_json(
+    changes: &IndexMap<String, Option<String>>,
+) -> miette::Result<String> {
+    let path_var = NuShell.path_var(&Platform::current()).to_string();
+    let mut env_changes = ShellEnvChanges {
+        set: IndexMap::new(),
+        unset: Vec::new(),
+    };
+    for (key, value) in changes {
+        match value {
+            // Nushell stores the path as a list
+            Some(value) if key == &path_var => {
+                let paths = std::env::split_paths(value)
+                    .map(|path| serde_json::Value::from(path.to_string_lossy()))
+                    .collect();
+                env_changes
+                    .set
+                    .insert(key.clone(), serde_json::Value::Array(paths));
+            }
+            Some(value) => {
+                env_changes
+                    .set
+                    .insert(key.clone(), serde_json::Value::from(value.as_str()));
+            }
+            None => env_changes.unset.push(key.clone()),
+        }
+    }
+    serde_json::to_string(&env_changes).into_diagnostic()
+}
+
+/// Renders the changes to the environment variables as a script for the given
+/// shell. A value of `None` unsets the variable.
+fn render_environment_changes(
+    shell: ShellEnum,
+    changes: &IndexMap<String, Option<String>>,
+) -> miette::Result<String> {
+    let path_var = shell.path_var(&Platform::current()).to_string();
+    let mut script = ShellScript::new(shell, Platform::current());
+    for (key, value) in changes {
+        match value {
+            Some(value) if key == &path_var => {
+                let paths = std::env::split_paths(value).collect::<Vec<_>>();
+                script
+                    .set_path(&paths, PathModificationBehavior::Replace)
+                    .into_diagnostic()?;
+            }
+            Some(value) => {
+                script.set_env_var(key, value).into_diagnostic()?;
+            }
+            None => {
+                script.unset_env_var(key).into_diagnostic()?;
+            }
+        }
+    }
+    script.contents().into_diagnostic()
+}
+
+/// Generates a script that restores the variables recorded in the activation
+/// backup.
+fn generate_deactivation_script(
+    shell: ShellEnum,
+    backup: BTreeMap<String, Option<String>>,
+) -> miette::Result<String> {
+    let mut changes: IndexMap<String, Option<String>> = backup.into_iter().collect();
+    changes.insert(AUTO_MANIFEST_ENV.to_string(), None);
+    changes.insert(ACTIVATION_BACKUP_ENV.to_string(), None);
+    render_environment_changes(shell, &changes)
+}
+
+/// Compares the `current` environment with the environment after restoring
+/// the `backup` of a previous activation and applying the `activated`
+/// variables. Returns the changes sorted by name, together with the source
+/// that set the variable.
+fn diff_environment(
+    current: &HashMap<String, String>,
+    backup: &BTreeMap<String, Option<String>>,
+    activated: &IndexMap<String, (String, ActivationSource)>,
+) -> Vec<(String, EnvVarChange, Option<ActivationSource>)> {
+    let mut target = current.clone();
+    for (key, value) in backup {
+        match value {
+            Some(value) => target.insert(key.clone(), value.clone()),
+            None => target.remove(key),
+        };
+    }
+    for (key, (value, _)) in activated {
+        target.insert(key.clone(), value.clone());
+    }
+
+    current
+        .keys()
+        .chain(target.keys())
+        .filter(|key| ![AUTO_MANIFEST_ENV, ACTIVATION_BACKUP_ENV].contains(&key.as_str()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|key| {
+            let change = match (current.get(key), target.get(key)) {
+                (None, Some(new)) => EnvVarChange::Added(new.clone()),
+                (Some(old), Some(new)) if old != new => EnvVarChange::Modified {
+                    old: old.clone(),
+                    new: new.clone(),
+                },
+                (Some(old), None) => EnvVarChange::Removed(old.clone()),
+                _ => return None,
+            };
+            let source = activated.get(key).map(|(_, source)| source.clone());
+            Some((key.clone(), change, source))
+        })
+        .collect()
+}
+
+/// Prints the changes to the environment variables created by
+/// [`diff_environment`].
+fn print_environment_diff(diff: &[(String, EnvVarChange, Option<ActivationSource>)]) {
+    if diff.is_empty() {
+        eprintln!(
+            "{}Activating the environment doesn't change any environment variables",
+            console::style(console::Emoji("✔ ", "")).green()
+        );
+        return;
+    }
+
+    for (name, change, source) in diff {
+        let source = console::style(format!(
+            "({})",
+            source
+                .as_ref()
+                .map_or_else(|| "previous activation".to_string(), ToString::to_string)
+        ))
+        .dim();
+        match change {
+            EnvVarChange::Added(value) => {
+                println!("{} {name}={value} {source}", console::style("+").green())
+            }
+            EnvVarChange::Modified { old, new } => {
+                println!("{} {name}={new} {source}", console::style("~").yellow());
+                println!("    {}", console::style(format!("was: {old}")).dim());
+            }
+            EnvVarChange::Removed(value) => {
+                println!("{} {name}={value} {source}", console::style("-").red())
+            }
+        }
+    }
+}
+
+/// Returns the shell from the arguments or from the current process or the
+/// default if all fails.
+fn shell_or_default(shell: Option<ShellEnum>) -> ShellEnum {
+    shell.unwrap_or_else(|| {
+        ShellEnum::from_parent_process()
+            .unwrap_or_else(|| ShellEnum::from_env().unwrap_or_default())
+    })
+}
+
+/// Returns the hook printed by `pixi shell-hook --auto`.
+fn generate_auto_hook(
+    shell: &ShellEnum,
+    pixi_exe: &Path,
+    change_ps1: bool,
+) -> miette::Result<String> {
+    get_auto_hook(shell, pixi_exe, change_ps1).ok_or_else(|| {
+        miette::miette!(
+            help = "use `eval \"$(pixi shell-hook)\"` to activate an environment in other shells",
+            "the `--auto` hook is only supported for bash, zsh, fish and nushell"
+        )
+    })
+}
+
 /// Prints the activation script to the stdout.
 pub async fn execute(args: Args) -> miette::Result<()> {
     let config = args
         .prompt_config
         .merge_config(args.activation_config.into())
         .merge_config(args.prefix_update_config.config.clone().into());
+
+    if args.auto {
+        let shell = shell_or_default(args.shell);
+        let pixi_exe = std::env::current_exe().into_diagnostic()?;
+        let change_ps1 = Config::load_global().merge_config(config).change_ps1();
+        println!("{}", generate_auto_hook(&shell, &pixi_exe, change_ps1)?);
+        return Ok(());
+    }
+
+    if args.auto_update {
+        let shell = shell_or_default(args.shell);
+        let current_dir = std::env::current_dir().into_diagnostic()?;
+        let changes = auto_update_environment(current_dir, config).await?;
+        let output = match shell {
+            ShellEnum::NuShell(_) => render_environment_changes_json(&changes)?,
+            shell => render_environment_changes(shell, &changes)?,
+        };
+        print!("{}", output);
+        return Ok(());
+    }
+
+    if args.deactivate {
+        let shell = shell_or_default(args.shell);
+        if !supports_deactivation(&shell) {
+            miette::bail!(
+                help =
+                    "deactivation is supported for bash, zsh, fish, xonsh, powershell and nushell",
+                "`--deactivate` is not supported for cmd.exe"
+            );
+        }
+        let backup = activation_backup().ok_or_else(|| {
+            miette::miette!("there is no environment activated by `pixi shell-hook` to deactivate")
+        })?;
+        println!("{}", generate_deactivation_script(shell, backup)?);
+        return Ok(());
+    }
+
     let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
         .with_cli_config(config);
     let environment = project.environment_from_name_or_env_var(args.environment)?;
@@ -133,6 +584,14 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     )
     .await?;
 
+    if args.diff {
+        let current = std::env::vars().collect::<HashMap<_, _>>();
+        let backup = restore_activation_backup();
+        let activated = run_activation_with_sources(&environment).await?;
+        print_environment_diff(&diff_environment(&current, &backup, &activated));
+        return Ok(());
+    }
+
     let output = match args.json {
         true => {
             generate_environment_json(
@@ -145,7 +604,32 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         }
         // Skipping the activated environment caching for the script.
         // As it can still run scripts.
-        false => generate_activation_script(args.shell, &environment).await?,
+        false => {
+            let shell = shell_or_default(args.shell);
+            let activation = generate_activation_script(Some(shell.clone()), &environment).await?;
+            if !supports_deactivation(&shell) {
+                activation.script
+            } else {
+                // Record the values the activation overwrites, so the environment
+                // can be deactivated with `pixi shell-hook --deactivate`.
+                let backup = create_activation_backup(
+                    activation_backup().unwrap_or_default(),
+                    &activated_variables(&environment, &activation).await?,
+                );
+                let backup_path = write_activation_backup(
+                    &project.pixi_dir().join(ACTIVATION_BACKUP_DIR),
+                    &backup,
+                )?;
+                let backup_script = render_environment_changes(
+                    shell,
+                    &IndexMap::from([(
+                        ACTIVATION_BACKUP_ENV.to_string(),
+                        Some(backup_path.to_string_lossy().into_owned()),
+                    )]),
+                )?;
+                format!("{}\n{backup_script}", activation.script)
+            }
+        }
     };
 
     // Print the output - either a JSON object or a shell script
@@ -169,7 +653,8 @@ mod tests {
         let environment = project.default_environment();
         let script = generate_activation_script(Some(ShellEnum::Bash(Bash)), &environment)
             .await
-            .unwrap();
+            .unwrap()
+            .script;
         assert!(script.contains(&format!("export {path_var_name}=")));
         assert!(script.contains("export CONDA_PREFIX="));
 
@@ -178,38 +663,263 @@ mod tests {
             &environment,
         )
         .await
-        .unwrap();
+        .unwrap()
+        .script;
         assert!(script.contains(&format!("${{Env:{path_var_name}}}")));
         assert!(script.contains("${Env:CONDA_PREFIX}"));
 
         let script = generate_activation_script(Some(ShellEnum::Zsh(Zsh)), &environment)
             .await
-            .unwrap();
+            .unwrap()
+            .script;
         assert!(script.contains(&format!("export {path_var_name}=")));
         assert!(script.contains("export CONDA_PREFIX="));
 
         let script = generate_activation_script(Some(ShellEnum::Fish(Fish)), &environment)
             .await
-            .unwrap();
+            .unwrap()
+            .script;
         assert!(script.contains(&format!("set -gx {path_var_name} ")));
         assert!(script.contains("set -gx CONDA_PREFIX "));
 
         let script = generate_activation_script(Some(ShellEnum::Xonsh(Xonsh)), &environment)
             .await
-            .unwrap();
+            .unwrap()
+            .script;
         assert!(script.contains(&format!("${path_var_name} = ")));
         assert!(script.contains("$CONDA_PREFIX = "));
 
         let script = generate_activation_script(Some(ShellEnum::CmdExe(CmdExe)), &environment)
             .await
-            .unwrap();
+            .unwrap()
+            .script;
         assert!(script.contains(&format!("@SET \"{path_var_name}=")));
         assert!(script.contains("@SET \"CONDA_PREFIX="));
 
         let script = generate_activation_script(Some(ShellEnum::NuShell(NuShell)), &environment)
             .await
-            .unwrap();
+            .unwrap()
+            .script;
         assert!(script.contains(&format!("$env.{path_var_name} = ")));
         assert!(script.contains("$env.CONDA_PREFIX = "));
     }
+
+    #[test]
+    fn test_activation_backup_roundtrip() {
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup = BTreeMap::from([
+            ("PATH".to_string(), Some("/usr/bin:/bin".repeat(1000))),
+            (
+                "QUOTED".to_string(),
+                Some("it's \"quoted\" $HOME".to_string()),
+            ),
+            ("CONDA_PREFIX".to_string(), None),
+        ]);
+        let path = write_activation_backup(backup_dir.path(), &backup).unwrap();
+        assert!(path.starts_with(backup_dir.path()));
+        // Only the path ends up in the environment, regardless of the size of
+        // the backup
+        assert!(path.to_string_lossy().len() < 8191);
+        assert_eq!(read_activation_backup(&path), Some(backup.clone()));
+
+        // The same backup is stored in the same file
+        assert_eq!(
+            write_activation_backup(backup_dir.path(), &backup).unwrap(),
+            path
+        );
+        assert_eq!(
+            read_activation_backup(&backup_dir.path().join("missing.json")),
+            None
+        );
+
+        assert!(supports_deactivation(&ShellEnum::Bash(Bash)));
+        assert!(!supports_deactivation(&ShellEnum::CmdExe(CmdExe)));
+    }
+
+    #[test]
+    fn test_auto_hook() {
+        let pixi_exe = Path::new("/opt/pixi/bin/pixi");
+        for shell in [
+            ShellEnum::Bash(Bash),
+            ShellEnum::Zsh(Zsh),
+            ShellEnum::Fish(Fish),
+            ShellEnum::NuShell(NuShell),
+        ] {
+            let hook = generate_auto_hook(&shell, pixi_exe, true).unwrap();
+            assert!(hook.contains("\"/opt/pixi/bin/pixi\" shell-hook --auto-update"));
+            assert!(!hook.contains("__PIXI_"));
+        }
+        assert!(generate_auto_hook(&ShellEnum::CmdExe(CmdExe), pixi_exe, true).is_err());
+    }
+
+    #[test]
+    fn test_render_environment_changes() {
+        let path_var_name = ShellEnum::Bash(Bash).path_var(&Platform::current());
+        let path = std::env::join_paths(["/env/bin", "/usr/bin"]).unwrap();
+        let changes = IndexMap::from([
+            (
+                path_var_name.to_string(),
+                Some(path.to_string_lossy().into_owned()),
+            ),
+            ("CONDA_PREFIX".to_string(), Some("/env".to_string())),
+            ("PIXI_ACTIVATION_BACKUP".to_string(), None),
+        ]);
+
+        let script = render_environment_changes(ShellEnum::Bash(Bash), &changes).unwrap();
+        assert!(script.contains(&format!("export {path_var_name}=")));
+        assert!(script.contains("export CONDA_PREFIX="));
+        assert!(script.contains("unset PIXI_ACTIVATION_BACKUP"));
+
+        let json = render_environment_changes_json(&changes).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            json["set"][path_var_name],
+            serde_json::json!(["/env/bin", "/usr/bin"])
+        );
+        assert_eq!(json["set"]["CONDA_PREFIX"], "/env");
+        assert_eq!(json["unset"], serde_json::json!(["PIXI_ACTIVATION_BACKUP"]));
+    }
+
+    #[test]
+    fn test_deactivation_script() {
+        let backup = BTreeMap::from([
+            ("CONDA_PREFIX".to_string(), None),
+            ("FOO".to_string(), Some("bar".to_string())),
+        ]);
+        let script = generate_deactivation_script(ShellEnum::Bash(Bash), backup.clone()).unwrap();
+        assert!(script.contains("unset CONDA_PREFIX"));
+        assert!(script.contains("export FOO="));
+        assert!(script.contains("unset PIXI_ACTIVATION_BACKUP"));
+
+        let script = generate_deactivation_script(ShellEnum::Fish(Fish), backup.clone()).unwrap();
+        assert!(script.contains("set -e CONDA_PREFIX"));
+        assert!(script.contains("set -gx FOO "));
+
+        let script = generate_deactivation_script(ShellEnum::NuShell(NuShell), backup).unwrap();
+        assert!(script.contains("hide-env CONDA_PREFIX"));
+        assert!(script.contains("$env.FOO = "));
+    }
+
+    #[test]
+    fn test_diff_environment() {
+        let current = HashMap::from([
+            ("PATH".to_string(), "/old/bin:/usr/bin".to_string()),
+            ("OLD_ONLY".to_string(), "1".to_string()),
+            ("UNCHANGED".to_string(), "1".to_string()),
+            ("PIXI_ACTIVATION_BACKUP".to_string(), "00".to_string()),
+        ]);
+        // A previous activation added `OLD_ONLY` and changed `PATH`
+        let backup = BTreeMap::from([
+            ("OLD_ONLY".to_string(), None),
+            ("PATH".to_string(), Some("/usr/bin".to_string())),
+        ]);
+        let activated = IndexMap::from([
+            (
+                "PATH".to_string(),
+                ("/new/bin:/usr/bin".to_string(), ActivationSource::Prefix),
+            ),
+            (
+                "FOO".to_string(),
+                ("bar".to_string(), ActivationSource::ActivationEnv),
+            ),
+            (
+                "UNCHANGED".to_string(),
+                (
+                    "1".to_string(),
+                    ActivationSource::Script(PathBuf::from("activate.sh")),
+                ),
+            ),
+        ]);
+
+        let diff = diff_environment(&current, &backup, &activated);
+        assert_eq!(
+            diff,
+            vec![
+                (
+                    "FOO".to_string(),
+                    EnvVarChange::Added("bar".to_string()),
+                    Some(ActivationSource::ActivationEnv)
+                ),
+                (
+                    "OLD_ONLY".to_string(),
+                    EnvVarChange::Removed("1".to_string()),
+                    None
+                ),
+                (
+                    "PATH".to_string(),
+                    EnvVarChange::Modified {
+                        old: "/old/bin:/usr/bin".to_string(),
+                        new: "/new/bin:/usr/bin".to_string()
+                    },
+                    Some(ActivationSource::Prefix)
+                ),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_deactivation_restores_script_variables() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let manifest_path = project_dir.path().join("pixi.toml");
+        fs_err::write(
+            &manifest_path,
+            format!(
+                r#"
+                [project]
+                name = "test"
+                channels = []
+                platforms = ["{}"]
+
+                [activation]
+                scripts = ["activate.sh"]
+                "#,
+                Platform::current()
+            ),
+        )
+        .unwrap();
+        fs_err::write(
+            project_dir.path().join("activate.sh"),
+            "export PIXI_TEST_SCRIPT_VAR=from-script\n",
+        )
+        .unwrap();
+
+        let project = Project::from_path(&manifest_path).unwrap();
+        let environment = project.default_environment();
+        let activation = generate_activation_script(Some(ShellEnum::Bash(Bash)), &environment)
+            .await
+            .unwrap();
+        assert!(!activation
+            .variables
+            .contains(&"PIXI_TEST_SCRIPT_VAR".to_string()));
+
+        // The variable exported by the script is part of the backup, so it is
+        // unset again by the deactivation
+        let variables = activated_variables(&environment, &activation)
+            .await
+            .unwrap();
+        assert!(variables.contains("PIXI_TEST_SCRIPT_VAR"));
+        let backup = create_activation_backup(BTreeMap::new(), &variables);
+        assert_eq!(backup.get("PIXI_TEST_SCRIPT_VAR"), Some(&None));
+        let script = generate_deactivation_script(ShellEnum::Bash(Bash), backup).unwrap();
+        assert!(script.contains("unset PIXI_TEST_SCRIPT_VAR"));
+    }
+
+    #[test]
+    fn test_create_activation_backup() {
+        let existing = BTreeMap::from([(
+            "PIXI_TEST_BACKUP_VAR".to_string(),
+            Some("original".to_string()),
+        )]);
+        let keys = [
+            "PIXI_TEST_BACKUP_VAR".to_string(),
+            "PIXI_TEST_BACKUP_UNSET_VAR".to_string(),
+        ];
+        let backup = create_activation_backup(existing, keys.iter());
+        assert_eq!(
+            backup.get("PIXI_TEST_BACKUP_VAR"),
+            Some(&Some("original".to_string()))
+        );
+        assert_eq!(backup.get("PIXI_TEST_BACKUP_UNSET_VAR"), Some(&None));
+    }
 }
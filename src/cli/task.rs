@@ -88,6 +88,10 @@ pub struct AddArgs {
     /// Isolate the task from the shell environment, and only use the pixi environment to run the task
     #[arg(long)]
     pub clean_env: bool,
+
+    /// The environment(s) the task should always run in, use --environment multiple times to run the task in more than one environment
+    #[arg(long, short)]
+    pub environment: Vec<EnvironmentName>,
 }
 
 /// Parse a single key-value pair
@@ -166,6 +170,7 @@ impl From<AddArgs> for Task {
             && value.cwd.is_none()
             && value.env.is_empty()
             && description.is_none()
+            && value.environment.is_empty()
         {
             Self::Plain(cmd_args)
         } else {
@@ -190,6 +195,10 @@ impl From<AddArgs> for Task {
                 env,
                 description,
                 clean_env,
+                env_passthrough: Vec::new(),
+                environment: value.environment,
+                run_if: None,
+                template: false,
             })
         }
     }
@@ -0,0 +1,392 @@
+use std::{
+    collections::BTreeMap,
+    io::{stdout, Write},
+    str::FromStr,
+};
+
+use clap::Parser;
+use console::Color;
+use fancy_display::FancyDisplay;
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use pixi_manifest::{FeatureName, HasFeaturesIter, SpecType};
+use rattler_conda_types::{
+    Channel, MatchSpec, Matches, NamelessMatchSpec, PackageName, ParseStrictness, Platform,
+    RepoDataRecord, Version,
+};
+
+use crate::{
+    cli::cli_config::{PrefixUpdateConfig, ProjectConfig},
+    lock_file::{PypiRecord, UpdateLockFileOptions},
+    project::Environment,
+    repodata::Repodata,
+    Project,
+};
+
+/// Explain why a package was locked at its version
+///
+/// Lists every spec in the manifest and every `depends` and `constrains` of
+/// the locked packages that mentions the package. For conda packages the
+/// newer versions in the channels are checked against these constraints, to
+/// show which constraints prevent a newer version from being selected.
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The name of the package to explain
+    pub package: String,
+
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// The environment to explain the package in. Defaults to the default
+    /// environment.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    /// The platform to explain the package for. Defaults to the current
+    /// platform.
+    #[arg(long, short)]
+    pub platform: Option<Platform>,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// A spec that mentions the package, with where it comes from.
+struct Constraint {
+    source: String,
+    spec: String,
+    /// The spec to check newer conda versions against.
+    match_spec: Option<MatchSpec>,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())
+        .wrap_err("Failed to load project")?;
+    let environment = project
+        .environment_from_name_or_env_var(args.environment)
+        .wrap_err("Environment not found")?;
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            ..UpdateLockFileOptions::default()
+        })
+        .await
+        .wrap_err("Failed to update lock file")?;
+
+    let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+    let Some(locked_environment) = lock_file.lock_file.environment(environment.name().as_str())
+    else {
+        miette::bail!(
+            "the environment {} is not in the lock file",
+            environment.name().fancy_display()
+        );
+    };
+    let conda_records = locked_environment
+        .conda_repodata_records_for_platform(platform)
+        .into_diagnostic()?
+        .unwrap_or_default();
+    let pypi_packages = locked_environment
+        .pypi_packages_for_platform(platform)
+        .unwrap_or_default();
+
+    let conda_name = PackageName::from_str(&args.package).ok();
+    let pypi_name = pep508_rs::PackageName::from_str(&args.package).ok();
+    if let Some(record) = conda_records
+        .iter()
+        .find(|record| Some(&record.package_record.name) == conda_name.as_ref())
+    {
+        explain_conda(&project, &environment, platform, record, &conda_records).await?;
+    } else if let Some((package, _)) = pypi_packages
+        .iter()
+        .find(|(package, _)| Some(&package.name) == pypi_name.as_ref())
+    {
+        let constraints = pypi_constraints(&environment, platform, &package.name, &pypi_packages);
+        println!(
+            "{} {} (pypi) is locked in {} ({})",
+            console::style(package.name.as_dist_info_name()).bold(),
+            console::style(&package.version).fg(Color::Blue),
+            environment.name().fancy_display(),
+            platform
+        );
+        print_constraints(&package.name.to_string(), &constraints, &[])?;
+        println!(
+            "\nNewer versions are only checked for conda packages, `pixi outdated` shows the newest version on the indexes."
+        );
+    } else {
+        miette::bail!(
+            help = "`pixi list` shows the locked packages",
+            "'{}' is not locked in {} ({platform})",
+            args.package,
+            environment.name().fancy_display()
+        );
+    }
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+async fn explain_conda(
+    project: &Project,
+    environment: &Environment<'_>,
+    platform: Platform,
+    locked: &RepoDataRecord,
+    records: &[RepoDataRecord],
+) -> miette::Result<()> {
+    let name = &locked.package_record.name;
+    let locked_version = locked.package_record.version.version();
+    let constraints = conda_constraints(project, environment, platform, name, records);
+
+    // The versions in the channels that are newer than the locked version.
+    let channel_config = project.channel_config();
+    let channels: Vec<Channel> = environment
+        .channels()
+        .into_iter()
+        .map(|channel| channel.clone().into_channel(&channel_config))
+        .try_collect()
+        .into_diagnostic()?;
+    let repodata = project
+        .repodata_gateway()?
+        .query(
+            channels,
+            [platform, Platform::NoArch],
+            [MatchSpec::from_nameless(
+                NamelessMatchSpec::default(),
+                Some(name.clone()),
+            )],
+        )
+        .recursive(false)
+        .await
+        .into_diagnostic()?;
+    let mut newer: BTreeMap<Version, Vec<&RepoDataRecord>> = BTreeMap::new();
+    for record in repodata.iter().flat_map(|repodata| repodata.iter()) {
+        let version = record.package_record.version.version();
+        if version > locked_version {
+            newer.entry(version.clone()).or_default().push(record);
+        }
+    }
+
+    // A constraint excludes a version if it doesn't match any of its builds.
+    let excluded_by = |records: &[&RepoDataRecord]| -> Vec<usize> {
+        constraints
+            .iter()
+            .positions(|constraint| {
+                constraint.match_spec.as_ref().is_some_and(|spec| {
+                    !records
+                        .iter()
+                        .any(|record| spec.matches(&record.package_record))
+                })
+            })
+            .collect()
+    };
+
+    // The constraints that exclude the next version are the tightest.
+    let tightest = newer
+        .values()
+        .next()
+        .map(|records| excluded_by(records))
+        .unwrap_or_default();
+
+    println!(
+        "{} {} {} (conda) is locked in {} ({})",
+        console::style(name.as_normalized()).bold(),
+        console::style(&locked.package_record.version).fg(Color::Yellow),
+        locked.package_record.build,
+        environment.name().fancy_display(),
+        platform
+    );
+    print_constraints(name.as_normalized(), &constraints, &tightest)?;
+    println!();
+
+    let Some((newest, newest_records)) = newer.iter().next_back() else {
+        println!(
+            "{}{} is the newest version of {} in the channels.",
+            console::style(console::Emoji("✔ ", "")).green(),
+            locked_version,
+            name.as_normalized()
+        );
+        return Ok(());
+    };
+
+    println!(
+        "There {} {} newer {} of {} in the channels, the newest is {newest}:",
+        if newer.len() == 1 { "is" } else { "are" },
+        newer.len(),
+        if newer.len() == 1 {
+            "version"
+        } else {
+            "versions"
+        },
+        name.as_normalized(),
+    );
+    // Explain the newest version and the next version after the locked one.
+    for (version, records) in [(newest, newest_records)].into_iter().chain(
+        newer
+            .iter()
+            .next()
+            .filter(|(version, _)| *version != newest),
+    ) {
+        let excluding = excluded_by(records);
+        if excluding.is_empty() {
+            println!(
+                "  {version} is allowed by all constraints on {}, it is excluded by its own dependencies or was published after the lock file was updated; `pixi update {}` selects it if possible",
+                name.as_normalized(),
+                name.as_normalized()
+            );
+        } else {
+            println!(
+                "  {version} is excluded by {}",
+                excluding
+                    .iter()
+                    .map(|&index| format!(
+                        "{} `{}`",
+                        constraints[index].source, constraints[index].spec
+                    ))
+                    .join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Collects the specs in the manifest and the `depends` and `constrains` of
+/// the locked conda packages that mention the package.
+fn conda_constraints(
+    project: &Project,
+    environment: &Environment<'_>,
+    platform: Platform,
+    name: &PackageName,
+    records: &[RepoDataRecord],
+) -> Vec<Constraint> {
+    let channel_config = project.channel_config();
+    let mut constraints = Vec::new();
+    for feature in environment.features() {
+        for spec_type in SpecType::all() {
+            let Some(dependencies) = feature.dependencies(Some(spec_type), Some(platform)) else {
+                continue;
+            };
+            let Some(spec) = dependencies.get(name) else {
+                continue;
+            };
+            let match_spec = spec
+                .clone()
+                .try_into_nameless_match_spec(&channel_config)
+                .ok()
+                .flatten()
+                .map(|spec| MatchSpec::from_nameless(spec, Some(name.clone())));
+            constraints.push(Constraint {
+                source: manifest_table(&feature.name, spec_type.name()),
+                spec: match &match_spec {
+                    Some(match_spec) => match_spec.to_string(),
+                    None => format!("{} (source)", name.as_normalized()),
+                },
+                match_spec,
+            });
+        }
+    }
+
+    for record in records {
+        let package_record = &record.package_record;
+        for (kind, specs) in [
+            ("depends", &package_record.depends),
+            ("constrains", &package_record.constrains),
+        ] {
+            for spec in specs {
+                let Ok(match_spec) = MatchSpec::from_str(spec, ParseStrictness::Lenient) else {
+                    continue;
+                };
+                if match_spec.name.as_ref() != Some(name) {
+                    continue;
+                }
+                constraints.push(Constraint {
+                    source: format!(
+                        "{} {} {} ({kind})",
+                        package_record.name.as_normalized(),
+                        package_record.version,
+                        package_record.build
+                    ),
+                    spec: spec.clone(),
+                    match_spec: Some(match_spec),
+                });
+            }
+        }
+    }
+    constraints
+}
+
+/// Collects the specs in the manifest and the requirements of the locked PyPI
+/// packages that mention the package.
+fn pypi_constraints(
+    environment: &Environment<'_>,
+    platform: Platform,
+    name: &pep508_rs::PackageName,
+    packages: &[PypiRecord],
+) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+    for feature in environment.features() {
+        let Some(dependencies) = feature.pypi_dependencies(Some(platform)) else {
+            continue;
+        };
+        for (dependency, requirement) in dependencies.iter() {
+            if dependency.as_normalized() == name {
+                constraints.push(Constraint {
+                    source: manifest_table(&feature.name, "pypi-dependencies"),
+                    spec: format!("{} = {requirement}", dependency.as_source()),
+                    match_spec: None,
+                });
+            }
+        }
+    }
+
+    for (package, _) in packages {
+        for requirement in &package.requires_dist {
+            if &requirement.name == name {
+                constraints.push(Constraint {
+                    source: format!("{} {} (requires-dist)", package.name, package.version),
+                    spec: requirement.to_string(),
+                    match_spec: None,
+                });
+            }
+        }
+    }
+    constraints
+}
+
+/// Returns the name of the table in the manifest that contains a spec.
+fn manifest_table(feature: &FeatureName, table: &str) -> String {
+    match feature {
+        FeatureName::Default => format!("manifest [{table}]"),
+        FeatureName::Named(feature) => format!("manifest [feature.{feature}.{table}]"),
+    }
+}
+
+fn print_constraints(
+    name: &str,
+    constraints: &[Constraint],
+    tightest: &[usize],
+) -> miette::Result<()> {
+    println!();
+    if constraints.is_empty() {
+        println!("Nothing constrains the version of {name}.");
+        return Ok(());
+    }
+
+    println!("Constraints on {name}:");
+    let mut writer = tabwriter::TabWriter::new(stdout());
+    for (index, constraint) in constraints.iter().enumerate() {
+        let result = if tightest.contains(&index) {
+            writeln!(
+                writer,
+                "  {}\t{}\t{}",
+                console::style(&constraint.source).bold(),
+                console::style(&constraint.spec).fg(Color::Red).bold(),
+                console::style("(tightest)").fg(Color::Red)
+            )
+        } else {
+            writeln!(writer, "  {}\t{}\t", constraint.source, constraint.spec)
+        };
+        result.into_diagnostic()?;
+    }
+    writer.flush().into_diagnostic()
+}
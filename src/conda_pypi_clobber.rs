@@ -1,12 +1,15 @@
-use std::path::PathBuf;
+use std::{fmt::Display, path::PathBuf};
 
+use indexmap::IndexMap;
+use itertools::Itertools;
 use rattler_conda_types::PrefixRecord;
-use uv_distribution_types::CachedDist;
+use uv_distribution_types::{CachedDist, Name};
+use uv_normalize::PackageName;
 use uv_python::PythonEnvironment;
 
 use crate::install_wheel::get_wheel_info;
 
-use ahash::{AHashMap, AHashSet};
+use ahash::AHashMap;
 
 #[derive(Default, Debug)]
 pub(crate) struct PypiCondaClobberRegistry {
@@ -33,16 +36,17 @@ impl PypiCondaClobberRegistry {
     }
 
     /// Check if the installation of the wheels is going to clobber any installed conda package
-    /// and return the names of the packages that are going to be clobbered
-    /// this allow to warn the user about the overwriting of already installed packages
+    /// and return the overlapping packages with the clobbered paths.
+    /// This allows to act on the overwriting of already installed packages
     /// in case of wrong mapping data
     /// or malicious packages
     pub(crate) fn clobber_on_installation(
         self,
-        wheels: Vec<CachedDist>,
+        wheels: &[CachedDist],
         venv: &PythonEnvironment,
-    ) -> miette::Result<Option<AHashSet<String>>> {
-        let mut clobber_packages: AHashSet<String> = AHashSet::default();
+    ) -> miette::Result<Vec<ClobberedPackage>> {
+        let mut clobbered: IndexMap<(PackageName, rattler_conda_types::PackageName), Vec<PathBuf>> =
+            IndexMap::new();
 
         for wheel in wheels {
             let Ok(Some(whl_info)) = get_wheel_info(wheel.path(), venv) else {
@@ -53,13 +57,51 @@ impl PypiCondaClobberRegistry {
                 let path_to_clobber = whl_info.1.join(entry.path);
 
                 if let Some(name) = self.paths_registry.get(&path_to_clobber) {
-                    clobber_packages.insert(name.as_normalized().to_string());
+                    clobbered
+                        .entry((wheel.name().clone(), name.clone()))
+                        .or_default()
+                        .push(path_to_clobber);
                 }
             }
         }
-        if clobber_packages.is_empty() {
-            return Ok(None);
+
+        Ok(clobbered
+            .into_iter()
+            .map(|((pypi_package, conda_package), paths)| ClobberedPackage {
+                pypi_package,
+                conda_package,
+                paths,
+            })
+            .collect())
+    }
+}
+
+/// A PyPI package that overwrites the files of an installed conda package.
+#[derive(Debug, Clone)]
+pub(crate) struct ClobberedPackage {
+    pub pypi_package: PackageName,
+    pub conda_package: rattler_conda_types::PackageName,
+    /// The paths relative to the prefix that are installed by both packages.
+    pub paths: Vec<PathBuf>,
+}
+
+impl Display for ClobberedPackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const MAX_PATHS: usize = 5;
+        write!(
+            f,
+            "{} (pypi) overwrites {} (conda): {}",
+            self.pypi_package,
+            self.conda_package.as_normalized(),
+            self.paths
+                .iter()
+                .take(MAX_PATHS)
+                .map(|path| path.display())
+                .join(", ")
+        )?;
+        if self.paths.len() > MAX_PATHS {
+            write!(f, " and {} more", self.paths.len() - MAX_PATHS)?;
         }
-        Ok(Some(clobber_packages))
+        Ok(())
     }
 }
@@ -1,6 +1,6 @@
 use crate::{
     install_pypi,
-    lock_file::{UpdateLockFileOptions, UpdateMode, UvResolutionContext},
+    lock_file::{InstallFilter, UpdateLockFileOptions, UpdateMode, UvResolutionContext},
     prefix::Prefix,
     project::{grouped_environment::GroupedEnvironment, Environment, HasProjectRef},
     rlimit::try_increase_rlimit_to_sensible,
@@ -11,8 +11,9 @@ use fancy_display::FancyDisplay;
 use fs_err as fs;
 use miette::{IntoDiagnostic, WrapErr};
 use pixi_consts::consts;
-use pixi_manifest::{EnvironmentName, FeaturesExt, SystemRequirements};
+use pixi_manifest::{EnvironmentName, FeaturesExt, PypiClobberPolicy, SystemRequirements};
 use pixi_progress::{await_in_progress, global_multi_progress};
+use pypi_modifiers::pypi_tags::is_python_record;
 use rattler::{
     install::{DefaultProgressFormatter, IndicatifReporter, Installer, PythonInfo, Transaction},
     package_cache::PackageCache,
@@ -61,6 +62,9 @@ pub async fn verify_prefix_location_unchanged(environment_dir: &Path) -> miette:
         }
         // Check if the path in the file aligns with the current path.
         Ok(p) if prefix_file.starts_with(&p) => Ok(()),
+        // Shared environments are linked into the project, so compare the
+        // resolved paths.
+        Ok(p) if is_linked_prefix(&prefix_file, Path::new(&p)) => Ok(()),
         Ok(p) => {
             let path = Path::new(&p);
             prefix_location_changed(environment_dir, path.parent().unwrap_or(path)).await
@@ -68,6 +72,18 @@ pub async fn verify_prefix_location_unchanged(environment_dir: &Path) -> miette:
     }
 }
 
+/// Returns true if the prefix file resolves to a file in the recorded prefix,
+/// which is the case when the environment directory links to it.
+fn is_linked_prefix(prefix_file: &Path, recorded: &Path) -> bool {
+    match (
+        dunce::canonicalize(prefix_file),
+        dunce::canonicalize(recorded),
+    ) {
+        (Ok(prefix_file), Ok(recorded)) => prefix_file.starts_with(recorded),
+        _ => false,
+    }
+}
+
 /// Called when the prefix has moved to a new location.
 ///
 /// Allows interactive users to delete the location and continue.
@@ -167,6 +183,10 @@ fn create_history_file(environment_dir: &Path) -> miette::Result<()> {
 #[derive(Debug, Hash, Serialize, Deserialize, PartialEq, Eq)]
 pub struct LockedEnvironmentHash(String);
 impl LockedEnvironmentHash {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+
     pub(crate) fn from_environment(
         environment: rattler_lock::Environment,
         platform: Platform,
@@ -214,6 +234,28 @@ pub(crate) struct EnvironmentFile {
     pub(crate) pixi_version: String,
     /// The hash of the lock file that was used to create the environment.
     pub(crate) environment_lock_file_hash: LockedEnvironmentHash,
+    /// Whether the PyPI packages were skipped when installing the environment.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) skipped_pypi: bool,
+    /// Whether the editable PyPI packages were skipped when installing the
+    /// environment.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) skipped_editable: bool,
+    /// Whether the PyPI packages that conda packages provide were skipped
+    /// because of the `prefer-conda` clobber policy.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) skipped_provided_by_conda: bool,
+}
+
+impl EnvironmentFile {
+    /// Returns true if all packages of the environment were installed, the
+    /// PyPI packages that conda packages provide are only installed without
+    /// the `prefer-conda` clobber policy.
+    pub(crate) fn is_complete(&self, clobber_policy: PypiClobberPolicy) -> bool {
+        !self.skipped_pypi
+            && !self.skipped_editable
+            && self.skipped_provided_by_conda == (clobber_policy == PypiClobberPolicy::PreferConda)
+    }
 }
 
 /// The path to the environment file in the `conda-meta` directory of the environment.
@@ -390,10 +432,29 @@ impl LockFileUsage {
 /// `None` it will be downloaded. If the lock-file is not updated, the
 /// `sparse_repo_data` is ignored.
 pub async fn get_update_lock_file_and_prefix<'env>(
+    environment: &Environment<'env>,
+    lock_file_usage: LockFileUsage,
+    no_install: bool,
+    update_mode: UpdateMode,
+) -> miette::Result<(LockFileDerivedData<'env>, Prefix)> {
+    get_update_lock_file_and_filtered_prefix(
+        environment,
+        lock_file_usage,
+        no_install,
+        update_mode,
+        InstallFilter::default(),
+    )
+    .await
+}
+
+/// Same as [`get_update_lock_file_and_prefix`] but only installs the parts of
+/// the environment that are selected by the `filter`.
+pub async fn get_update_lock_file_and_filtered_prefix<'env>(
     environment: &Environment<'env>,
     lock_file_usage: LockFileUsage,
     mut no_install: bool,
     update_mode: UpdateMode,
+    filter: InstallFilter,
 ) -> miette::Result<(LockFileDerivedData<'env>, Prefix)> {
     let current_platform = environment.best_platform();
     let project = environment.project();
@@ -420,7 +481,9 @@ pub async fn get_update_lock_file_and_prefix<'env>(
     let prefix = if no_install {
         Prefix::new(environment.dir())
     } else {
-        lock_file.prefix(environment, update_mode).await?
+        lock_file
+            .filtered_prefix(environment, update_mode, filter)
+            .await?
     };
 
     Ok((lock_file, prefix))
@@ -442,6 +505,8 @@ pub async fn update_prefix_pypi(
     lock_file_dir: &Path,
     platform: Platform,
     non_isolated_packages: Option<Vec<String>>,
+    clobber_policy: PypiClobberPolicy,
+    skip_editable: bool,
 ) -> miette::Result<()> {
     // If we have changed interpreter, we need to uninstall all site-packages from
     // the old interpreter We need to do this before the pypi prefix update,
@@ -507,6 +572,8 @@ pub async fn update_prefix_pypi(
                 environment_variables,
                 platform,
                 non_isolated_packages,
+                clobber_policy,
+                skip_editable,
             )
         },
     )
@@ -603,6 +670,19 @@ impl PythonStatus {
         }
     }
 
+    /// Determine the [`PythonStatus`] of a prefix whose conda packages are not
+    /// changed, from the packages that are installed in it.
+    pub(crate) fn from_installed(installed: &[PrefixRecord], platform: Platform) -> Self {
+        installed
+            .iter()
+            .find(|record| is_python_record(&record.repodata_record.package_record))
+            .and_then(|record| {
+                PythonInfo::from_python_record(&record.repodata_record.package_record, platform)
+                    .ok()
+            })
+            .map_or(PythonStatus::DoesNotExist, PythonStatus::Unchanged)
+    }
+
     /// Returns the info of the current situation (e.g. after the transaction
     /// completed).
     pub(crate) fn current_info(&self) -> Option<&PythonInfo> {
@@ -0,0 +1,297 @@
+//! A store of environments that are shared between projects. Environments in
+//! the store are keyed by the hash of the locked environment, so projects
+//! with the same lock-file link to the same installation instead of
+//! installing it again.
+
+use std::path::{Path, PathBuf};
+
+use fs_err as fs;
+use miette::{Context, IntoDiagnostic};
+use pixi_config::Config;
+use pixi_utils::PrefixGuard;
+use rattler_conda_types::Platform;
+use rattler_lock::UrlOrPath;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::environment::LockedEnvironmentHash;
+
+/// The directory in the store that records which environment directories
+/// link to an environment in the store.
+const REFERENCES_DIR: &str = "refs";
+
+/// The file of the [`PrefixGuard`] in the directory of an environment.
+const PREFIX_GUARD_FILE: &str = ".guard";
+
+/// An environment in the store together with the environment directories
+/// that currently link to it.
+#[derive(Debug)]
+pub(crate) struct StoredEnvironment {
+    pub(crate) path: PathBuf,
+    pub(crate) references: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EnvironmentStore {
+    root: PathBuf,
+}
+
+impl EnvironmentStore {
+    pub(crate) fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the store if shared environments are enabled in the config.
+    pub(crate) fn from_config(config: &Config) -> miette::Result<Option<Self>> {
+        if !config.shared_environments() {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(
+            config.detached_environments().store_path()?,
+        )))
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory of the environment with the given hash.
+    pub(crate) fn prefix_dir(&self, hash: &LockedEnvironmentHash) -> PathBuf {
+        self.root.join(hash.as_str())
+    }
+
+    fn references_dir(&self, name: &str) -> PathBuf {
+        self.root.join(REFERENCES_DIR).join(name)
+    }
+
+    /// Links the environment directory of a project to the environment with
+    /// the given hash in the store and records the reference. An existing
+    /// environment directory is replaced.
+    pub(crate) fn link(
+        &self,
+        hash: &LockedEnvironmentHash,
+        environment_dir: &Path,
+    ) -> miette::Result<()> {
+        let target = self.prefix_dir(hash);
+        match fs::read_link(environment_dir) {
+            Ok(existing) if existing == target => {}
+            Ok(_) => remove_symlink(environment_dir)?,
+            Err(_) if environment_dir.exists() => {
+                tracing::info!(
+                    "replacing '{}' with a link to the shared environment '{}'",
+                    environment_dir.display(),
+                    target.display()
+                );
+                fs::remove_dir_all(environment_dir).into_diagnostic()?;
+            }
+            Err(_) => {}
+        }
+
+        if !environment_dir.is_symlink() {
+            if let Some(parent) = environment_dir.parent() {
+                fs::create_dir_all(parent).into_diagnostic()?;
+            }
+            create_symlink(&target, environment_dir)
+                .into_diagnostic()
+                .with_context(|| {
+                    format!(
+                        "failed to link '{}' to the shared environment '{}'",
+                        environment_dir.display(),
+                        target.display()
+                    )
+                })?;
+        }
+
+        let references_dir = self.references_dir(hash.as_str());
+        fs::create_dir_all(&references_dir).into_diagnostic()?;
+        fs::write(
+            references_dir.join(reference_name(environment_dir)),
+            environment_dir.to_string_lossy().as_bytes(),
+        )
+        .into_diagnostic()
+    }
+
+    /// Removes the environment directory if it links into the store, so the
+    /// environment can be installed in the project again. Returns true if a
+    /// link was removed.
+    pub(crate) fn unlink(&self, environment_dir: &Path) -> miette::Result<bool> {
+        match fs::read_link(environment_dir) {
+            Ok(target) if target.starts_with(&self.root) => {
+                remove_symlink(environment_dir)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns the environments in the store with the environment directories
+    /// that still link to them. References of environment directories that
+    /// were removed or link elsewhere are forgotten.
+    pub(crate) fn environments(&self) -> miette::Result<Vec<StoredEnvironment>> {
+        let mut environments = Vec::new();
+        for entry in fs::read_dir(&self.root).into_iter().flatten() {
+            let entry = entry.into_diagnostic()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == REFERENCES_DIR || !entry.file_type().into_diagnostic()?.is_dir() {
+                continue;
+            }
+
+            let path = entry.path();
+            let references = self.references(&name, &path)?;
+            environments.push(StoredEnvironment { path, references });
+        }
+        Ok(environments)
+    }
+
+    /// Returns the environment directories that still link to the
+    /// environment at `path`, forgetting the stale references.
+    fn references(&self, name: &str, path: &Path) -> miette::Result<Vec<PathBuf>> {
+        let mut references = Vec::new();
+        for reference in fs::read_dir(self.references_dir(name))
+            .into_iter()
+            .flatten()
+        {
+            let reference = reference.into_diagnostic()?.path();
+            let environment_dir = PathBuf::from(fs::read_to_string(&reference).into_diagnostic()?);
+            if fs::read_link(&environment_dir).is_ok_and(|target| target == path) {
+                references.push(environment_dir);
+            } else {
+                fs::remove_file(&reference).into_diagnostic()?;
+            }
+        }
+        Ok(references)
+    }
+
+    /// Removes an environment and its references from the store. The prefix
+    /// guard of the environment is held while it is removed, so an install
+    /// of the same environment either finishes first or installs it again
+    /// afterwards. Environments that were linked again in the meantime are
+    /// not removed.
+    pub(crate) fn remove(&self, environment: &StoredEnvironment) -> miette::Result<()> {
+        let name = environment
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut guard = PrefixGuard::new(&environment.path)
+            .into_diagnostic()
+            .context("failed to create prefix guard")?;
+        let write_guard = guard
+            .write()
+            .into_diagnostic()
+            .context("failed to acquire write lock to prefix guard")?;
+
+        if !self.references(&name, &environment.path)?.is_empty() {
+            miette::bail!(
+                "'{}' is in use by a project again",
+                environment.path.display()
+            );
+        }
+
+        let references_dir = self.references_dir(&name);
+        if references_dir.exists() {
+            fs::remove_dir_all(references_dir).into_diagnostic()?;
+        }
+        // The guard file stays open until the lock is released.
+        for entry in fs::read_dir(&environment.path).into_diagnostic()? {
+            let entry = entry.into_diagnostic()?;
+            if entry.file_name() == PREFIX_GUARD_FILE {
+                continue;
+            }
+            if entry.file_type().into_diagnostic()?.is_dir() {
+                fs::remove_dir_all(entry.path()).into_diagnostic()?;
+            } else {
+                fs::remove_file(entry.path()).into_diagnostic()?;
+            }
+        }
+        drop(write_guard);
+        drop(guard);
+        fs::remove_dir_all(&environment.path).into_diagnostic()
+    }
+}
+
+/// Returns true if the locked environment can be shared between projects.
+/// PyPI packages that are editable or installed from a path refer to the
+/// sources of a specific project, so those environments can't be shared.
+pub(crate) fn is_shareable(environment: rattler_lock::Environment, platform: Platform) -> bool {
+    environment
+        .pypi_packages_for_platform(platform)
+        .unwrap_or_default()
+        .iter()
+        .all(|(data, _)| !data.editable && !matches!(data.url_or_path, UrlOrPath::Path(_)))
+}
+
+/// The name of the file that records that an environment directory links to
+/// the store.
+fn reference_name(environment_dir: &Path) -> String {
+    format!(
+        "{:x}",
+        xxh3_64(environment_dir.to_string_lossy().as_bytes())
+    )
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+fn remove_symlink(link: &Path) -> miette::Result<()> {
+    // Directory symlinks are removed as directories on Windows.
+    #[cfg(windows)]
+    let result = fs::remove_dir(link);
+    #[cfg(not(windows))]
+    let result = fs::remove_file(link);
+    result.into_diagnostic()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rattler_lock::LockFile;
+    use std::str::FromStr;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_references() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = EnvironmentStore::new(tmp.path().join("store"));
+        let lock_file =
+            LockFile::from_str(include_str!("./global/test_data/lockfiles/ripgrep.lock")).unwrap();
+        let hash = LockedEnvironmentHash::from_environment(
+            lock_file.default_environment().unwrap(),
+            Platform::Linux64,
+        );
+        fs::create_dir_all(store.prefix_dir(&hash).join("conda-meta")).unwrap();
+
+        // A project environment that was installed before is replaced by a link.
+        let first = tmp.path().join("first/.pixi/envs/default");
+        fs::create_dir_all(first.join("conda-meta")).unwrap();
+        store.link(&hash, &first).unwrap();
+        assert_eq!(fs::read_link(&first).unwrap(), store.prefix_dir(&hash));
+
+        let second = tmp.path().join("second/.pixi/envs/default");
+        store.link(&hash, &second).unwrap();
+        store.link(&hash, &second).unwrap();
+
+        let environments = store.environments().unwrap();
+        assert_eq!(environments.len(), 1);
+        assert_eq!(environments[0].references.len(), 2);
+
+        // Removed links are no longer counted.
+        assert!(store.unlink(&first).unwrap());
+        assert!(!store.unlink(&first).unwrap());
+        let environments = store.environments().unwrap();
+        assert_eq!(environments[0].references, vec![second.clone()]);
+
+        fs::remove_file(&second).unwrap();
+        let environments = store.environments().unwrap();
+        assert!(environments[0].references.is_empty());
+        store.remove(&environments[0]).unwrap();
+        assert!(store.environments().unwrap().is_empty());
+    }
+}
@@ -5,7 +5,9 @@ pub(crate) mod project;
 pub(crate) mod trampoline;
 
 pub(crate) use common::{BinDir, EnvChanges, EnvDir, EnvRoot, EnvState, StateChange, StateChanges};
-pub(crate) use project::{EnvironmentName, ExposedName, Mapping, Project};
+pub(crate) use project::{
+    ConflictStrategy, EnvironmentName, ExposedName, GlobalExport, Mapping, Project,
+};
 
 use crate::prefix::Prefix;
 use rattler_conda_types::PrefixRecord;
@@ -0,0 +1,351 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use fancy_display::FancyDisplay;
+use indexmap::IndexMap;
+use miette::IntoDiagnostic;
+use rattler_conda_types::{ChannelConfig, MatchSpec, PackageName, ParseStrictness};
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, TomlError};
+use url::Url;
+
+use super::parsed_manifest::{ManifestParsingError, ManifestVersion};
+use super::{inline_environment_tables, EnvironmentName, Manifest, ParsedEnvironment, Project};
+
+/// The file name used when displaying errors of an export file without a name.
+const EXPORT_DEFAULT_NAME: &str = "pixi-global-export.toml";
+
+/// How to handle environments or exposed names of an export file that
+/// already exist in the local global manifest.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Abort the import without changing anything.
+    #[default]
+    Error,
+    /// Keep the local environment or exposed name and skip the imported one.
+    Skip,
+    /// Replace the local environment or exposed name with the imported one.
+    Overwrite,
+}
+
+/// A package as it was installed in a global environment at the time of the
+/// export.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct LockedPackage {
+    pub name: PackageName,
+    pub version: String,
+    pub build: String,
+    pub url: Url,
+}
+
+impl LockedPackage {
+    /// Returns a match spec that pins the package to this exact version and
+    /// build.
+    fn pinned_spec(&self) -> miette::Result<MatchSpec> {
+        MatchSpec::from_str(
+            &format!(
+                "{} =={} {}",
+                self.name.as_normalized(),
+                self.version,
+                self.build
+            ),
+            ParseStrictness::Lenient,
+        )
+        .into_diagnostic()
+    }
+}
+
+/// A portable representation of (a subset of) the global environments.
+///
+/// The `envs` use the same format as the global manifest, such that an export
+/// can also be copied into a `pixi-global.toml` by hand. The optional `locked`
+/// table records the packages that were installed when the export was made.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GlobalExport {
+    #[serde(default)]
+    version: ManifestVersion,
+    #[serde(default)]
+    pub(crate) envs: IndexMap<EnvironmentName, ParsedEnvironment>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub(crate) locked: IndexMap<EnvironmentName, Vec<LockedPackage>>,
+}
+
+impl GlobalExport {
+    /// Creates an export of the given environments of the project, or of all
+    /// environments if none are given.
+    ///
+    /// If `with_locked` is set, the packages currently installed in the
+    /// environments are recorded as well.
+    pub(crate) async fn from_project(
+        project: &Project,
+        environments: &[EnvironmentName],
+        with_locked: bool,
+    ) -> miette::Result<Self> {
+        let mut export = Self::default();
+        for (env_name, environment) in project.environments() {
+            if !environments.is_empty() && !environments.contains(env_name) {
+                continue;
+            }
+            export.envs.insert(env_name.clone(), environment.clone());
+
+            if with_locked {
+                let mut packages = project
+                    .environment_prefix(env_name)
+                    .await?
+                    .find_installed_packages(None)
+                    .await?
+                    .into_iter()
+                    .map(|record| LockedPackage {
+                        name: record.repodata_record.package_record.name,
+                        version: record.repodata_record.package_record.version.to_string(),
+                        build: record.repodata_record.package_record.build,
+                        url: record.repodata_record.url,
+                    })
+                    .collect::<Vec<_>>();
+                packages.sort_by(|a, b| a.name.cmp(&b.name));
+                export.locked.insert(env_name.clone(), packages);
+            }
+        }
+
+        if let Some(missing) = environments
+            .iter()
+            .find(|env_name| !export.envs.contains_key(*env_name))
+        {
+            miette::bail!("Environment {} doesn't exist.", missing.fancy_display());
+        }
+
+        Ok(export)
+    }
+
+    /// Parses an export from the contents of a file at `path`.
+    pub(crate) fn from_str(path: &Path, contents: &str) -> miette::Result<Self> {
+        match toml_edit::de::from_str::<Self>(contents) {
+            Ok(export) => Ok(export),
+            Err(e) => ManifestParsingError::Error(TomlError::from(e)).to_fancy(
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or(EXPORT_DEFAULT_NAME),
+                contents,
+                path,
+            ),
+        }
+    }
+
+    /// Serializes the export to a toml string that is formatted like the
+    /// global manifest.
+    pub(crate) fn to_toml_string(&self) -> miette::Result<String> {
+        let toml_pretty = toml_edit::ser::to_string_pretty(self).into_diagnostic()?;
+        let mut document: DocumentMut = toml_pretty.parse().into_diagnostic()?;
+        inline_environment_tables(&mut document);
+        Ok(document.to_string())
+    }
+
+    /// Merges the environments of this export into the manifest.
+    ///
+    /// If `use_locked` is set, dependencies are pinned to the exact version
+    /// and build that was recorded in the export. Returns the names of the
+    /// environments that were imported.
+    pub(crate) fn merge_into(
+        self,
+        manifest: &mut Manifest,
+        strategy: ConflictStrategy,
+        use_locked: bool,
+        channel_config: &ChannelConfig,
+    ) -> miette::Result<Vec<EnvironmentName>> {
+        let mut imported = Vec::new();
+        for (env_name, environment) in self.envs {
+            if manifest.parsed.envs.contains_key(&env_name) {
+                match strategy {
+                    ConflictStrategy::Error => miette::bail!(
+                        "Environment {} already exists. Use `--on-conflict skip` or `--on-conflict overwrite` to resolve the conflict.",
+                        env_name.fancy_display()
+                    ),
+                    ConflictStrategy::Skip => {
+                        tracing::warn!(
+                            "Skipping environment {} because it already exists",
+                            env_name.fancy_display()
+                        );
+                        continue;
+                    }
+                    ConflictStrategy::Overwrite => manifest.remove_environment(&env_name)?,
+                }
+            }
+
+            let channels = environment.channels().into_iter().cloned().collect();
+            manifest.add_environment(&env_name, Some(channels))?;
+
+            if let Some(platform) = environment.platform() {
+                manifest.set_platform(&env_name, platform)?;
+            }
+
+            let locked = use_locked.then(|| self.locked.get(&env_name)).flatten();
+            if use_locked && locked.is_none() {
+                tracing::warn!(
+                    "The export doesn't contain locked packages for environment {}",
+                    env_name.fancy_display()
+                );
+            }
+            for (name, spec) in environment.dependencies() {
+                match locked.and_then(|packages| packages.iter().find(|p| &p.name == name)) {
+                    Some(package) => manifest.add_dependency(
+                        &env_name,
+                        &package.pinned_spec()?,
+                        channel_config,
+                    )?,
+                    None => manifest.add_pixi_spec(&env_name, name, spec)?,
+                }
+            }
+
+            for mapping in environment.exposed() {
+                if manifest
+                    .exposed_name_already_exists_in_other_envs(mapping.exposed_name(), &env_name)
+                {
+                    match strategy {
+                        ConflictStrategy::Error => miette::bail!(
+                            "Exposed name {} already exists. Use `--on-conflict skip` or `--on-conflict overwrite` to resolve the conflict.",
+                            mapping.exposed_name().fancy_display()
+                        ),
+                        ConflictStrategy::Skip => {
+                            tracing::warn!(
+                                "Skipping exposed name {} because it already exists",
+                                mapping.exposed_name().fancy_display()
+                            );
+                            continue;
+                        }
+                        ConflictStrategy::Overwrite => {
+                            let other_env =
+                                manifest.match_exposed_name_to_environment(mapping.exposed_name())?;
+                            manifest.remove_exposed_name(&other_env, mapping.exposed_name())?;
+                        }
+                    }
+                }
+                manifest.add_exposed_mapping(&env_name, mapping)?;
+            }
+
+            imported.push(env_name);
+        }
+
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pixi_config::default_channel_config;
+
+    use super::*;
+    use crate::global::ExposedName;
+
+    const LOCAL: &str = r#"
+[envs.python]
+channels = ["conda-forge"]
+dependencies = { python = "3.11.*" }
+exposed = { python = "python", python3 = "python3" }
+"#;
+
+    const EXPORT: &str = r#"
+version = 1
+
+[envs.python]
+channels = ["conda-forge"]
+dependencies = { python = "3.12.*" }
+exposed = { python = "python" }
+
+[envs.ruff]
+channels = ["conda-forge"]
+dependencies = { ruff = "*" }
+exposed = { ruff = "ruff", python3 = "python3" }
+
+[[locked.ruff]]
+name = "ruff"
+version = "0.7.2"
+build = "py312h1234_0"
+url = "https://conda.anaconda.org/conda-forge/linux-64/ruff-0.7.2-py312h1234_0.conda"
+"#;
+
+    fn local_manifest() -> Manifest {
+        Manifest::from_str(Path::new("pixi-global.toml"), LOCAL).unwrap()
+    }
+
+    fn export() -> GlobalExport {
+        GlobalExport::from_str(Path::new("export.toml"), EXPORT).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let export = export();
+        let toml = export.to_toml_string().unwrap();
+        let parsed = GlobalExport::from_str(Path::new("export.toml"), &toml).unwrap();
+        assert_eq!(parsed.envs.len(), 2);
+        assert_eq!(parsed.locked, export.locked);
+    }
+
+    #[test]
+    fn test_merge_conflict_error() {
+        let mut manifest = local_manifest();
+        let result = export().merge_into(
+            &mut manifest,
+            ConflictStrategy::Error,
+            false,
+            &default_channel_config(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_merge_conflict_skip() {
+        let mut manifest = local_manifest();
+        let imported = export()
+            .merge_into(
+                &mut manifest,
+                ConflictStrategy::Skip,
+                false,
+                &default_channel_config(),
+            )
+            .unwrap();
+        let ruff = EnvironmentName::from_str("ruff").unwrap();
+        let python = EnvironmentName::from_str("python").unwrap();
+        assert_eq!(imported, vec![ruff.clone()]);
+
+        // The local python environment and its exposed names are kept
+        let python3 = ExposedName::from_str("python3").unwrap();
+        assert_eq!(
+            manifest
+                .match_exposed_name_to_environment(&python3)
+                .unwrap(),
+            python
+        );
+        assert!(manifest.document.to_string().contains("3.11.*"));
+    }
+
+    #[test]
+    fn test_merge_conflict_overwrite_locked() {
+        let mut manifest = local_manifest();
+        let imported = export()
+            .merge_into(
+                &mut manifest,
+                ConflictStrategy::Overwrite,
+                true,
+                &default_channel_config(),
+            )
+            .unwrap();
+        assert_eq!(imported.len(), 2);
+
+        // The exposed name moved to the imported environment
+        let ruff = EnvironmentName::from_str("ruff").unwrap();
+        let python3 = ExposedName::from_str("python3").unwrap();
+        assert_eq!(
+            manifest
+                .match_exposed_name_to_environment(&python3)
+                .unwrap(),
+            ruff
+        );
+
+        // Locked packages are pinned
+        let document = manifest.document.to_string();
+        assert!(document.contains("3.12.*"));
+        assert!(document.contains("==0.7.2"));
+        assert!(document.contains("py312h1234_0"));
+    }
+}
@@ -137,7 +137,16 @@ impl Manifest {
             miette::bail!("pixi doesn't support wildcard dependencies")
         };
         let spec = PixiSpec::from_nameless_matchspec(spec, channel_config);
+        self.add_pixi_spec(env_name, &name, &spec)
+    }
 
+    /// Adds a dependency given as [`PixiSpec`] to the manifest
+    pub fn add_pixi_spec(
+        &mut self,
+        env_name: &EnvironmentName,
+        name: &PackageName,
+        spec: &PixiSpec,
+    ) -> miette::Result<()> {
         // Update self.parsed
         self.parsed
             .envs
@@ -16,6 +16,7 @@ use crate::{
 };
 use ahash::HashSet;
 pub(crate) use environment::EnvironmentName;
+pub(crate) use export::{ConflictStrategy, GlobalExport};
 use fancy_display::FancyDisplay;
 use fs::tokio as tokio_fs;
 use fs_err as fs;
@@ -24,6 +25,7 @@ use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
 pub(crate) use manifest::{ExposedType, Manifest, Mapping};
 use miette::{miette, Context, IntoDiagnostic};
+use once_cell::sync::OnceCell;
 pub(crate) use parsed_manifest::ExposedName;
 pub(crate) use parsed_manifest::ParsedEnvironment;
 use parsed_manifest::ParsedManifest;
@@ -44,7 +46,6 @@ use rattler_solve::resolvo::Solver;
 use rattler_solve::{SolverImpl, SolverTask};
 use rattler_virtual_packages::{VirtualPackage, VirtualPackageOverrides};
 use reqwest_middleware::ClientWithMiddleware;
-use std::sync::OnceLock;
 use std::{
     ffi::OsStr,
     fmt::{Debug, Formatter},
@@ -54,6 +55,7 @@ use std::{
 use toml_edit::DocumentMut;
 
 mod environment;
+mod export;
 mod manifest;
 mod parsed_manifest;
 
@@ -77,11 +79,11 @@ pub struct Project {
     /// Binary directory
     pub(crate) bin_dir: BinDir,
     /// Reqwest client shared for this project.
-    /// This is wrapped in a `OnceLock` to allow for lazy initialization.
-    client: OnceLock<(reqwest::Client, ClientWithMiddleware)>,
+    /// This is wrapped in a `OnceCell` to allow for lazy initialization.
+    client: OnceCell<(ClientWithMiddleware, ClientWithMiddleware)>,
     /// The repodata gateway to use for answering queries about repodata.
-    /// This is wrapped in a `OnceLock` to allow for lazy initialization.
-    repodata_gateway: OnceLock<Gateway>,
+    /// This is wrapped in a `OnceCell` to allow for lazy initialization.
+    repodata_gateway: OnceCell<Gateway>,
 }
 
 impl Debug for Project {
@@ -167,6 +169,29 @@ impl ExposedData {
     }
 }
 
+/// Ensures that the environments in the document use inline tables for
+/// "dependencies" and "exposed", which is how they are written by pixi itself.
+fn inline_environment_tables(document: &mut DocumentMut) {
+    let Some(envs) = document
+        .get_mut("envs")
+        .and_then(|item| item.as_table_mut())
+    else {
+        return;
+    };
+
+    for (_, env_table) in envs.iter_mut() {
+        let Some(env_table) = env_table.as_table_mut() else {
+            continue;
+        };
+
+        for entry in ["dependencies", "exposed"] {
+            if let Some(table) = env_table.get(entry).and_then(|item| item.as_table()) {
+                env_table.insert(entry, toml_edit::value(table.clone().into_inline_table()));
+            }
+        }
+    }
+}
+
 fn determine_env_path(executable_path: &Path, env_root: &Path) -> miette::Result<PathBuf> {
     let mut current_path = executable_path;
 
@@ -240,8 +265,8 @@ impl Project {
 
         let config = Config::load(&root);
 
-        let client = OnceLock::new();
-        let repodata_gateway = OnceLock::new();
+        let client = OnceCell::new();
+        let repodata_gateway = OnceCell::new();
         Self {
             root,
             manifest,
@@ -332,25 +357,7 @@ impl Project {
         let parsed_manifest = ParsedManifest::from(exposed_binaries);
         let toml_pretty = toml_edit::ser::to_string_pretty(&parsed_manifest).into_diagnostic()?;
         let mut document: DocumentMut = toml_pretty.parse().into_diagnostic()?;
-
-        // Ensure that the manifest uses inline tables for "dependencies" and "exposed"
-        if let Some(envs) = document
-            .get_mut("envs")
-            .and_then(|item| item.as_table_mut())
-        {
-            for (_, env_table) in envs.iter_mut() {
-                let Some(env_table) = env_table.as_table_mut() else {
-                    continue;
-                };
-
-                for entry in ["dependencies", "exposed"] {
-                    if let Some(table) = env_table.get(entry).and_then(|item| item.as_table()) {
-                        env_table
-                            .insert(entry, toml_edit::value(table.clone().into_inline_table()));
-                    }
-                }
-            }
-        }
+        inline_environment_tables(&mut document);
         let toml = document.to_string();
         tokio_fs::write(&manifest_path, &toml)
             .await
@@ -414,13 +421,15 @@ impl Project {
 
     /// Create an authenticated reqwest client for this project
     /// use authentication from `rattler_networking`
-    pub fn authenticated_client(&self) -> &ClientWithMiddleware {
-        &self.client_and_authenticated_client().1
+    pub fn authenticated_client(&self) -> miette::Result<&ClientWithMiddleware> {
+        Ok(&self.client_and_authenticated_client()?.1)
     }
 
-    fn client_and_authenticated_client(&self) -> &(reqwest::Client, ClientWithMiddleware) {
+    fn client_and_authenticated_client(
+        &self,
+    ) -> miette::Result<&(ClientWithMiddleware, ClientWithMiddleware)> {
         self.client
-            .get_or_init(|| build_reqwest_clients(Some(&self.config)))
+            .get_or_try_init(|| build_reqwest_clients(Some(&self.config)))
     }
 
     pub(crate) fn config(&self) -> &Config {
@@ -467,13 +476,14 @@ impl Project {
             })
             .collect::<miette::Result<(Vec<MatchSpec>, Vec<PackageName>)>>()?;
 
+        let gateway = self.repodata_gateway()?;
         let repodata = await_in_progress(
             format!(
                 "Querying repodata for environment: {} ",
                 env_name.fancy_display()
             ),
             |_| async {
-                self.repodata_gateway()
+                gateway
                     .query(channels, [platform, Platform::NoArch], match_specs.clone())
                     .recursive(true)
                     .await
@@ -515,6 +525,7 @@ impl Project {
         // Install the environment
         let package_cache = PackageCache::new(pixi_config::get_cache_dir()?.join("pkgs"));
         let prefix = self.environment_prefix(env_name).await?;
+        let download_client = self.authenticated_client()?.clone();
         let result = await_in_progress(
             format!(
                 "Creating virtual environment for {}",
@@ -522,7 +533,7 @@ impl Project {
             ),
             |pb| {
                 Installer::new()
-                    .with_download_client(self.authenticated_client().clone())
+                    .with_download_client(download_client)
                     .with_io_concurrency_limit(100)
                     .with_execute_link_scripts(false)
                     .with_package_cache(package_cache)
@@ -1011,12 +1022,12 @@ impl Project {
 
 impl Repodata for Project {
     /// Returns the [`Gateway`] used by this project.
-    fn repodata_gateway(&self) -> &Gateway {
-        self.repodata_gateway.get_or_init(|| {
-            Self::repodata_gateway_init(
-                self.authenticated_client().clone(),
+    fn repodata_gateway(&self) -> miette::Result<&Gateway> {
+        self.repodata_gateway.get_or_try_init(|| {
+            Ok(Self::repodata_gateway_init(
+                self.authenticated_client()?.clone(),
                 self.config().clone().into(),
-            )
+            ))
         })
     }
 }
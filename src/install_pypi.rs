@@ -12,7 +12,7 @@ use itertools::Itertools;
 use miette::{IntoDiagnostic, WrapErr};
 use pep440_rs::{Version, VersionSpecifiers};
 use pixi_consts::consts;
-use pixi_manifest::{pyproject::PyProjectManifest, SystemRequirements};
+use pixi_manifest::{pyproject::PyProjectManifest, PypiClobberPolicy, SystemRequirements};
 use pixi_uv_conversions::{
     isolated_names_to_packages, locked_indexes_to_index_locations, to_uv_normalize, to_uv_version,
     to_uv_version_specifiers, ConversionError,
@@ -25,7 +25,7 @@ use rattler_lock::{
 use url::Url;
 use uv_auth::store_credentials_from_url;
 use uv_cache::{ArchiveTarget, ArchiveTimestamp, Cache};
-use uv_client::{Connectivity, FlatIndexClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClientBuilder};
 use uv_configuration::{ConfigSettings, Constraints, IndexStrategy, LowerBound};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::{DistributionDatabase, RegistryWheelIndex};
@@ -48,8 +48,8 @@ use uv_types::HashStrategy;
 use pixi_uv_conversions::names_to_build_isolation;
 
 use crate::{
-    conda_pypi_clobber::PypiCondaClobberRegistry,
-    lock_file::UvResolutionContext,
+    conda_pypi_clobber::{ClobberedPackage, PypiCondaClobberRegistry},
+    lock_file::{PypiPackageIdentifier, UvResolutionContext},
     prefix::Prefix,
     uv_reporter::{UvReporter, UvReporterOptions},
 };
@@ -477,10 +477,14 @@ fn need_reinstall(
 /// Figure out what we can link from the cache locally
 /// and what we need to download from the registry.
 /// Also determine what we need to remove.
+///
+/// Installed packages in `keep_pkgs` are left as they are, they are neither
+/// reinstalled nor removed.
 fn whats_the_plan<'a>(
     site_packages: &'a mut SitePackages,
     mut registry_index: RegistryWheelIndex<'a>,
     required_pkgs: &'a HashMap<uv_normalize::PackageName, &'a PypiPackageData>,
+    keep_pkgs: &HashSet<uv_normalize::PackageName>,
     uv_cache: &Cache,
     python_version: &Version,
     lock_file_dir: &Path,
@@ -506,6 +510,10 @@ fn whats_the_plan<'a>(
 
     // Walk over all installed packages and check if they are required
     for dist in site_packages.iter() {
+        if keep_pkgs.contains(dist.name()) {
+            continue;
+        }
+
         // Check if we require the package to be installed
         let pkg = required_pkgs.get(dist.name());
         // Get the installer name
@@ -625,6 +633,8 @@ pub async fn update_python_distributions(
     environment_variables: &HashMap<String, String>,
     platform: Platform,
     non_isolated_packages: Option<Vec<String>>,
+    clobber_policy: PypiClobberPolicy,
+    skip_editable: bool,
 ) -> miette::Result<()> {
     let start = std::time::Instant::now();
     use pixi_consts::consts::PROJECT_MANIFEST;
@@ -645,7 +655,7 @@ pub async fn update_python_distributions(
             .client(uv_context.client.clone())
             .index_urls(index_locations.index_urls())
             .keyring(uv_context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(uv_context.connectivity)
             .build(),
     );
 
@@ -726,14 +736,39 @@ pub async fn update_python_distributions(
         &index_locations,
         &HashStrategy::None,
     );
+    // Skipped editable packages are not installed, but an already installed
+    // version is kept instead of being removed as extraneous. With
+    // `prefer-conda` the same holds for the packages that a conda package
+    // provides, so their wheels are never fetched.
+    let conda_provided = match clobber_policy {
+        PypiClobberPolicy::PreferConda => provided_by_conda(conda_package),
+        _ => HashSet::new(),
+    };
+    let (skipped_packages, python_packages): (Vec<_>, Vec<_>) =
+        python_packages.iter().partition(|(pkg, _)| {
+            (skip_editable && pkg.editable) || conda_provided.contains(&pkg.name)
+        });
+    let skipped_by_clobber_policy = skipped_packages
+        .iter()
+        .filter(|(pkg, _)| conda_provided.contains(&pkg.name))
+        .map(|(pkg, _)| format!("\t{}", pkg.name))
+        .join("\n");
+    if !skipped_by_clobber_policy.is_empty() {
+        tracing::info!(
+            "These pypi-packages are not installed because conda-packages provide them: \n{skipped_by_clobber_policy}"
+        );
+    }
+    let to_uv_name = |pkg: &PypiPackageData| {
+        uv_normalize::PackageName::new(pkg.name.to_string()).expect("should be correct")
+    };
+    let keep_map: HashSet<uv_normalize::PackageName> = skipped_packages
+        .iter()
+        .map(|(pkg, _)| to_uv_name(pkg))
+        .collect();
     let required_map: std::collections::HashMap<uv_normalize::PackageName, &PypiPackageData> =
         python_packages
             .iter()
-            .map(|(pkg, _)| {
-                let uv_name = uv_normalize::PackageName::new(pkg.name.to_string())
-                    .expect("should be correct");
-                (uv_name, pkg)
-            })
+            .map(|(pkg, _)| (to_uv_name(pkg), pkg))
             .collect();
 
     tracing::debug!("Figuring out what to install/reinstall/remove");
@@ -750,6 +785,7 @@ pub async fn update_python_distributions(
         &mut site_packages,
         registry_index,
         &required_map,
+        &keep_map,
         &uv_context.cache,
         &pep440_rs::Version::from_str(&venv.interpreter().python_version().to_string())
             .expect("should be the same"),
@@ -930,19 +966,20 @@ pub async fn update_python_distributions(
     let wheels = wheels.into_iter().chain(local).collect::<Vec<_>>();
 
     // Verify if pypi wheels will override existing conda packages
-    // and warn if they are
-    if let Ok(Some(clobber_packages)) =
-        pypi_conda_clobber.clobber_on_installation(wheels.clone(), &venv)
-    {
-        let packages_names = clobber_packages.iter().join(", ");
-
-        tracing::warn!("These conda-packages will be overridden by pypi: \n\t{packages_names}");
+    // and act on it according to the clobber policy of the project
+    let clobbered = pypi_conda_clobber
+        .clobber_on_installation(&wheels, &venv)
+        .unwrap_or_default();
+    if !clobbered.is_empty() {
+        apply_clobber_policy(clobber_policy, &clobbered)?;
 
         // because we are removing conda packages
-        // we filter the ones we already warn
-        if !installer_mismatch.is_empty() {
-            installer_mismatch.retain(|name| !packages_names.contains(name));
-        }
+        // we filter the ones we already reported
+        installer_mismatch.retain(|name| {
+            !clobbered
+                .iter()
+                .any(|clobber| clobber.pypi_package.to_string() == *name)
+        });
     }
 
     if !installer_mismatch.is_empty() {
@@ -986,6 +1023,48 @@ pub async fn update_python_distributions(
     Ok(())
 }
 
+/// Returns the normalized names of the PyPI packages that the conda packages
+/// provide according to the mapping.
+fn provided_by_conda(conda_packages: &[RepoDataRecord]) -> HashSet<pep508_rs::PackageName> {
+    conda_packages
+        .iter()
+        .flat_map(|record| PypiPackageIdentifier::from_record(record).unwrap_or_default())
+        .map(|identifier| identifier.name.as_normalized().clone())
+        .collect()
+}
+
+/// Acts on the PyPI packages that overwrite the files of conda packages
+/// according to the clobber policy. The packages that a conda package
+/// provides are already left out of the installation with `prefer-conda`, so
+/// the remaining overlaps are reported.
+fn apply_clobber_policy(
+    clobber_policy: PypiClobberPolicy,
+    clobbered: &[ClobberedPackage],
+) -> miette::Result<()> {
+    let report = clobbered
+        .iter()
+        .map(|clobber| format!("\t{clobber}"))
+        .join("\n");
+    match clobber_policy {
+        PypiClobberPolicy::Warn => {
+            tracing::warn!("These pypi-packages will overwrite files of conda-packages: \n{report}");
+        }
+        PypiClobberPolicy::Error => miette::bail!(
+            help = "remove one of the packages from the manifest, or change `pypi-clobber-policy` in the [project] table",
+            "these pypi-packages would overwrite files of conda-packages:\n{report}"
+        ),
+        PypiClobberPolicy::PreferPypi => {
+            tracing::debug!("These pypi-packages overwrite files of conda-packages: \n{report}");
+        }
+        PypiClobberPolicy::PreferConda => {
+            tracing::warn!(
+                "These pypi-packages will overwrite files of conda-packages that don't provide them: \n{report}"
+            );
+        }
+    }
+    Ok(())
+}
+
 /// Returns `true` if the source tree at the given path contains dynamic
 /// metadata.
 #[allow(dead_code)]
@@ -1015,10 +1094,90 @@ mod tests {
     use std::{path::PathBuf, str::FromStr};
 
     use pep440_rs::Version;
+    use pixi_manifest::PypiClobberPolicy;
+    use rattler_conda_types::{PackageRecord, PackageUrl, RepoDataRecord, VersionWithSource};
     use rattler_lock::{PypiPackageData, UrlOrPath};
     use uv_distribution_types::RemoteSource;
 
-    use super::convert_to_dist;
+    use super::{apply_clobber_policy, convert_to_dist, provided_by_conda};
+    use crate::conda_pypi_clobber::ClobberedPackage;
+
+    /// A conda package that provides the given pypi packages.
+    fn conda_record(name: &str, provides: &[&str]) -> RepoDataRecord {
+        let mut package_record = PackageRecord::new(
+            name.parse().unwrap(),
+            VersionWithSource::from_str("1.0.0").unwrap(),
+            "h123_0".to_string(),
+        );
+        package_record.purls = Some(
+            provides
+                .iter()
+                .map(|name| {
+                    PackageUrl::builder(String::from("pypi"), name.to_string())
+                        .build()
+                        .unwrap()
+                })
+                .collect(),
+        );
+        RepoDataRecord {
+            package_record,
+            file_name: format!("{name}-1.0.0-h123_0.conda"),
+            url: url::Url::parse(&format!(
+                "https://example.com/channel/noarch/{name}-1.0.0-h123_0.conda"
+            ))
+            .unwrap(),
+            channel: "https://example.com/channel/".to_string(),
+        }
+    }
+
+    fn clobbered(pypi_package: &str, conda_package: &str) -> ClobberedPackage {
+        ClobberedPackage {
+            pypi_package: pypi_package.parse().unwrap(),
+            conda_package: conda_package.parse().unwrap(),
+            paths: vec![PathBuf::from(format!(
+                "lib/python3.12/site-packages/{pypi_package}/__init__.py"
+            ))],
+        }
+    }
+
+    #[test]
+    fn test_provided_by_conda() {
+        let conda_packages = [
+            conda_record("pytorch", &["torch"]),
+            conda_record("other", &[]),
+        ];
+
+        // `pytorch` provides `torch`, so with `prefer-conda` its wheel is left
+        // out of the installation before it is fetched.
+        let provided = provided_by_conda(&conda_packages);
+        assert_eq!(
+            provided
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["torch"]
+        );
+    }
+
+    #[test]
+    fn test_clobber_policy_error() {
+        let err = apply_clobber_policy(PypiClobberPolicy::Error, &[clobbered("torch", "pytorch")])
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("torch (pypi) overwrites pytorch (conda)"));
+    }
+
+    #[test]
+    fn test_clobber_policy_installs_anyway() {
+        for policy in [
+            PypiClobberPolicy::Warn,
+            PypiClobberPolicy::PreferPypi,
+            PypiClobberPolicy::PreferConda,
+        ] {
+            apply_clobber_policy(policy, &[clobbered("six", "other")]).unwrap();
+        }
+    }
 
     #[test]
     /// Create locked pypi data, pass this into the convert_to_dist function
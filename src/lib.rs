@@ -1,8 +1,11 @@
 pub mod activation;
+mod audit;
+mod cache_registry;
 pub mod cli;
 pub(crate) mod conda_pypi_clobber;
 mod diff;
 pub mod environment;
+mod environment_store;
 mod global;
 mod install_pypi;
 mod install_wheel;
@@ -12,6 +15,7 @@ mod project;
 mod prompt;
 pub(crate) mod repodata;
 pub mod task;
+mod verify;
 
 mod uv_reporter;
 
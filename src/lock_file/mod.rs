@@ -13,15 +13,17 @@ use rattler_conda_types::RepoDataRecord;
 use rattler_lock::{LockFile, PypiPackageData, PypiPackageEnvironmentData};
 pub(crate) use records_by_name::{PypiRecordsByName, RepoDataRecordsByName};
 pub(crate) use resolve::{
-    conda::resolve_conda, pypi::resolve_pypi, uv_resolution_context::UvResolutionContext,
+    conda::{resolve_conda, CondaSpecSources},
+    pypi::resolve_pypi,
+    uv_resolution_context::UvResolutionContext,
 };
 pub use satisfiability::{
     verify_environment_satisfiability, verify_platform_satisfiability, EnvironmentUnsat,
     PlatformUnsat,
 };
+pub use update::{InstallFilter, UpdateLockFileOptions, UpdateMode};
 pub(crate) use update::{LockFileDerivedData, UpdateContext};
-pub use update::{UpdateLockFileOptions, UpdateMode};
-pub(crate) use utils::filter_lock_file;
+pub(crate) use utils::{filter_lock_file, IoConcurrencyLimit};
 
 use crate::Project;
 
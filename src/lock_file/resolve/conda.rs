@@ -1,9 +1,21 @@
-use miette::IntoDiagnostic;
-use rattler_conda_types::{GenericVirtualPackage, MatchSpec, RepoDataRecord};
+use std::fmt::Display;
+
+use itertools::Itertools;
+use miette::{Diagnostic, IntoDiagnostic, LabeledSpan, NamedSource};
+use pixi_manifest::{
+    FeatureName, HasFeaturesIter, HasManifestRef, ManifestKind, SpecType, TargetSelector,
+};
+use rattler_conda_types::{
+    GenericVirtualPackage, MatchSpec, PackageName, Platform, RepoDataRecord,
+};
 use rattler_repodata_gateway::RepoData;
 use rattler_solve::{resolvo, ChannelPriority, SolverImpl};
+use thiserror::Error;
 
-use crate::lock_file::LockedCondaPackages;
+use crate::{
+    lock_file::LockedCondaPackages,
+    project::{grouped_environment::GroupedEnvironment, HasProjectRef},
+};
 
 /// Solves the conda package environment for the given input. This function is
 /// async because it spawns a background task for the solver. Since solving is a
@@ -34,3 +46,243 @@ pub async fn resolve_conda(
         Err(_err) => Err(miette::miette!("cancelled")),
     })
 }
+
+/// A conda spec from the manifest that is passed to the solver.
+#[derive(Debug, Clone)]
+struct ManifestSpec {
+    name: PackageName,
+    spec: String,
+    spec_type: SpecType,
+    feature: FeatureName,
+    target: Option<TargetSelector>,
+}
+
+impl ManifestSpec {
+    /// Describes where the spec is defined in the manifest.
+    fn origin(&self) -> String {
+        let feature = match &self.feature {
+            FeatureName::Default => "the default feature".to_string(),
+            FeatureName::Named(name) => format!("feature '{name}'"),
+        };
+        match &self.target {
+            Some(target) => format!(
+                "[{}] of {feature} for target '{target}'",
+                self.spec_type.name()
+            ),
+            None => format!("[{}] of {feature}", self.spec_type.name()),
+        }
+    }
+
+    /// Returns true if the spec restricts the versions of the package.
+    fn is_pinned(&self) -> bool {
+        self.spec != "*"
+    }
+}
+
+/// The conda specs of a grouped environment for a platform together with the
+/// source of the manifest. When the solve fails, these are used to point at
+/// the specs in the manifest that take part in the conflict.
+#[derive(Debug, Clone)]
+pub struct CondaSpecSources {
+    file_name: String,
+    kind: ManifestKind,
+    contents: String,
+    specs: Vec<ManifestSpec>,
+}
+
+impl CondaSpecSources {
+    /// Collects the specs of all features and targets of the group that apply
+    /// to the platform.
+    pub fn from_group(group: &GroupedEnvironment<'_>, platform: Platform) -> Self {
+        let manifest = group.manifest();
+        let channel_config = group.project().channel_config();
+        let mut specs = Vec::new();
+        for feature in group.features() {
+            for (target, selector) in feature.targets.iter() {
+                if selector.is_some_and(|selector| !selector.matches(platform)) {
+                    continue;
+                }
+                for spec_type in SpecType::all() {
+                    let Some(dependencies) = target.dependencies(Some(spec_type)) else {
+                        continue;
+                    };
+                    for (name, spec) in dependencies.iter() {
+                        let spec_string =
+                            match spec.clone().try_into_nameless_match_spec(&channel_config) {
+                                Ok(Some(spec)) => spec.to_string(),
+                                _ => spec.to_toml_value().to_string().trim().to_string(),
+                            };
+                        specs.push(ManifestSpec {
+                            name: name.clone(),
+                            spec: spec_string,
+                            spec_type,
+                            feature: feature.name.clone(),
+                            target: selector.cloned(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Self {
+            file_name: manifest.file_name().to_string(),
+            kind: manifest.kind(),
+            contents: manifest.contents.clone(),
+            specs,
+        }
+    }
+
+    /// Turns the error of a failed solve into a diagnostic that points at the
+    /// specs in the manifest that are mentioned by the solver. Only the
+    /// mentioned specs are located in the manifest.
+    pub fn into_solve_error(
+        self,
+        error: miette::Report,
+        environment: impl Display,
+        platform: impl Display,
+    ) -> CondaSolveError {
+        let message = error.to_string();
+        let mentioned = self
+            .specs
+            .into_iter()
+            .filter(|spec| is_mentioned(&message, &spec.name))
+            .collect_vec();
+
+        let source_locs = self.kind.dependency_source_locs(
+            &self.contents,
+            mentioned.iter().map(|spec| {
+                (
+                    &spec.name,
+                    spec.spec_type,
+                    spec.target.as_ref(),
+                    &spec.feature,
+                )
+            }),
+        );
+        let labels = mentioned
+            .iter()
+            .zip(source_locs)
+            .filter_map(|(spec, span)| {
+                let span = span?;
+                Some(LabeledSpan::at(
+                    span,
+                    format!(
+                        "{} {} in {}",
+                        spec.name.as_source(),
+                        spec.spec,
+                        spec.origin()
+                    ),
+                ))
+            })
+            .collect_vec();
+
+        CondaSolveError {
+            environment: environment.to_string(),
+            platform: platform.to_string(),
+            source: error.into(),
+            help: conflict_help(&mentioned),
+            src: NamedSource::new(self.file_name, self.contents),
+            labels,
+        }
+    }
+}
+
+/// Returns true if the name of the package appears as a word in the message
+/// of the solver.
+fn is_mentioned(message: &str, name: &PackageName) -> bool {
+    message
+        .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .any(|word| word.eq_ignore_ascii_case(name.as_normalized()))
+}
+
+/// Suggests how to resolve the conflict between the specs in the manifest.
+fn conflict_help(specs: &[ManifestSpec]) -> Option<String> {
+    // A package that is required by multiple features or targets is combined
+    // into a single spec for the solve.
+    for name in specs.iter().map(|spec| &spec.name).unique() {
+        let origins = specs
+            .iter()
+            .filter(|spec| &spec.name == name)
+            .map(ManifestSpec::origin)
+            .collect_vec();
+        if origins.len() > 1 {
+            return Some(format!(
+                "{} is required by {}, these specs are combined in the environment, make sure they overlap or move them to features that are not used in the same environment",
+                name.as_source(),
+                origins.join(" and ")
+            ));
+        }
+    }
+
+    let pinned = specs.iter().filter(|spec| spec.is_pinned()).collect_vec();
+    if pinned.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "try relaxing the pin of {}",
+        pinned
+            .iter()
+            .map(|spec| format!("`{} {}`", spec.name.as_source(), spec.spec))
+            .join(" or ")
+    ))
+}
+
+/// The conda solver could not find a solution for the specs of an
+/// environment.
+#[derive(Debug, Error)]
+#[error("failed to solve the conda requirements of '{environment}' '{platform}'")]
+pub struct CondaSolveError {
+    environment: String,
+    platform: String,
+    #[source]
+    source: Box<dyn std::error::Error + Send + Sync>,
+    help: Option<String>,
+    src: NamedSource<String>,
+    labels: Vec<LabeledSpan>,
+}
+
+impl Diagnostic for CondaSolveError {
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.help
+            .as_ref()
+            .map(|help| Box::new(help) as Box<dyn Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        if self.labels.is_empty() {
+            None
+        } else {
+            Some(&self.src)
+        }
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        if self.labels.is_empty() {
+            None
+        } else {
+            Some(Box::new(self.labels.iter().cloned()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_is_mentioned() {
+        let message = "Cannot solve the request because of: The following packages are incompatible\n\
+            ├─ numpy >=2 can be installed with any of the following options:\n\
+            │  └─ numpy 2.0.0 would require\n\
+            │     └─ python >=3.9,<3.10.0a0, which can be installed with any of the following options:\n\
+            └─ python-dateutil 3.12.* cannot be installed because there are no viable options";
+        let is = |name: &str| is_mentioned(message, &PackageName::from_str(name).unwrap());
+        assert!(is("numpy"));
+        assert!(is("python"));
+        assert!(is("python-dateutil"));
+        assert!(!is("num"));
+        assert!(!is("scipy"));
+    }
+}
@@ -13,11 +13,14 @@ use indexmap::{IndexMap, IndexSet};
 use indicatif::ProgressBar;
 use itertools::{Either, Itertools};
 use miette::{Context, IntoDiagnostic};
-use pixi_manifest::{pypi::pypi_options::PypiOptions, PyPiRequirement, SystemRequirements};
+use pixi_manifest::{
+    pypi::pypi_options::PypiOptions, PyPiRequirement, PypiClobberPolicy, SystemRequirements,
+};
 use pixi_uv_conversions::{
     as_uv_req, convert_uv_requirements_to_pep508, isolated_names_to_packages,
-    names_to_build_isolation, pypi_options_to_index_locations, to_index_strategy, to_normalize,
-    to_requirements, to_uv_normalize, to_uv_version, to_version_specifiers, ConversionError,
+    names_to_build_isolation, pypi_options_to_build_options, pypi_options_to_index_locations,
+    to_index_strategy, to_normalize, to_requirements, to_uv_normalize, to_uv_version,
+    to_version_specifiers, ConversionError,
 };
 use pypi_modifiers::{
     pypi_marker_env::determine_marker_environment,
@@ -29,7 +32,7 @@ use rattler_lock::{
     PackageHashes, PypiPackageData, PypiPackageEnvironmentData, PypiSourceTreeHashable, UrlOrPath,
 };
 use url::Url;
-use uv_client::{Connectivity, FlatIndexClient, RegistryClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClient, RegistryClientBuilder};
 use uv_configuration::{ConfigSettings, Constraints, IndexStrategy, LowerBound, Overrides};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::DistributionDatabase;
@@ -159,6 +162,7 @@ fn print_overridden_requests(package_requests: &HashMap<uv_normalize::PackageNam
 pub async fn resolve_pypi(
     context: UvResolutionContext,
     pypi_options: &PypiOptions,
+    clobber_policy: PypiClobberPolicy,
     dependencies: IndexMap<uv_normalize::PackageName, IndexSet<PyPiRequirement>>,
     system_requirements: SystemRequirements,
     locked_conda_records: &[RepoDataRecord],
@@ -269,6 +273,9 @@ pub async fn resolve_pypi(
     let index_locations =
         pypi_options_to_index_locations(pypi_options, project_root).into_diagnostic()?;
 
+    // Only use wheels and build source distributions as allowed by the pypi-options.
+    let build_options = pypi_options_to_build_options(pypi_options).into_diagnostic()?;
+
     // TODO: create a cached registry client per index_url set?
     let index_strategy = to_index_strategy(pypi_options.index_strategy.as_ref());
     let registry_client = Arc::new(
@@ -278,7 +285,7 @@ pub async fn resolve_pypi(
             .index_strategy(index_strategy)
             .markers(&marker_environment)
             .keyring(context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(context.connectivity)
             .build(),
     );
 
@@ -294,12 +301,7 @@ pub async fn resolve_pypi(
             .await
             .into_diagnostic()
             .wrap_err("failed to query find-links locations")?;
-        FlatIndex::from_entries(
-            entries,
-            Some(&tags),
-            &context.hash_strategy,
-            &context.build_options,
-        )
+        FlatIndex::from_entries(entries, Some(&tags), &context.hash_strategy, &build_options)
     };
 
     // Create a shared in-memory index.
@@ -341,7 +343,7 @@ pub async fn resolve_pypi(
         &config_settings,
         build_isolation,
         LinkMode::default(),
-        &context.build_options,
+        &build_options,
         &context.hash_strategy,
         None,
         LowerBound::default(),
@@ -447,7 +449,7 @@ pub async fn resolve_pypi(
         AllowedYanks::from_manifest(&manifest, &resolver_env, options.dependency_mode),
         &context.hash_strategy,
         options.exclude_newer,
-        &context.build_options,
+        &build_options,
         &context.capabilities,
     );
     let package_requests = Rc::new(RefCell::new(Default::default()));
@@ -493,7 +495,7 @@ pub async fn resolve_pypi(
     }
 
     // Collect resolution into locked packages
-    lock_pypi_packages(
+    let locked_packages = lock_pypi_packages(
         conda_python_packages,
         &build_dispatch,
         &registry_client,
@@ -502,7 +504,61 @@ pub async fn resolve_pypi(
         context.concurrency.downloads,
         project_root,
     )
-    .await
+    .await?;
+
+    check_clobbering_packages(clobber_policy, locked_conda_records, &locked_packages)?;
+
+    Ok(locked_packages)
+}
+
+/// Reports the locked PyPI packages that have the same name as a conda package
+/// that doesn't provide them according to the conda to PyPI mapping. Both
+/// packages end up in the environment and most likely overwrite each other's
+/// files when the PyPI package is installed.
+///
+/// Only packages with the same name are compared because the files of the
+/// packages are not known at this point. A PyPI package that overwrites a
+/// conda package with a different name, e.g. `torch` and `pytorch`, is only
+/// detected when it is installed.
+fn check_clobbering_packages(
+    clobber_policy: PypiClobberPolicy,
+    locked_conda_records: &[RepoDataRecord],
+    locked_pypi_packages: &[PypiRecord],
+) -> miette::Result<()> {
+    let overlapping = locked_pypi_packages
+        .iter()
+        .filter_map(|(package, _)| {
+            let record = locked_conda_records.iter().find(|record| {
+                pep508_rs::PackageName::from_str(record.package_record.name.as_normalized())
+                    .is_ok_and(|name| name == package.name)
+            })?;
+            Some(format!(
+                "\t{} {} (pypi) and {} {} (conda)",
+                package.name,
+                package.version,
+                record.package_record.name.as_normalized(),
+                record.package_record.version
+            ))
+        })
+        .collect::<Vec<_>>();
+    if overlapping.is_empty() {
+        return Ok(());
+    }
+
+    let overlapping = overlapping.join("\n");
+    match clobber_policy {
+        PypiClobberPolicy::Error => miette::bail!(
+            help = "remove one of the packages from the manifest, add the missing name to the `conda-pypi-map`, or change `pypi-clobber-policy` in the [project] table",
+            "these pypi-packages are not provided by the conda-packages with the same name according to the conda-pypi mapping, and would overwrite their files:\n{overlapping}"
+        ),
+        PypiClobberPolicy::Warn | PypiClobberPolicy::PreferConda => tracing::warn!(
+            "These pypi-packages are not provided by the conda-packages with the same name according to the conda-pypi mapping, and will likely overwrite their files: \n{overlapping}"
+        ),
+        PypiClobberPolicy::PreferPypi => tracing::debug!(
+            "These pypi-packages have the same name as conda-packages: \n{overlapping}"
+        ),
+    }
+    Ok(())
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -798,3 +854,85 @@ async fn lock_pypi_packages<'a>(
 
     Ok(locked_packages)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use pep440_rs::Version;
+    use rattler_conda_types::{PackageRecord, VersionWithSource};
+    use rattler_lock::{PypiPackageData, PypiPackageEnvironmentData, UrlOrPath};
+
+    use super::*;
+
+    fn conda_record(name: &str) -> RepoDataRecord {
+        RepoDataRecord {
+            package_record: PackageRecord::new(
+                name.parse().unwrap(),
+                VersionWithSource::from_str("1.0.0").unwrap(),
+                "h123_0".to_string(),
+            ),
+            file_name: format!("{name}-1.0.0-h123_0.conda"),
+            url: Url::parse(&format!(
+                "https://example.com/channel/noarch/{name}-1.0.0-h123_0.conda"
+            ))
+            .unwrap(),
+            channel: "https://example.com/channel/".to_string(),
+        }
+    }
+
+    fn pypi_record(name: &str) -> PypiRecord {
+        (
+            PypiPackageData {
+                name: name.parse().unwrap(),
+                version: Version::from_str("2.0.0").unwrap(),
+                url_or_path: UrlOrPath::Url(
+                    Url::parse(&format!(
+                        "https://example.com/{name}-2.0.0-py3-none-any.whl"
+                    ))
+                    .unwrap(),
+                ),
+                hash: None,
+                requires_dist: vec![],
+                requires_python: None,
+                editable: false,
+            },
+            PypiPackageEnvironmentData::default(),
+        )
+    }
+
+    #[test]
+    fn test_check_clobbering_packages() {
+        let conda_records = [conda_record("pytorch"), conda_record("six")];
+
+        // Packages with the same name clobber each other.
+        let err = check_clobbering_packages(
+            PypiClobberPolicy::Error,
+            &conda_records,
+            &[pypi_record("six")],
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("six 2.0.0 (pypi) and six 1.0.0 (conda)"));
+
+        // The other policies only report them.
+        for policy in [
+            PypiClobberPolicy::Warn,
+            PypiClobberPolicy::PreferConda,
+            PypiClobberPolicy::PreferPypi,
+        ] {
+            assert!(
+                check_clobbering_packages(policy, &conda_records, &[pypi_record("six")]).is_ok()
+            );
+        }
+
+        // Packages with a different name are not compared.
+        assert!(check_clobbering_packages(
+            PypiClobberPolicy::Error,
+            &conda_records,
+            &[pypi_record("torch")],
+        )
+        .is_ok());
+    }
+}
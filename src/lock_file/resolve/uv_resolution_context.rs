@@ -2,13 +2,17 @@ use std::sync::Arc;
 
 use miette::{Context, IntoDiagnostic};
 use uv_cache::Cache;
+use uv_client::Connectivity;
 use uv_configuration::{BuildOptions, Concurrency, SourceStrategy};
 use uv_distribution_types::IndexCapabilities;
 use uv_types::{HashStrategy, InFlight};
 
 use crate::Project;
-use pixi_config::{self, get_cache_dir};
+use pixi_config::{self, get_cache_dir, Config};
 use pixi_consts::consts;
+use pixi_manifest::FeaturesExt;
+use pixi_utils::reqwest::build_uv_client;
+use rattler_lock::FindLinksUrlOrPath;
 
 /// Objects that are needed for resolutions which can be shared between different resolutions.
 #[derive(Clone)]
@@ -22,10 +26,42 @@ pub struct UvResolutionContext {
     pub concurrency: Concurrency,
     pub source_strategy: SourceStrategy,
     pub capabilities: IndexCapabilities,
+    pub connectivity: Connectivity,
 }
 
 impl UvResolutionContext {
     pub(crate) fn from_project(project: &Project) -> miette::Result<Self> {
+        // uv doesn't use the middleware of pixi, so the client certificate of
+        // the package indexes has to be configured on the client itself.
+        let index_urls = project
+            .environments()
+            .iter()
+            .flat_map(|environment| {
+                let pypi_options = environment.pypi_options();
+                let find_links = pypi_options
+                    .find_links
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|find_links| match find_links {
+                        FindLinksUrlOrPath::Url(url) => Some(url),
+                        FindLinksUrlOrPath::Path(_) => None,
+                    });
+                let index_url = pypi_options
+                    .index_url
+                    .unwrap_or_else(|| consts::DEFAULT_PYPI_INDEX_URL.clone());
+                std::iter::once(index_url)
+                    .chain(pypi_options.extra_index_urls.unwrap_or_default())
+                    .chain(find_links)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let client = build_uv_client(project.config(), &index_urls)?;
+        Self::from_config(project.config(), client)
+    }
+
+    /// Creates the context from the given config, used when there is no
+    /// project.
+    pub(crate) fn from_config(config: &Config, client: reqwest::Client) -> miette::Result<Self> {
         let uv_cache = get_cache_dir()?.join(consts::PYPI_CACHE_DIR);
         if !uv_cache.exists() {
             std::fs::create_dir_all(&uv_cache)
@@ -35,7 +71,7 @@ impl UvResolutionContext {
 
         let cache = Cache::from_path(uv_cache);
 
-        let keyring_provider = match project.config().pypi_config().use_keyring() {
+        let keyring_provider = match config.pypi_config().use_keyring() {
             pixi_config::KeyringProvider::Subprocess => {
                 tracing::info!("using uv keyring (subprocess) provider");
                 uv_configuration::KeyringProviderType::Subprocess
@@ -46,17 +82,25 @@ impl UvResolutionContext {
             }
         };
 
+        let connectivity = if config.offline() {
+            tracing::info!("uv will only use cached data because pixi is offline");
+            Connectivity::Offline
+        } else {
+            Connectivity::Online
+        };
+
         let in_flight = Arc::new(InFlight::default());
         Ok(Self {
             cache,
             in_flight,
             hash_strategy: HashStrategy::None,
-            client: project.client().clone(),
+            client,
             build_options: BuildOptions::default(),
             keyring_provider,
             concurrency: Concurrency::default(),
             source_strategy: SourceStrategy::Disabled,
             capabilities: IndexCapabilities::default(),
+            connectivity,
         })
     }
 }
@@ -211,6 +211,12 @@ pub enum PlatformUnsat {
 
     #[error("failed to convert between pep508 and uv types {0}")]
     UvTypesConversionError(#[from] ConversionError),
+
+    #[error("'{0}' is locked as a source distribution but building it is disabled by `no-build` or `only-binary`")]
+    LockedPyPISourceNotAllowed(pep508_rs::PackageName),
+
+    #[error("'{0}' is locked as a wheel but `no-binary` requires it to be built from source")]
+    LockedPyPIWheelNotAllowed(pep508_rs::PackageName),
 }
 
 impl PlatformUnsat {
@@ -225,7 +231,9 @@ impl PlatformUnsat {
                 | PlatformUnsat::FailedToDetermineSourceTreeHash(_, _)
                 | PlatformUnsat::PythonVersionMismatch(_, _, _)
                 | PlatformUnsat::EditablePackageMismatch(_)
-                | PlatformUnsat::SourceTreeHashMismatch(_),
+                | PlatformUnsat::SourceTreeHashMismatch(_)
+                | PlatformUnsat::LockedPyPISourceNotAllowed(_)
+                | PlatformUnsat::LockedPyPIWheelNotAllowed(_),
         )
     }
 }
@@ -427,6 +435,25 @@ pub fn verify_platform_satisfiability(
         }
     }
 
+    // Verify that the locked pypi packages can be installed with the build
+    // options of the environment.
+    if !pypi_packages.is_empty() {
+        let pypi_options = environment.pypi_options();
+        for (package, _) in &pypi_packages {
+            if is_wheel(&package.url_or_path) {
+                if !pypi_options.allows_binary(&package.name) {
+                    return Err(PlatformUnsat::LockedPyPIWheelNotAllowed(
+                        package.name.clone(),
+                    ));
+                }
+            } else if !pypi_options.allows_build(&package.name) {
+                return Err(PlatformUnsat::LockedPyPISourceNotAllowed(
+                    package.name.clone(),
+                ));
+            }
+        }
+    }
+
     // Create a lookup table from package name to package record. Returns an error
     // if we find a duplicate entry for a record
     let repodata_records_by_name = match RepoDataRecordsByName::from_unique_iter(conda_packages) {
@@ -454,6 +481,16 @@ pub fn verify_platform_satisfiability(
     )
 }
 
+/// Returns true if the locked pypi package is a wheel, everything else has to
+/// be built from source. This includes local directories and editable
+/// packages, which are therefore not allowed with `no-build` just like in uv.
+fn is_wheel(url_or_path: &UrlOrPath) -> bool {
+    match url_or_path {
+        UrlOrPath::Url(url) => url.path().ends_with(".whl"),
+        UrlOrPath::Path(path) => path.extension().is_some_and(|ext| ext == "whl"),
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 enum Dependency {
     Input(
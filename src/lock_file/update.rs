@@ -7,11 +7,12 @@ use itertools::Itertools;
 use miette::{Diagnostic, IntoDiagnostic, LabeledSpan, MietteDiagnostic, WrapErr};
 use parking_lot::Mutex;
 use pixi_consts::consts;
-use pixi_manifest::{EnvironmentName, FeaturesExt, HasFeaturesIter};
+use pixi_manifest::{EnvironmentName, FeaturesExt, HasFeaturesIter, PypiClobberPolicy};
 use pixi_progress::global_multi_progress;
+use pixi_utils::PrefixGuard;
 use pixi_uv_conversions::{
-    to_extra_name, to_marker_environment, to_normalize, to_uv_extra_name, to_uv_normalize,
-    ConversionError,
+    pypi_options_to_build_options, to_extra_name, to_marker_environment, to_normalize,
+    to_uv_extra_name, to_uv_normalize, ConversionError,
 };
 use pypi_mapping::{self, Reporter};
 use pypi_modifiers::{pypi_marker_env::determine_marker_environment, pypi_tags::is_python_record};
@@ -20,6 +21,7 @@ use rattler_conda_types::{Arch, Channel, MatchSpec, Platform, RepoDataRecord};
 use rattler_lock::{LockFile, PypiIndexes, PypiPackageData, PypiPackageEnvironmentData};
 use rattler_repodata_gateway::{Gateway, RepoData};
 use rattler_solve::ChannelPriority;
+use reqwest_middleware::ClientWithMiddleware;
 use std::cmp::PartialEq;
 use std::{
     borrow::Cow,
@@ -27,7 +29,7 @@ use std::{
     fmt::Write,
     future::{ready, Future},
     iter,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -44,14 +46,16 @@ use crate::environment::{read_environment_file, LockedEnvironmentHash};
 use crate::repodata::Repodata;
 use crate::{
     activation::CurrentEnvVarBehavior,
+    cache_registry,
     environment::{
         self, write_environment_file, EnvironmentFile, LockFileUsage, PerEnvironmentAndPlatform,
         PerGroup, PerGroupAndPlatform, PythonStatus,
     },
+    environment_store::{self, EnvironmentStore},
     load_lock_file,
     lock_file::{
-        self, update, utils::IoConcurrencyLimit, OutdatedEnvironments, PypiRecord,
-        PypiRecordsByName, RepoDataRecordsByName, UvResolutionContext,
+        self, update, utils::IoConcurrencyLimit, CondaSpecSources, OutdatedEnvironments,
+        PypiRecord, PypiRecordsByName, RepoDataRecordsByName, UvResolutionContext,
     },
     prefix::Prefix,
     project::{
@@ -141,6 +145,26 @@ pub enum UpdateMode {
     Revalidate,
 }
 
+/// Selects which packages of an environment are installed, used to install
+/// the conda and the PyPI packages in separate steps.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InstallFilter {
+    /// Only install the conda packages.
+    pub skip_pypi: bool,
+    /// Only install the PyPI packages, the conda packages must already be
+    /// installed from the same lock-file.
+    pub only_pypi: bool,
+    /// Don't install the editable PyPI packages.
+    pub skip_editable: bool,
+}
+
+impl InstallFilter {
+    /// Returns true if all packages are installed.
+    pub fn is_full(&self) -> bool {
+        !self.skip_pypi && !self.only_pypi && !self.skip_editable
+    }
+}
+
 impl<'p> LockFileDerivedData<'p> {
     /// Write the lock-file to disk.
     pub(crate) fn write_to_disk(&self) -> miette::Result<()> {
@@ -171,15 +195,90 @@ impl<'p> LockFileDerivedData<'p> {
         environment: &Environment<'p>,
         update_mode: UpdateMode,
     ) -> miette::Result<Prefix> {
-        // Check if the prefix is already up-to-date by validating the hash with the environment file
+        self.filtered_prefix(environment, update_mode, InstallFilter::default())
+            .await
+    }
+
+    /// Returns the prefix for the given environment, with only the packages
+    /// selected by the `filter` brought up-to-date.
+    pub async fn filtered_prefix(
+        &mut self,
+        environment: &Environment<'p>,
+        update_mode: UpdateMode,
+        filter: InstallFilter,
+    ) -> miette::Result<Prefix> {
         let hash = self.locked_environment_hash(environment)?;
+
+        // Shared environments are installed in the store and linked into the
+        // project. The environment in the store is locked while it is checked
+        // and installed, so concurrent installs of the same lock-file wait for
+        // each other.
+        let store = self.environment_store(environment)?;
+        let prefix_dir = match &store {
+            Some(store) => store.prefix_dir(&hash),
+            None => {
+                // The environment might still link into the store from when
+                // it was shared, which is replaced by an installation in the
+                // project. The store is only needed when the environment
+                // directory is a link at all.
+                if environment.dir().is_symlink() {
+                    match self.project.config().detached_environments().store_path() {
+                        Ok(store_path) => {
+                            EnvironmentStore::new(store_path).unlink(&environment.dir())?;
+                        }
+                        Err(e) => tracing::debug!(
+                            "failed to determine the environment store, not unlinking {}: {e}",
+                            environment.dir().display()
+                        ),
+                    }
+                }
+                environment.dir()
+            }
+        };
+        let mut store_guard = store
+            .as_ref()
+            .map(|_| PrefixGuard::new(&prefix_dir))
+            .transpose()
+            .into_diagnostic()
+            .context("failed to create prefix guard")?;
+        let _store_lock = store_guard
+            .as_mut()
+            .map(|guard| guard.write())
+            .transpose()
+            .into_diagnostic()
+            .context("failed to acquire write lock to prefix guard")?;
+
+        // Check if the prefix is already up-to-date by validating the hash with the environment file
+        let environment_file = read_environment_file(&prefix_dir);
+
+        // The PyPI packages can only be installed on top of the conda packages of
+        // the same lock-file.
+        if filter.only_pypi {
+            let conda_installed = matches!(
+                &environment_file,
+                Ok(Some(environment_file)) if environment_file.environment_lock_file_hash == hash
+            );
+            if !conda_installed {
+                return Err(miette::miette!(
+                    help = "install the conda packages first with `pixi install --skip-pypi`",
+                    "the conda packages of '{}' are not installed from the current lock-file",
+                    environment.name().fancy_display()
+                ));
+            }
+        }
+
         if update_mode == UpdateMode::QuickValidate {
-            if let Ok(Some(environment_file)) = read_environment_file(&environment.dir()) {
-                if environment_file.environment_lock_file_hash == hash {
+            if let Ok(Some(environment_file)) = &environment_file {
+                if environment_file.environment_lock_file_hash == hash
+                    && environment_file.is_complete(self.project.pypi_clobber_policy())
+                {
                     tracing::info!(
                         "Environment '{}' is up-to-date with lock file hash",
                         environment.name().fancy_display()
                     );
+                    if let Some(store) = &store {
+                        store.link(&hash, &environment.dir())?;
+                    }
                     return Ok(Prefix::new(environment.dir()));
                 }
             } else {
@@ -191,34 +290,94 @@ impl<'p> LockFileDerivedData<'p> {
         }
 
         // Get the up-to-date prefix
-        let prefix = self.update_prefix(environment).await?;
+        let prefix = self.update_prefix(environment, &prefix_dir, filter).await?;
 
         // Save an environment file to the environment directory after the update.
-        // Avoiding writing the cache away before the update is done.
+        // Avoiding writing the cache away before the update is done. The PyPI
+        // packages of a previous `--skip-pypi` install are installed by now
+        // when only installing the PyPI packages.
         write_environment_file(
-            &environment.dir(),
+            &prefix_dir,
             EnvironmentFile {
                 manifest_path: environment.project().manifest_path(),
                 environment_name: environment.name().to_string(),
                 pixi_version: consts::PIXI_VERSION.to_string(),
                 environment_lock_file_hash: hash,
+                skipped_pypi: filter.skip_pypi,
+                skipped_editable: filter.skip_editable,
+                skipped_provided_by_conda: self.project.pypi_clobber_policy()
+                    == PypiClobberPolicy::PreferConda,
             },
         )?;
 
+        if let Some(store) = &store {
+            store.link(&hash, &environment.dir())?;
+            return Ok(Prefix::new(environment.dir()));
+        }
+
         Ok(prefix)
     }
 
+    /// Returns the store to install the environment in, or `None` if shared
+    /// environments are disabled or the environment can't be shared.
+    fn environment_store(
+        &self,
+        environment: &Environment<'p>,
+    ) -> miette::Result<Option<EnvironmentStore>> {
+        let Some(store) = EnvironmentStore::from_config(self.project.config())? else {
+            return Ok(None);
+        };
+        let locked_environment = self
+            .lock_file
+            .environment(environment.name().as_str())
+            .ok_or_else(|| UpdateError::LockFileMissingEnv(environment.name().clone()))?;
+        if !environment_store::is_shareable(locked_environment, environment.best_platform()) {
+            tracing::info!(
+                "installing '{}' in the project because it contains editable or local PyPI packages",
+                environment.name().fancy_display()
+            );
+            return Ok(None);
+        }
+        Ok(Some(store))
+    }
+
     /// Returns the up-to-date prefix for the given environment.
-    async fn update_prefix(&mut self, environment: &Environment<'p>) -> miette::Result<Prefix> {
-        // If we previously updated this environment, early out.
-        if let Some(prefix) = self.updated_pypi_prefixes.get(environment.name()) {
-            return Ok(prefix.clone());
+    async fn update_prefix(
+        &mut self,
+        environment: &Environment<'p>,
+        prefix_dir: &Path,
+        filter: InstallFilter,
+    ) -> miette::Result<Prefix> {
+        // If we previously updated this environment in the same directory, early
+        // out. A shared environment is installed in the store instead of the
+        // environment directory.
+        if filter.is_full() {
+            if let Some(prefix) = self
+                .updated_pypi_prefixes
+                .get(environment.name())
+                .filter(|prefix| prefix.root() == prefix_dir)
+            {
+                return Ok(prefix.clone());
+            }
         }
 
         tracing::info!("Updating prefix");
-        // Get the prefix with the conda packages installed.
+        // Get the prefix with the conda packages installed. When only installing
+        // the PyPI packages the conda packages are not touched, they are already
+        // installed from the same lock-file.
         let platform = environment.best_platform();
-        let (prefix, python_status) = self.conda_prefix(environment).await?;
+        let (prefix, python_status) = if filter.only_pypi {
+            let prefix = Prefix::new(prefix_dir);
+            let installed_packages = prefix.find_installed_packages(None).await?;
+            let python_status = PythonStatus::from_installed(&installed_packages, platform);
+            (prefix, python_status)
+        } else {
+            self.conda_prefix(environment, prefix_dir).await?
+        };
+        if filter.skip_pypi {
+            return Ok(prefix);
+        }
+
         let repodata_records = self
             .repodata_records(environment, platform)
             .into_diagnostic()?
@@ -256,7 +415,16 @@ impl<'p> LockFileDerivedData<'p> {
             )
             .await?;
 
-        let non_isolated_packages = environment.pypi_options().no_build_isolation;
+        let pypi_options = environment.pypi_options();
+        let non_isolated_packages = pypi_options.no_build_isolation.clone();
+
+        // Only install wheels and build source distributions as allowed by the
+        // pypi-options of the environment.
+        let uv_context = UvResolutionContext {
+            build_options: pypi_options_to_build_options(&pypi_options).into_diagnostic()?,
+            ..uv_context
+        };
+
         // Update the prefix with Pypi records
         environment::update_prefix_pypi(
             environment.name(),
@@ -272,6 +440,8 @@ impl<'p> LockFileDerivedData<'p> {
             self.project.root(),
             environment.best_platform(),
             non_isolated_packages,
+            self.project.pypi_clobber_policy(),
+            filter.skip_editable,
         )
         .await
         .with_context(|| {
@@ -282,8 +452,10 @@ impl<'p> LockFileDerivedData<'p> {
         })?;
 
         // Store that we updated the environment, so we won't have to do it again.
-        self.updated_pypi_prefixes
-            .insert(environment.name().clone(), prefix.clone());
+        if filter.is_full() {
+            self.updated_pypi_prefixes
+                .insert(environment.name().clone(), prefix.clone());
+        }
 
         Ok(prefix)
     }
@@ -329,13 +501,20 @@ impl<'p> LockFileDerivedData<'p> {
     async fn conda_prefix(
         &mut self,
         environment: &Environment<'p>,
+        prefix_dir: &Path,
     ) -> miette::Result<(Prefix, PythonStatus)> {
-        // If we previously updated this environment, early out.
-        if let Some((prefix, python_status)) = self.updated_conda_prefixes.get(environment.name()) {
+        // If we previously updated this environment in the same directory, early
+        // out. The prefixes instantiated while solving are in the environment
+        // directory, not in the store of the shared environments.
+        if let Some((prefix, python_status)) = self
+            .updated_conda_prefixes
+            .get(environment.name())
+            .filter(|(prefix, _)| prefix.root() == prefix_dir)
+        {
             return Ok((prefix.clone(), python_status.clone()));
         }
 
-        let prefix = Prefix::new(environment.dir());
+        let prefix = Prefix::new(prefix_dir);
         let platform = environment.best_platform();
 
         // Determine the currently installed packages.
@@ -361,7 +540,7 @@ impl<'p> LockFileDerivedData<'p> {
         let python_status = environment::update_prefix_conda(
             &prefix,
             self.package_cache.clone(),
-            environment.project().authenticated_client().clone(),
+            environment.project().authenticated_client()?.clone(),
             installed_packages,
             records,
             platform,
@@ -454,6 +633,10 @@ pub struct UpdateContext<'p> {
 
     /// Whether it is allowed to instantiate any prefix.
     no_install: bool,
+
+    /// The directory in which the prefixes needed to solve pypi dependencies
+    /// are instantiated instead of in the environment directories.
+    solve_prefix_dir: Option<PathBuf>,
 }
 
 impl<'p> UpdateContext<'p> {
@@ -634,6 +817,10 @@ pub async fn update_lock_file(
     let package_cache =
         PackageCache::new(pixi_config::get_cache_dir()?.join(consts::CONDA_PACKAGE_CACHE_DIR));
 
+    // Remember that this project uses the cache, so `pixi clean cache --unused`
+    // keeps the packages it references.
+    cache_registry::register_lock_file(&project.lock_file_path());
+
     // should we check the lock-file in the first place?
     if !options.lock_file_usage.should_check_if_out_of_date() {
         tracing::info!("skipping check if lock-file is up-to-date");
@@ -710,6 +897,10 @@ pub struct UpdateContextBuilder<'p> {
     /// a python interpreter.
     no_install: bool,
 
+    /// The directory in which the prefixes needed to solve pypi dependencies
+    /// are instantiated instead of in the environment directories.
+    solve_prefix_dir: Option<PathBuf>,
+
     /// The package cache to use during the update process.
     package_cache: Option<PackageCache>,
 
@@ -739,6 +930,16 @@ impl<'p> UpdateContextBuilder<'p> {
         Self { no_install, ..self }
     }
 
+    /// Instantiates the prefixes that are needed to solve pypi dependencies
+    /// in the given directory, so the environments of the project are left
+    /// untouched.
+    pub(crate) fn with_solve_prefix_dir(self, solve_prefix_dir: PathBuf) -> Self {
+        Self {
+            solve_prefix_dir: Some(solve_prefix_dir),
+            ..self
+        }
+    }
+
     /// Sets the current lock-file that should be used to determine the
     /// previously locked packages.
     pub(crate) fn with_lock_file(self, lock_file: LockFile) -> Self {
@@ -964,6 +1165,7 @@ impl<'p> UpdateContextBuilder<'p> {
             io_concurrency_limit: self.io_concurrency_limit.unwrap_or_default(),
 
             no_install: self.no_install,
+            solve_prefix_dir: self.solve_prefix_dir,
         })
     }
 }
@@ -976,6 +1178,7 @@ impl<'p> UpdateContext<'p> {
             lock_file: LockFile::default(),
             outdated_environments: None,
             no_install: true,
+            solve_prefix_dir: None,
             package_cache: None,
             max_concurrent_solves: None,
             io_concurrency_limit: None,
@@ -1052,10 +1255,11 @@ impl<'p> UpdateContext<'p> {
                 let group_solve_task = spawn_solve_conda_environment_task(
                     source.clone(),
                     locked_group_records,
-                    project.repodata_gateway().clone(),
+                    project.repodata_gateway()?.clone(),
                     platform,
                     self.conda_solve_semaphore.clone(),
-                    project.client().clone(),
+                    project.client()?.clone(),
+                    project.authenticated_client()?.clone(),
                     channel_priority,
                 )
                 .boxed_local();
@@ -1118,8 +1322,13 @@ impl<'p> UpdateContext<'p> {
 
             // Spawn a task to instantiate the environment
             let environment_name = environment.name().clone();
+            let prefix = match &self.solve_prefix_dir {
+                Some(dir) => Prefix::new(dir.join(group.name().as_str())),
+                None => group.prefix(),
+            };
             let pypi_env_task = spawn_create_prefix_task(
                 group.clone(),
+                prefix,
                 self.package_cache.clone(),
                 records_future,
                 self.io_concurrency_limit.clone(),
@@ -1479,7 +1688,12 @@ impl<'p> UpdateContext<'p> {
         Ok(LockFileDerivedData {
             project,
             lock_file,
-            updated_conda_prefixes: self.take_instantiated_conda_prefixes(),
+            // Prefixes outside of the environment directories can't be reused
+            // for the environments.
+            updated_conda_prefixes: match self.solve_prefix_dir {
+                Some(_) => HashMap::default(),
+                None => self.take_instantiated_conda_prefixes(),
+            },
             package_cache: self.package_cache,
             updated_pypi_prefixes: HashMap::default(),
             uv_context,
@@ -1585,7 +1799,8 @@ async fn spawn_solve_conda_environment_task(
     repodata_gateway: Gateway,
     platform: Platform,
     concurrency_semaphore: Arc<Semaphore>,
-    client: reqwest::Client,
+    client: ClientWithMiddleware,
+    package_client: ClientWithMiddleware,
     channel_priority: ChannelPriority,
 ) -> miette::Result<TaskResult> {
     // Get the dependencies for this platform
@@ -1609,6 +1824,9 @@ async fn spawn_solve_conda_environment_task(
     // Get the channel configuration
     let channel_config = group.project().channel_config();
 
+    // The location of the specs in the manifest, to explain a failed solve.
+    let spec_sources = CondaSpecSources::from_group(&group, platform);
+
     tokio::spawn(
         async move {
             let _permit = concurrency_semaphore
@@ -1670,11 +1888,11 @@ async fn spawn_solve_conda_environment_task(
                 channel_priority,
             )
             .await
-            .with_context(|| {
-                format!(
-                    "failed to solve the conda requirements of '{}' '{}'",
+            .map_err(|err| {
+                spec_sources.into_solve_error(
+                    err,
                     group_name.fancy_display(),
-                    consts::PLATFORM_STYLE.apply_to(platform)
+                    consts::PLATFORM_STYLE.apply_to(platform),
                 )
             })?;
 
@@ -1683,7 +1901,8 @@ async fn spawn_solve_conda_environment_task(
             if has_pypi_dependencies {
                 pb.set_message("extracting pypi packages");
                 pypi_mapping::amend_pypi_purls(
-                    client.into(),
+                    client,
+                    package_client,
                     &pypi_name_mapping_location,
                     &mut records,
                     Some(pb.purl_amend_reporter()),
@@ -1935,8 +2154,11 @@ async fn spawn_solve_pypi_task(
     let mut conda_records = repodata_records.records.clone();
     let locked_pypi_records = locked_pypi_packages.records.clone();
 
+    // The authenticated client is only needed to download the packages of
+    // private channels when the mapping is derived from the package contents.
     pypi_mapping::amend_pypi_purls(
-        environment.project().client().clone().into(),
+        environment.project().client()?.clone(),
+        environment.project().authenticated_client()?.clone(),
         pypi_name_mapping_location,
         &mut conda_records,
         None,
@@ -1944,6 +2166,7 @@ async fn spawn_solve_pypi_task(
     .await?;
 
     let pypi_options = environment.pypi_options();
+    let clobber_policy = environment.project().pypi_clobber_policy();
     // let (pypi_packages, duration) = tokio::spawn(
     let (pypi_packages, duration) = async move {
         let pb = SolveProgressBar::new(
@@ -1976,6 +2199,7 @@ async fn spawn_solve_pypi_task(
         let records = lock_file::resolve_pypi(
             resolution_context,
             &pypi_options,
+            clobber_policy,
             index_map,
             system_requirements,
             &conda_records,
@@ -2021,13 +2245,13 @@ async fn spawn_solve_pypi_task(
 /// available.
 async fn spawn_create_prefix_task(
     group: GroupedEnvironment<'_>,
+    prefix: Prefix,
     package_cache: PackageCache,
     conda_records: impl Future<Output = Arc<RepoDataRecordsByName>>,
     io_concurrency_limit: IoConcurrencyLimit,
 ) -> miette::Result<TaskResult> {
     let group_name = group.name().clone();
-    let prefix = group.prefix();
-    let client = group.project().authenticated_client().clone();
+    let client = group.project().authenticated_client()?.clone();
 
     // Spawn a task to determine the currently installed packages.
     let installed_packages_future = tokio::spawn({
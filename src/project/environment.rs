@@ -8,10 +8,11 @@ use std::{
 };
 
 use itertools::Either;
+use miette::{LabeledSpan, NamedSource};
 use pixi_consts::consts;
 use pixi_manifest::{
-    self as manifest, EnvironmentName, Feature, FeatureName, FeaturesExt, HasFeaturesIter,
-    HasManifestRef, Manifest, SystemRequirements, Task, TaskName,
+    self as manifest, parse_env_file, EnvironmentName, Feature, FeatureName, FeaturesExt,
+    HasFeaturesIter, HasManifestRef, Manifest, SystemRequirements, Task, TaskName,
 };
 use rattler_conda_types::{Arch, Platform};
 
@@ -271,14 +272,63 @@ impl<'p> Environment<'p> {
     /// this environment.
     ///
     /// The environment variables of all features are combined in the order they
-    /// are defined for the environment.
+    /// are defined for the environment. Within a feature the variables from the
+    /// `env-file`s are read first, in order, and the variables from `env`
+    /// take precedence over them.
+    ///
+    /// The env-files are only read once per platform, later calls return the
+    /// cached variables.
     pub(crate) fn activation_env(&self, platform: Option<Platform>) -> IndexMap<String, String> {
-        self.features()
-            .map(|f| f.activation_env(platform))
-            .fold(IndexMap::new(), |mut acc, env| {
-                acc.extend(env.iter().map(|(k, v)| (k.clone(), v.clone())));
-                acc
-            })
+        let Some(cache) = self
+            .project()
+            .env_vars
+            .get(self.name())
+            .map(|vars| vars.activation_env())
+        else {
+            return self.read_activation_env(platform);
+        };
+
+        let mut cache = cache.lock();
+        cache
+            .entry(platform)
+            .or_insert_with(|| self.read_activation_env(platform))
+            .clone()
+    }
+
+    /// Reads the variables of the activation tables and env-files of the
+    /// features of this environment, see [`Self::activation_env`]. An env-file
+    /// that can't be read is skipped with a warning that points at its
+    /// definition in the manifest.
+    fn read_activation_env(&self, platform: Option<Platform>) -> IndexMap<String, String> {
+        let manifest = &self.project().manifest;
+        let root = self.project().root();
+        self.features().fold(IndexMap::new(), |mut acc, feature| {
+            for (env_file, span) in feature.activation_env_files(platform) {
+                let path = root.join(env_file);
+                let result = fs_err::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| {
+                        parse_env_file(&contents, &mut acc).map_err(|e| e.to_string())
+                    });
+                if let Err(e) = result {
+                    let report = miette::miette!(
+                        labels = span
+                            .map(|span| LabeledSpan::at(span, "the env-file is defined here"))
+                            .into_iter()
+                            .collect::<Vec<_>>(),
+                        "failed to read the env-file '{}': {e}",
+                        path.display(),
+                    )
+                    .with_source_code(NamedSource::new(
+                        manifest.file_name(),
+                        manifest.contents.clone(),
+                    ));
+                    tracing::warn!("{:?}", report);
+                }
+            }
+            acc.extend(feature.activation_env(platform));
+            acc
+        })
     }
 
     /// Validates that the given platform is supported by this environment.
@@ -581,6 +631,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_activation_env_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs_err::write(
+            temp_dir.path().join(".env"),
+            "HOST=localhost\nURL=http://$HOST\nOVERRIDE=from-file\n",
+        )
+        .unwrap();
+        fs_err::write(temp_dir.path().join(".env.linux"), "HOST=linux-host\n").unwrap();
+
+        let manifest = Project::from_str(
+            &temp_dir.path().join("pixi.toml"),
+            r#"
+            [project]
+            name = "foobar"
+            channels = []
+            platforms = ["linux-64", "osx-64"]
+
+            [activation]
+            env-file = [".env"]
+            env = { OVERRIDE = "from-env" }
+
+            [target.linux-64.activation]
+            env-file = [".env.linux"]
+            "#,
+        )
+        .unwrap();
+
+        let default_env = manifest.default_environment();
+        assert_eq!(
+            default_env.activation_env(Some(Platform::Osx64)),
+            indexmap! {
+                "HOST".to_string() => "localhost".to_string(),
+                "URL".to_string() => "http://localhost".to_string(),
+                "OVERRIDE".to_string() => "from-env".to_string(),
+            }
+        );
+        assert_eq!(
+            default_env.activation_env(Some(Platform::Linux64))["HOST"],
+            "linux-host"
+        );
+
+        // A missing env-file doesn't prevent loading the manifest, it is
+        // skipped with a warning when activating
+        let project = Project::from_str(
+            &temp_dir.path().join("pixi.toml"),
+            r#"
+            [project]
+            name = "foobar"
+            channels = []
+            platforms = ["linux-64"]
+
+            [activation]
+            env-file = [".env.missing"]
+            "#,
+        )
+        .unwrap();
+        assert!(project
+            .default_environment()
+            .activation_env(Some(Platform::Linux64))
+            .is_empty());
+
+        // An env-file that can't be parsed doesn't prevent loading the
+        // manifest either, it is skipped with a warning when activating
+        fs_err::write(temp_dir.path().join(".env.invalid"), "1A=1\n").unwrap();
+        let project = Project::from_str(
+            &temp_dir.path().join("pixi.toml"),
+            r#"
+            [project]
+            name = "foobar"
+            channels = []
+            platforms = ["linux-64"]
+
+            [activation]
+            env-file = [".env.invalid", ".env"]
+            "#,
+        )
+        .unwrap();
+        let environment = project.default_environment();
+        assert_eq!(
+            environment.activation_env(Some(Platform::Linux64))["HOST"],
+            "localhost"
+        );
+
+        // The env-files are read once, a change is only picked up by the next
+        // command
+        fs_err::write(temp_dir.path().join(".env"), "HOST=changed\n").unwrap();
+        assert_eq!(
+            environment.activation_env(Some(Platform::Linux64))["HOST"],
+            "localhost"
+        );
+    }
+
     #[test]
     fn test_channel_feature_priority() {
         let manifest = Project::from_str(
@@ -15,7 +15,7 @@ use std::{
     hash::Hash,
     path::{Path, PathBuf},
     str::FromStr,
-    sync::{Arc, OnceLock},
+    sync::Arc,
 };
 
 use async_once_cell::OnceCell as AsyncCell;
@@ -26,17 +26,20 @@ use indexmap::{Equivalent, IndexMap};
 use itertools::Itertools;
 use miette::IntoDiagnostic;
 use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
 use pep440_rs::VersionSpecifiers;
 use pep508_rs::{Requirement, VersionOrUrl::VersionSpecifier};
-use pixi_config::{Config, PinningStrategy};
+use pixi_config::{CondaPypiMapSource, Config, PinningStrategy};
 use pixi_consts::consts;
 use pixi_manifest::{
     pypi::PyPiPackageName, DependencyOverwriteBehavior, EnvironmentName, Environments, FeatureName,
     FeaturesExt, HasFeaturesIter, HasManifestRef, KnownPreviewFeature, Manifest, ParsedManifest,
-    PypiDependencyLocation, SpecType,
+    PypiClobberPolicy, PypiDependencyLocation, SpecType,
 };
 use pixi_utils::reqwest::build_reqwest_clients;
-use pypi_mapping::{ChannelName, CustomMapping, MappingLocation, MappingSource};
+use pypi_mapping::{
+    ChannelName, CustomMapping, MappingLocation, MappingSource, PACKAGE_CONTENTS_MAPPING,
+};
 use rattler_conda_types::{Channel, ChannelConfig, MatchSpec, PackageName, Platform, Version};
 use rattler_lock::{LockFile, Package};
 use rattler_repodata_gateway::Gateway;
@@ -79,6 +82,7 @@ pub struct EnvironmentVars {
     clean: Arc<AsyncCell<HashMap<String, String>>>,
     pixi_only: Arc<AsyncCell<HashMap<String, String>>>,
     full: Arc<AsyncCell<HashMap<String, String>>>,
+    activation_env: Arc<Mutex<HashMap<Option<Platform>, IndexMap<String, String>>>>,
 }
 
 impl EnvironmentVars {
@@ -88,6 +92,7 @@ impl EnvironmentVars {
             clean: Arc::new(AsyncCell::new()),
             pixi_only: Arc::new(AsyncCell::new()),
             full: Arc::new(AsyncCell::new()),
+            activation_env: Default::default(),
         }
     }
 
@@ -105,6 +110,14 @@ impl EnvironmentVars {
     pub(crate) fn full(&self) -> &Arc<AsyncCell<HashMap<String, String>>> {
         &self.full
     }
+
+    /// Get the variables from the activation tables per platform, including
+    /// the variables read from the env-files
+    pub(crate) fn activation_env(
+        &self,
+    ) -> &Arc<Mutex<HashMap<Option<Platform>, IndexMap<String, String>>>> {
+        &self.activation_env
+    }
 }
 
 /// List of packages that are not following the semver versioning scheme
@@ -123,11 +136,11 @@ pub struct Project {
     /// Root folder of the project
     root: PathBuf,
     /// Reqwest client shared for this project.
-    /// This is wrapped in a `OnceLock` to allow for lazy initialization.
-    client: OnceLock<(reqwest::Client, ClientWithMiddleware)>,
+    /// This is wrapped in a `OnceCell` to allow for lazy initialization.
+    client: OnceCell<(ClientWithMiddleware, ClientWithMiddleware)>,
     /// The repodata gateway to use for answering queries about repodata.
-    /// This is wrapped in a `OnceLock` to allow for lazy initialization.
-    repodata_gateway: OnceLock<Gateway>,
+    /// This is wrapped in a `OnceCell` to allow for lazy initialization.
+    repodata_gateway: OnceCell<Gateway>,
     /// The manifest for the project
     pub(crate) manifest: Manifest,
     /// The environment variables that are activated when the environment is
@@ -518,20 +531,23 @@ impl Project {
             })
     }
 
-    /// Returns the reqwest client used for http networking
-    pub(crate) fn client(&self) -> &reqwest::Client {
-        &self.client_and_authenticated_client().0
+    /// Returns the client without authentication used for public requests,
+    /// like the conda-pypi mapping
+    pub fn client(&self) -> miette::Result<&ClientWithMiddleware> {
+        Ok(&self.client_and_authenticated_client()?.0)
     }
 
     /// Create an authenticated reqwest client for this project
     /// use authentication from `rattler_networking`
-    pub fn authenticated_client(&self) -> &ClientWithMiddleware {
-        &self.client_and_authenticated_client().1
+    pub fn authenticated_client(&self) -> miette::Result<&ClientWithMiddleware> {
+        Ok(&self.client_and_authenticated_client()?.1)
     }
 
-    fn client_and_authenticated_client(&self) -> &(reqwest::Client, ClientWithMiddleware) {
+    fn client_and_authenticated_client(
+        &self,
+    ) -> miette::Result<&(ClientWithMiddleware, ClientWithMiddleware)> {
         self.client
-            .get_or_init(|| build_reqwest_clients(Some(&self.config)))
+            .get_or_try_init(|| build_reqwest_clients(Some(&self.config)))
     }
 
     pub(crate) fn config(&self) -> &Config {
@@ -555,14 +571,62 @@ impl Project {
         self.pixi_dir().join(consts::ACTIVATION_ENV_CACHE_DIR)
     }
 
+    /// Returns what to do when a PyPI package overwrites the files of a conda
+    /// package.
+    pub fn pypi_clobber_policy(&self) -> PypiClobberPolicy {
+        self.manifest
+            .parsed
+            .project
+            .pypi_clobber_policy
+            .unwrap_or_default()
+    }
+
     /// Returns what pypi mapping configuration we should use.
     /// It can be a custom one  in following format : conda_name: pypi_name
     /// Or we can use our self-hosted
+    /// Or it can be derived from the contents of the packages, for the channels
+    /// mapped to `package-contents` or for all channels if the config selects it.
     pub fn pypi_name_mapping_source(&self) -> miette::Result<&MappingSource> {
         fn build_pypi_name_mapping_source(
             manifest: &Manifest,
             channel_config: &ChannelConfig,
+            config_source: CondaPypiMapSource,
         ) -> miette::Result<MappingSource> {
+            let project_channels: HashSet<_> = manifest
+                .parsed
+                .project
+                .channels
+                .iter()
+                .map(|pc| pc.channel.clone().into_channel(channel_config))
+                .try_collect()
+                .into_diagnostic()?;
+
+            let feature_channels: HashSet<_> = manifest
+                .parsed
+                .features
+                .values()
+                .flat_map(|feature| feature.channels.iter())
+                .flatten()
+                .map(|pc| pc.channel.clone().into_channel(channel_config))
+                .try_collect()
+                .into_diagnostic()?;
+
+            let project_and_feature_channels: HashSet<_> =
+                project_channels.union(&feature_channels).collect();
+
+            // The channels without a mapping in the manifest use the mapping
+            // derived from the package contents if the config selects it.
+            let package_contents_for_channels =
+                |mapping: &mut HashMap<ChannelName, MappingLocation>| {
+                    if config_source == CondaPypiMapSource::PackageContents {
+                        for channel in &project_and_feature_channels {
+                            mapping
+                                .entry(channel.canonical_name().trim_end_matches('/').into())
+                                .or_insert(MappingLocation::PackageContents);
+                        }
+                    }
+                };
+
             match manifest.parsed.project.conda_pypi_map.clone() {
                 Some(map) => {
                     let channel_to_location_map = map
@@ -578,28 +642,6 @@ impl Project {
                         return Ok(MappingSource::Disabled);
                     }
 
-                    let project_channels: HashSet<_> = manifest
-                        .parsed
-                        .project
-                        .channels
-                        .iter()
-                        .map(|pc| pc.channel.clone().into_channel(channel_config))
-                        .try_collect()
-                        .into_diagnostic()?;
-
-                    let feature_channels: HashSet<_> = manifest
-                        .parsed
-                        .features
-                        .values()
-                        .flat_map(|feature| feature.channels.iter())
-                        .flatten()
-                        .map(|pc| pc.channel.clone().into_channel(channel_config))
-                        .try_collect()
-                        .into_diagnostic()?;
-
-                    let project_and_feature_channels: HashSet<_> =
-                        project_channels.union(&feature_channels).collect();
-
                     for channel in channel_to_location_map.keys() {
                         if !project_and_feature_channels.contains(channel) {
                             let channels = project_and_feature_channels
@@ -622,16 +664,20 @@ impl Project {
                         }
                     }
 
-                    let mapping = channel_to_location_map
+                    let mut mapping = channel_to_location_map
                         .iter()
                         .map(|(channel, mapping_location)| {
-                            let url_or_path = match Url::parse(mapping_location) {
-                                Ok(url) => MappingLocation::Url(url),
-                                Err(err) => {
-                                    if let ParseError::RelativeUrlWithoutBase = err {
-                                        MappingLocation::Path(PathBuf::from(mapping_location))
-                                    } else {
-                                        miette::bail!("Could not convert {mapping_location} to neither URL or Path")
+                            let url_or_path = if mapping_location == PACKAGE_CONTENTS_MAPPING {
+                                MappingLocation::PackageContents
+                            } else {
+                                match Url::parse(mapping_location) {
+                                    Ok(url) => MappingLocation::Url(url),
+                                    Err(err) => {
+                                        if let ParseError::RelativeUrlWithoutBase = err {
+                                            MappingLocation::Path(PathBuf::from(mapping_location))
+                                        } else {
+                                            miette::bail!("Could not convert {mapping_location} to neither URL or Path")
+                                        }
                                     }
                                 }
                             };
@@ -639,14 +685,24 @@ impl Project {
                             Ok((channel.canonical_name().trim_end_matches('/').into(), url_or_path))
                         })
                         .collect::<miette::Result<HashMap<ChannelName, MappingLocation>>>()?;
+                    package_contents_for_channels(&mut mapping);
 
                     Ok(MappingSource::Custom(CustomMapping::new(mapping).into()))
                 }
+                None if config_source == CondaPypiMapSource::PackageContents => {
+                    let mut mapping = HashMap::new();
+                    package_contents_for_channels(&mut mapping);
+                    Ok(MappingSource::Custom(CustomMapping::new(mapping).into()))
+                }
                 None => Ok(MappingSource::Prefix),
             }
         }
         self.mapping_source.get_or_try_init(|| {
-            build_pypi_name_mapping_source(&self.manifest, &self.channel_config())
+            build_pypi_name_mapping_source(
+                &self.manifest,
+                &self.channel_config(),
+                self.config.conda_pypi_map_source(),
+            )
         })
     }
 
@@ -1382,6 +1438,36 @@ mod tests {
                 .unwrap(),
             &MappingLocation::Path(PathBuf::from("mapping.json"))
         );
+
+        // Check a channel mapped to the contents of its packages
+        let file_contents = r#"
+            [project]
+            name = "foo"
+            channels = ["conda-forge", "https://prefix.dev/private-channel"]
+            platforms = []
+            conda-pypi-map = {"https://prefix.dev/private-channel" = "package-contents"}
+            "#;
+        let manifest = Manifest::from_str(Path::new("pixi.toml"), file_contents).unwrap();
+        let project = Project::from_manifest(manifest);
+
+        let mapping = project.pypi_name_mapping_source().unwrap();
+        assert_eq!(
+            mapping
+                .custom()
+                .unwrap()
+                .mapping
+                .get(
+                    Channel::from_str(
+                        "https://prefix.dev/private-channel",
+                        &project.channel_config()
+                    )
+                    .unwrap()
+                    .canonical_name()
+                    .trim_end_matches('/')
+                )
+                .unwrap(),
+            &MappingLocation::PackageContents
+        );
     }
 
     #[test]
@@ -1,3 +1,7 @@
+use std::path::Path;
+
+use rattler_shell::shell::ShellEnum;
+
 /// Set default pixi prompt for the bash shell
 pub(crate) fn get_bash_hook(env_name: &str) -> String {
     format!(
@@ -89,3 +93,28 @@ pub(crate) fn get_nu_prompt(env_name: &str) -> String {
 pub(crate) fn get_cmd_prompt(env_name: &str) -> String {
     format!(r"@PROMPT ({}) $P$G", env_name)
 }
+
+/// Returns the hook that activates the pixi environment of the current
+/// directory whenever the working directory changes, or `None` if the shell
+/// is not supported.
+pub(crate) fn get_auto_hook(
+    shell: &ShellEnum,
+    pixi_exe: &Path,
+    change_ps1: bool,
+) -> Option<String> {
+    let snippet = match shell {
+        ShellEnum::Bash(_) => include_str!("shell_snippets/pixi-auto-hook.bash"),
+        ShellEnum::Zsh(_) => include_str!("shell_snippets/pixi-auto-hook.zsh"),
+        ShellEnum::Fish(_) => include_str!("shell_snippets/pixi-auto-hook.fish"),
+        ShellEnum::NuShell(_) => include_str!("shell_snippets/pixi-auto-hook.nu"),
+        _ => return None,
+    };
+    Some(
+        snippet
+            .replace("__PIXI_EXE__", &pixi_exe.to_string_lossy())
+            .replace(
+                "__PIXI_CHANGE_PS1__",
+                if change_ps1 { "true" } else { "false" },
+            ),
+    )
+}
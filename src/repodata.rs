@@ -26,5 +26,5 @@ pub(crate) trait Repodata {
     }
 
     /// Returns the [`Gateway`] used by this project.
-    fn repodata_gateway(&self) -> &Gateway;
+    fn repodata_gateway(&self) -> miette::Result<&Gateway>;
 }
@@ -0,0 +1,17 @@
+_PIXI_AUTO_ORIG_PS1="${_PIXI_AUTO_ORIG_PS1-$PS1}"
+
+_pixi_auto_hook() {
+    local previous_exit_status=$?
+    if [[ "${_PIXI_AUTO_PWD:-}" != "$PWD" ]]; then
+        _PIXI_AUTO_PWD="$PWD"
+        eval "$("__PIXI_EXE__" shell-hook --auto-update --shell bash)"
+        if __PIXI_CHANGE_PS1__; then
+            PS1="${PIXI_PROMPT:-}${_PIXI_AUTO_ORIG_PS1}"
+        fi
+    fi
+    return $previous_exit_status
+}
+
+if [[ ";${PROMPT_COMMAND:-};" != *";_pixi_auto_hook;"* ]]; then
+    PROMPT_COMMAND="_pixi_auto_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
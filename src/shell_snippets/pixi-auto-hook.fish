@@ -0,0 +1,20 @@
+function __pixi_auto_hook --on-variable PWD --description 'Activate the pixi environment of the current directory'
+    "__PIXI_EXE__" shell-hook --auto-update --shell fish | source
+end
+
+if __PIXI_CHANGE_PS1__; and not functions -q __pixi_auto_fish_prompt_orig
+    functions -c fish_prompt __pixi_auto_fish_prompt_orig
+
+    function fish_prompt
+        set -l last_status $status
+        if set -q PIXI_PROMPT
+            set_color -o green
+            echo -n $PIXI_PROMPT
+            set_color normal
+        end
+        __pixi_auto_fish_prompt_orig
+        return $last_status
+    end
+end
+
+__pixi_auto_hook
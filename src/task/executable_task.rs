@@ -9,7 +9,8 @@ use deno_task_shell::{
     execute_with_pipes, parser::SequentialList, pipe, ShellPipeWriter, ShellState,
 };
 use itertools::Itertools;
-use miette::{Context, Diagnostic, IntoDiagnostic};
+use miette::{Context, Diagnostic, IntoDiagnostic, NamedSource, SourceSpan};
+use minijinja::{context, UndefinedBehavior};
 use rattler_lock::LockFile;
 use thiserror::Error;
 use tokio::task::JoinHandle;
@@ -26,7 +27,7 @@ use pixi_consts::consts;
 use crate::activation::CurrentEnvVarBehavior;
 use crate::project::virtual_packages::verify_current_platform_has_required_virtual_packages;
 use crate::project::HasProjectRef;
-use pixi_manifest::{Task, TaskName};
+use pixi_manifest::{HasFeaturesIter, Task, TaskName};
 use pixi_progress::await_in_progress;
 
 /// Runs task in project.
@@ -44,6 +45,20 @@ pub struct FailedToParseShellScript {
     pub error: String,
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("failed to render the command of task '{task_name}'")]
+#[diagnostic(help(
+    "the commands of tasks with `template = true` are rendered as templates, see https://pixi.sh/latest/features/advanced_tasks/#templating"
+))]
+pub struct FailedToRenderTemplate {
+    pub task_name: String,
+    #[source_code]
+    pub src: NamedSource<String>,
+    #[label("{reason}")]
+    pub span: Option<SourceSpan>,
+    pub reason: String,
+}
+
 #[derive(Debug, Error, Diagnostic)]
 #[error("invalid working directory '{path}'")]
 pub struct InvalidWorkingDirectory {
@@ -57,6 +72,10 @@ pub enum TaskExecutionError {
 
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    FailedToRenderTemplate(#[from] FailedToRenderTemplate),
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -76,6 +95,14 @@ pub enum CanSkip {
     No(Option<TaskHash>),
 }
 
+/// The command of a task after it has been rendered as a template.
+struct RenderedCommand {
+    command: String,
+    /// True if the template refers to the additional arguments, in which case
+    /// they are not appended to the command.
+    uses_args: bool,
+}
+
 /// A task that contains enough information to be able to execute it. The
 /// lifetime [`'p`] refers to the lifetime of the project that contains the
 /// tasks.
@@ -116,19 +143,139 @@ impl<'p> ExecutableTask<'p> {
         self.project
     }
 
+    /// Renders the command of the task as a template. The template has
+    /// access to information about the project and the environment the task
+    /// runs in through the `pixi` variable.
+    ///
+    /// Only commands of tasks that opt in with `template = true` are
+    /// rendered, other commands are returned verbatim.
+    fn render_command(&self, command: &str) -> Result<RenderedCommand, FailedToRenderTemplate> {
+        if self.task.is_custom() || !self.task.template() {
+            return Ok(RenderedCommand {
+                command: command.to_string(),
+                uses_args: false,
+            });
+        }
+
+        let mut env = minijinja::Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        let template = env
+            .template_from_str(command)
+            .map_err(|err| self.template_error(command, err))?;
+        let uses_args = template.undeclared_variables(true).contains("pixi.args");
+
+        let platform = self.run_environment.best_platform();
+        let rendered = template
+            .render(context! {
+                pixi => context! {
+                    platform => platform.as_str(),
+                    is_win => platform.is_windows(),
+                    is_unix => platform.is_unix(),
+                    is_linux => platform.is_linux(),
+                    is_osx => platform.is_osx(),
+                    manifest_path => self.project.manifest_path(),
+                    environment => context! {
+                        name => self.run_environment.name().as_str(),
+                    },
+                    project => context! {
+                        name => self.project.name(),
+                        version => self.project.version().as_ref().map(|v| v.to_string()),
+                        root => self.project.root(),
+                    },
+                    task => context! {
+                        name => self.name(),
+                    },
+                    args => &self.additional_args,
+                },
+            })
+            .map_err(|err| self.template_error(command, err))?;
+
+        Ok(RenderedCommand {
+            command: rendered,
+            uses_args,
+        })
+    }
+
+    /// Returns the source location of the definition of this task in the
+    /// manifest.
+    fn source_loc(&self) -> Option<std::ops::Range<usize>> {
+        let name = self.name.as_ref()?;
+        let Cow::Borrowed(task) = &self.task else {
+            return None;
+        };
+        self.run_environment
+            .features()
+            .flat_map(|feature| feature.targets.targets())
+            .find(|target| {
+                target
+                    .tasks
+                    .get(name)
+                    .is_some_and(|defined| std::ptr::eq(defined, *task))
+            })
+            .and_then(|target| target.task_source_loc(name))
+    }
+
+    /// Converts a template error into a diagnostic that points at the
+    /// definition of the task in the manifest, or at the offending part of
+    /// the command if the task is not defined in the manifest.
+    fn template_error(&self, command: &str, err: minijinja::Error) -> FailedToRenderTemplate {
+        let task_name = self.name().unwrap_or("unnamed").to_string();
+        let reason = err
+            .detail()
+            .map(str::to_string)
+            .unwrap_or_else(|| err.kind().to_string());
+
+        let manifest = &self.project.manifest;
+        let Some(task_loc) = self.source_loc() else {
+            return FailedToRenderTemplate {
+                src: NamedSource::new(format!("task '{task_name}'"), command.to_string()),
+                span: err.range().map(SourceSpan::from),
+                reason,
+                task_name,
+            };
+        };
+
+        // Point at the offending part of the command if it appears verbatim
+        // in the definition of the task, otherwise at the whole definition.
+        let span = manifest.contents[task_loc.clone()]
+            .find(command)
+            .zip(err.range())
+            .map(|(offset, range)| {
+                let start = task_loc.start + offset + range.start;
+                start..start + range.len()
+            })
+            .unwrap_or(task_loc);
+
+        FailedToRenderTemplate {
+            src: NamedSource::new(manifest.file_name(), manifest.contents.clone()),
+            span: Some(SourceSpan::from(span)),
+            reason,
+            task_name,
+        }
+    }
+
     /// Returns the task as script
-    fn as_script(&self) -> Option<String> {
+    fn as_script(&self) -> Result<Option<String>, FailedToRenderTemplate> {
         // Convert the task into an executable string
-        let task = self.task.as_single_command()?;
+        let Some(task) = self.task.as_single_command() else {
+            return Ok(None);
+        };
+        let rendered = self.render_command(&task)?;
+        let task = rendered.command;
 
         // Get the export specific environment variables
         let export = get_export_specific_task_env(self.task.as_ref());
 
-        // Append the command line arguments verbatim
-        let cli_args = self
-            .additional_args
-            .iter()
-            .format_with(" ", |arg, f| f(&format_args!("'{}'", arg)));
+        // Append the command line arguments verbatim, unless the template
+        // already used them
+        let cli_args = if rendered.uses_args {
+            String::new()
+        } else {
+            self.additional_args
+                .iter()
+                .format_with(" ", |arg, f| f(&format_args!("'{}'", arg)))
+                .to_string()
+        };
 
         // Skip the export if it's empty, to avoid newlines
         let full_script = if export.is_empty() {
@@ -137,23 +284,24 @@ impl<'p> ExecutableTask<'p> {
             format!("{export}\n{task} {cli_args}")
         };
 
-        Some(full_script)
+        Ok(Some(full_script))
     }
 
     /// Returns a [`SequentialList`] which can be executed by deno task shell.
     /// Returns `None` if the command is not executable like in the case of
     /// an alias.
-    pub(crate) fn as_deno_script(
-        &self,
-    ) -> Result<Option<SequentialList>, FailedToParseShellScript> {
-        if let Some(full_script) = self.as_script() {
+    pub(crate) fn as_deno_script(&self) -> Result<Option<SequentialList>, TaskExecutionError> {
+        if let Some(full_script) = self.as_script()? {
             tracing::debug!("Parsing shell script: {}", full_script);
 
             // Parse the shell command
             deno_task_shell::parser::parse(full_script.trim())
-                .map_err(|e| FailedToParseShellScript {
-                    script: full_script,
-                    error: e.to_string(),
+                .map_err(|e| {
+                    FailedToParseShellScript {
+                        script: full_script,
+                        error: e.to_string(),
+                    }
+                    .into()
                 })
                 .map(Some)
         } else {
@@ -195,6 +343,26 @@ impl<'p> ExecutableTask<'p> {
         Some(cmd)
     }
 
+    /// Returns the full command like [`Self::full_command`] but with the
+    /// command rendered as a template.
+    ///
+    /// If the command fails to render the unrendered command is returned, the
+    /// error is reported when the task is executed.
+    pub(crate) fn full_rendered_command(&self) -> Option<String> {
+        let command = self.task.as_single_command()?;
+        let Ok(rendered) = self.render_command(&command) else {
+            return self.full_command();
+        };
+
+        let mut cmd = rendered.command;
+        if !rendered.uses_args && !self.additional_args.is_empty() {
+            cmd.push(' ');
+            cmd.push_str(&self.additional_args.join(" "));
+        }
+
+        Some(cmd)
+    }
+
     /// Returns an object that implements [`Display`] which outputs the command
     /// of the wrapped task.
     pub(crate) fn display_command(&self) -> impl Display + '_ {
@@ -247,14 +415,21 @@ impl<'p> ExecutableTask<'p> {
     /// `CanSkip::No` and includes the hash of the task that caused the task
     /// to not be skipped - we can use this later to update the cache file
     /// quickly.
-    pub(crate) async fn can_skip(&self, lock_file: &LockFile) -> Result<CanSkip, std::io::Error> {
+    ///
+    /// `clean_env` is whether the task runs in a clean environment, either
+    /// because the task or the command line requests it.
+    pub(crate) async fn can_skip(
+        &self,
+        lock_file: &LockFile,
+        clean_env: bool,
+    ) -> Result<CanSkip, std::io::Error> {
         tracing::info!("Checking if task can be skipped");
         let cache_name = self.cache_name();
         let cache_file = self.project().task_cache_folder().join(cache_name);
         if cache_file.exists() {
             let cache = tokio::fs::read_to_string(&cache_file).await?;
             let cache: TaskCache = serde_json::from_str(&cache)?;
-            let hash = TaskHash::from_task(self, lock_file).await;
+            let hash = TaskHash::from_task(self, lock_file, clean_env).await;
             if let Ok(Some(hash)) = hash {
                 if hash.computation_hash() != cache.hash {
                     return Ok(CanSkip::No(Some(hash)));
@@ -273,13 +448,16 @@ impl<'p> ExecutableTask<'p> {
         &self,
         lock_file: &LockFileDerivedData<'_>,
         previous_hash: Option<TaskHash>,
+        clean_env: bool,
     ) -> Result<(), CacheUpdateError> {
         let task_cache_folder = self.project().task_cache_folder();
         let cache_file = task_cache_folder.join(self.cache_name());
         let new_hash = if let Some(mut previous_hash) = previous_hash {
             previous_hash.update_output(self).await?;
             previous_hash
-        } else if let Some(hash) = TaskHash::from_task(self, &lock_file.lock_file).await? {
+        } else if let Some(hash) =
+            TaskHash::from_task(self, &lock_file.lock_file, clean_env).await?
+        {
             hash
         } else {
             return Ok(());
@@ -303,15 +481,31 @@ struct ExecutableTaskConsoleDisplay<'p, 't> {
 
 impl<'p, 't> Display for ExecutableTaskConsoleDisplay<'p, 't> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let command = self.task.task.as_single_command();
+        // Show the rendered command if possible, rendering errors are reported
+        // when the task is executed.
+        let command = self.task.task.as_single_command().map(|command| {
+            self.task
+                .render_command(&command)
+                .unwrap_or_else(|_| RenderedCommand {
+                    command: command.to_string(),
+                    uses_args: false,
+                })
+        });
         write!(
             f,
             "{}",
             consts::TASK_STYLE
-                .apply_to(command.as_deref().unwrap_or("<alias>"))
+                .apply_to(
+                    command
+                        .as_ref()
+                        .map(|command| command.command.as_str())
+                        .unwrap_or("<alias>")
+                )
                 .bold()
         )?;
-        if !self.task.additional_args.is_empty() {
+        if !self.task.additional_args.is_empty()
+            && !command.as_ref().is_some_and(|command| command.uses_args)
+        {
             write!(
                 f,
                 " {}",
@@ -457,10 +651,83 @@ mod tests {
             additional_args: vec![],
         };
 
-        let script = executable_task.as_script().unwrap();
+        let script = executable_task.as_script().unwrap().unwrap();
         assert_eq!(script, "export \"FOO=bar\";\n\ntest ");
     }
 
+    #[test]
+    fn test_as_script_template() {
+        let file_contents = r#"
+            [tasks]
+            test = { cmd = "echo {{ pixi.environment.name }} {{ pixi.project.version }} {{ pixi.args | join(',') }}", template = true }
+            platform = { cmd = "{% if pixi.is_win %}dir{% else %}ls{% endif %}", template = true }
+            invalid = { cmd = "echo {{ pixi.unknown }}", template = true }
+            literal = "echo ${{ github.ref }} {% raw %}"
+            "#;
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            format!("{PROJECT_BOILERPLATE}\n{file_contents}").as_str(),
+        )
+        .unwrap();
+
+        let project = Project::from_manifest(manifest);
+        let executable_task = |name: &str, additional_args: Vec<String>| ExecutableTask {
+            project: &project,
+            name: Some(name.into()),
+            task: Cow::Borrowed(
+                project
+                    .default_environment()
+                    .task(&TaskName::from(name), None)
+                    .unwrap(),
+            ),
+            run_environment: project.default_environment(),
+            additional_args,
+        };
+
+        // The arguments are used by the template, so they are not appended
+        let script = executable_task("test", vec!["a".to_string(), "b".to_string()])
+            .as_script()
+            .unwrap()
+            .unwrap();
+        assert_eq!(script, "echo default 0.1.0 a,b ");
+
+        let script = executable_task("platform", vec![])
+            .as_script()
+            .unwrap()
+            .unwrap();
+        let expected = if project.default_environment().best_platform().is_windows() {
+            "dir "
+        } else {
+            "ls "
+        };
+        assert_eq!(script, expected);
+
+        // The error points at the definition of the task in the manifest
+        let err = executable_task("invalid", vec![]).as_script().unwrap_err();
+        assert_eq!(err.task_name, "invalid");
+        assert_eq!(err.src.name(), "pixi.toml");
+        let span = err.span.unwrap();
+        let labeled = &project.manifest.contents[span.offset()..span.offset() + span.len()];
+        assert!(labeled.contains("unknown"), "{labeled}");
+
+        // Commands of tasks that don't opt in are never rendered
+        let script = executable_task("literal", vec![])
+            .as_script()
+            .unwrap()
+            .unwrap();
+        assert_eq!(script, "echo ${{ github.ref }} {% raw %} ");
+
+        // The hash and the report use the rendered command
+        assert_eq!(
+            executable_task("test", vec!["a".to_string()]).full_rendered_command(),
+            Some("echo default 0.1.0 a".to_string())
+        );
+        assert_eq!(
+            executable_task("literal", vec!["a".to_string()]).full_rendered_command(),
+            Some("echo ${{ github.ref }} {% raw %} a".to_string())
+        );
+    }
+
     #[tokio::test]
     async fn test_get_task_env() {
         let file_contents = r#"
@@ -1,20 +1,22 @@
 mod error;
 mod executable_task;
 mod file_hashes;
+mod report;
 mod task_environment;
 mod task_graph;
 mod task_hash;
 
 pub use file_hashes::{FileHashes, FileHashesError};
 pub use pixi_manifest::{Task, TaskName};
+pub use report::{ReportFormat, RunReport, TaskReport, TaskStatus};
 pub use task_hash::{ComputationHash, EnvironmentHash, InputHashes, TaskHash};
 
 pub use executable_task::{
-    get_task_env, CanSkip, ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory,
-    RunOutput, TaskExecutionError,
+    get_task_env, CanSkip, ExecutableTask, FailedToParseShellScript, FailedToRenderTemplate,
+    InvalidWorkingDirectory, RunOutput, TaskExecutionError,
 };
 pub use task_environment::{
     AmbiguousTask, FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment,
     TaskDisambiguation,
 };
-pub use task_graph::{TaskGraph, TaskGraphError, TaskId, TaskNode};
+pub use task_graph::{SkipReason, TaskGraph, TaskGraphError, TaskId, TaskNode};
@@ -0,0 +1,273 @@
+use std::{fmt::Write, path::PathBuf, time::Duration};
+
+use itertools::Itertools;
+use pixi_manifest::EnvironmentName;
+use serde::{Serialize, Serializer};
+
+use super::ExecutableTask;
+
+/// The format of the report that is written by `pixi run --report`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// A JSON document with an entry per task.
+    Json,
+    /// A JUnit XML document with a test case per task.
+    Junit,
+}
+
+impl ReportFormat {
+    /// The file name that is used for the report if no path is specified.
+    pub fn default_file_name(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "pixi-run-report.json",
+            ReportFormat::Junit => "pixi-run-report.xml",
+        }
+    }
+}
+
+/// The outcome of a single task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskStatus {
+    /// The task was executed and exited with a zero exit code.
+    Success,
+    /// The task was executed and exited with a non-zero exit code.
+    Failed,
+    /// The task was not executed because its inputs and outputs didn't change.
+    CacheHit,
+    /// The task was not executed because its `run-if` conditions were not
+    /// met.
+    Skipped,
+}
+
+/// The report of a single task.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TaskReport {
+    pub name: Option<String>,
+    pub command: Option<String>,
+    pub environment: EnvironmentName,
+    pub status: TaskStatus,
+    pub exit_code: Option<i32>,
+    #[serde(rename = "duration-secs", serialize_with = "serialize_secs")]
+    pub duration: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<PathBuf>,
+}
+
+impl TaskReport {
+    /// Creates a report for the given task that did not run (yet).
+    pub fn new(task: &ExecutableTask<'_>, status: TaskStatus) -> Self {
+        Self {
+            name: task.name().map(ToOwned::to_owned),
+            command: task.full_rendered_command(),
+            environment: task.run_environment.name().clone(),
+            status,
+            exit_code: None,
+            duration: Duration::ZERO,
+            skip_reason: None,
+            log_file: None,
+        }
+    }
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// A summary of all the tasks that were part of a `pixi run`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunReport {
+    pub tasks: Vec<TaskReport>,
+}
+
+impl RunReport {
+    /// Renders the report in the given format.
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Json => {
+                serde_json::to_string_pretty(self).expect("the report is always valid json")
+            }
+            ReportFormat::Junit => self.to_junit(),
+        }
+    }
+
+    /// Renders the report as a JUnit XML document, every task is a test case
+    /// and the environment is used as the class name.
+    fn to_junit(&self) -> String {
+        let count = |status| self.tasks.iter().filter(|t| t.status == status).count();
+        let failures = count(TaskStatus::Failed);
+        // A cache hit means the outputs of the task are up to date, so it is
+        // reported as passed instead of skipped.
+        let skipped = count(TaskStatus::Skipped);
+        let time: f64 = self.tasks.iter().map(|t| t.duration.as_secs_f64()).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let attributes = format!(
+            "name=\"pixi run\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" time=\"{time:.3}\"",
+            self.tasks.len()
+        );
+        writeln!(xml, "<testsuites {attributes}>").unwrap();
+        writeln!(xml, "  <testsuite {attributes}>").unwrap();
+        for task in &self.tasks {
+            write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape_xml(task.name.as_deref().unwrap_or("unnamed")),
+                escape_xml(task.environment.as_str()),
+                task.duration.as_secs_f64()
+            )
+            .unwrap();
+
+            let mut children = Vec::new();
+            match task.status {
+                TaskStatus::Success | TaskStatus::CacheHit => {}
+                TaskStatus::Failed => children.push(format!(
+                    "<failure message=\"exited with code {}\"/>",
+                    task.exit_code.unwrap_or_default()
+                )),
+                TaskStatus::Skipped => children.push(format!(
+                    "<skipped message=\"{}\"/>",
+                    escape_xml(task.skip_reason.as_deref().unwrap_or_default())
+                )),
+            }
+            let system_out = (task.status == TaskStatus::CacheHit)
+                .then(|| {
+                    String::from("cache hit: the inputs and outputs of the task didn't change")
+                })
+                .into_iter()
+                .chain(
+                    task.command
+                        .iter()
+                        .map(|command| format!("command: {command}")),
+                )
+                .chain(
+                    task.log_file
+                        .iter()
+                        .map(|log_file| format!("log: {}", log_file.display())),
+                )
+                .join("\n");
+            if !system_out.is_empty() {
+                children.push(format!(
+                    "<system-out>{}</system-out>",
+                    escape_xml(&system_out)
+                ));
+            }
+
+            if children.is_empty() {
+                xml.push_str("/>\n");
+            } else {
+                xml.push_str(">\n");
+                for child in children {
+                    writeln!(xml, "      {child}").unwrap();
+                }
+                xml.push_str("    </testcase>\n");
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+/// Escapes the characters that have a special meaning in XML.
+fn escape_xml(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> RunReport {
+        RunReport {
+            tasks: vec![
+                TaskReport {
+                    name: Some("build".to_string()),
+                    command: Some("cargo build".to_string()),
+                    environment: EnvironmentName::Default,
+                    status: TaskStatus::Success,
+                    exit_code: Some(0),
+                    duration: Duration::from_millis(1500),
+                    skip_reason: None,
+                    log_file: Some(PathBuf::from("logs/default-build.log")),
+                },
+                TaskReport {
+                    name: Some("test".to_string()),
+                    command: Some("cargo test && echo \"<done>\"".to_string()),
+                    environment: EnvironmentName::Named("test".to_string()),
+                    status: TaskStatus::Failed,
+                    exit_code: Some(101),
+                    duration: Duration::from_secs(2),
+                    skip_reason: None,
+                    log_file: None,
+                },
+                TaskReport {
+                    name: Some("docs".to_string()),
+                    command: Some("mkdocs build".to_string()),
+                    environment: EnvironmentName::Default,
+                    status: TaskStatus::Skipped,
+                    exit_code: None,
+                    duration: Duration::ZERO,
+                    skip_reason: Some("the environment variable 'CI' is not set".to_string()),
+                    log_file: None,
+                },
+                TaskReport {
+                    name: Some("lint".to_string()),
+                    command: Some("ruff check".to_string()),
+                    environment: EnvironmentName::Default,
+                    status: TaskStatus::CacheHit,
+                    exit_code: None,
+                    duration: Duration::ZERO,
+                    skip_reason: None,
+                    log_file: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_json_report() {
+        let json: serde_json::Value =
+            serde_json::from_str(&report().render(ReportFormat::Json)).unwrap();
+        let tasks = json["tasks"].as_array().unwrap();
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(tasks[0]["status"], "success");
+        assert_eq!(tasks[0]["duration-secs"], 1.5);
+        assert_eq!(tasks[1]["environment"], "test");
+        assert_eq!(tasks[1]["exit-code"], 101);
+        assert_eq!(tasks[2]["status"], "skipped");
+        assert!(tasks[2].get("log-file").is_none());
+        assert_eq!(tasks[3]["status"], "cache-hit");
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let xml = report().render(ReportFormat::Junit);
+        assert!(xml.contains("tests=\"4\" failures=\"1\" skipped=\"1\" time=\"3.500\""));
+        assert!(xml.contains("<testcase name=\"build\" classname=\"default\" time=\"1.500\">"));
+        assert!(xml.contains("<failure message=\"exited with code 101\"/>"));
+        assert!(xml.contains("cargo test &amp;&amp; echo &quot;&lt;done&gt;&quot;"));
+        assert!(xml
+            .contains("<skipped message=\"the environment variable &apos;CI&apos; is not set\"/>"));
+
+        // Cache hits are reported as passed, with a note in the output
+        let lint = xml.split("name=\"lint\"").nth(1).unwrap();
+        assert!(!lint.contains("<skipped"));
+        assert!(lint.contains(
+            "<system-out>cache hit: the inputs and outputs of the task didn&apos;t change"
+        ));
+    }
+}
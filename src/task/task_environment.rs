@@ -136,9 +136,20 @@ impl<'p, D: TaskDisambiguation<'p>> SearchEnvironments<'p, D> {
         &self,
         name: TaskName,
         source: FindTaskSource<'p>,
+    ) -> Result<TaskAndEnvironment<'p>, FindTaskError> {
+        self.find_task_in(name, source, self.explicit_environment.as_ref())
+    }
+
+    /// Finds the task with the given name like [`Self::find_task`] but uses
+    /// the given environment instead of the explicit environment.
+    pub(crate) fn find_task_in(
+        &self,
+        name: TaskName,
+        source: FindTaskSource<'p>,
+        explicit_environment: Option<&Environment<'p>>,
     ) -> Result<TaskAndEnvironment<'p>, FindTaskError> {
         // If no explicit environment was specified
-        if self.explicit_environment.is_none() {
+        if explicit_environment.is_none() {
             let default_env = self.project.default_environment();
             // If the default environment has the task
             if let Ok(default_env_task) = default_env.task(&name, self.platform) {
@@ -173,7 +184,7 @@ impl<'p, D: TaskDisambiguation<'p>> SearchEnvironments<'p, D> {
 
         // If an explicit environment was specified, only look for tasks in that
         // environment and the default environment.
-        let environments = if let Some(explicit_environment) = &self.explicit_environment {
+        let environments = if let Some(explicit_environment) = explicit_environment {
             vec![explicit_environment.clone()]
         } else {
             self.project.environments()
@@ -4,14 +4,17 @@ use std::{
     env, fmt,
     fmt::Display,
     ops::Index,
+    path::{Path, PathBuf},
 };
 
+use fancy_display::FancyDisplay;
 use itertools::Itertools;
 use miette::Diagnostic;
 use pixi_manifest::{
-    task::{CmdArgs, Custom},
-    Task, TaskName,
+    task::{CmdArgs, Custom, RunIf},
+    EnvironmentName, Task, TaskName,
 };
+use rattler_conda_types::Platform;
 use thiserror::Error;
 
 use crate::{
@@ -23,7 +26,7 @@ use crate::{
     },
     task::{
         error::{AmbiguousTaskError, MissingTaskError},
-        task_environment::{FindTaskError, FindTaskSource, SearchEnvironments},
+        task_environment::{FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment},
         TaskDisambiguation,
     },
     Project,
@@ -53,6 +56,9 @@ pub struct TaskNode<'p> {
 
     /// The id's of the task that this task depends on.
     pub dependencies: Vec<TaskId>,
+
+    /// The reason why this task is skipped or `None` if the task should run.
+    pub skip_reason: Option<SkipReason>,
 }
 impl fmt::Display for TaskNode<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -72,6 +78,58 @@ impl fmt::Display for TaskNode<'_> {
     }
 }
 
+/// Describes the `run-if` condition of a task that was not met.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The task does not run on this platform.
+    Platform(Platform),
+
+    /// An environment variable is not set.
+    EnvVarNotSet(String),
+
+    /// A file does not exist.
+    FileMissing(PathBuf),
+}
+
+impl SkipReason {
+    /// Returns the first condition of `run_if` that is not met or `None` if
+    /// the task should run.
+    fn from_run_if(run_if: &RunIf, platform: Platform, root: &Path) -> Option<Self> {
+        if !run_if.platform.is_empty() && !run_if.platform.contains(&platform) {
+            return Some(Self::Platform(platform));
+        }
+        if let Some(env_var) = run_if
+            .env_var
+            .iter()
+            .find(|env_var| env::var_os(env_var).is_none())
+        {
+            return Some(Self::EnvVarNotSet(env_var.clone()));
+        }
+        if let Some(path) = run_if
+            .file_exists
+            .iter()
+            .find(|path| !root.join(path).exists())
+        {
+            return Some(Self::FileMissing(path.clone()));
+        }
+        None
+    }
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Platform(platform) => write!(f, "it does not run on {platform}"),
+            SkipReason::EnvVarNotSet(name) => {
+                write!(f, "the environment variable '{name}' is not set")
+            }
+            SkipReason::FileMissing(path) => {
+                write!(f, "the file '{}' does not exist", path.display())
+            }
+        }
+    }
+}
+
 impl<'p> TaskNode<'p> {
     /// Returns the full command that should be executed for this task. This
     /// includes any additional arguments that should be passed to the
@@ -159,22 +217,48 @@ impl<'p> TaskGraph<'p> {
                     return Err(TaskGraphError::AmbiguousTask(err))
                 }
                 Ok((task_env, task)) => {
-                    // If an explicit environment was specified and the task is from the default
-                    // environment use the specified environment instead.
-                    let run_env = match search_envs.explicit_environment.clone() {
-                        Some(explicit_env) if task_env.is_default() => explicit_env,
-                        _ => task_env,
+                    let name = TaskName::from(args.remove(0));
+                    let environments = if task.environments().is_empty() {
+                        // If an explicit environment was specified and the task is from the
+                        // default environment use the specified environment instead.
+                        let run_env = match search_envs.explicit_environment.clone() {
+                            Some(explicit_env) if task_env.is_default() => explicit_env,
+                            _ => task_env,
+                        };
+                        vec![(run_env, task)]
+                    } else {
+                        // The task defines the environments it runs in, an explicit environment
+                        // can only select one of those.
+                        let env_names = match &search_envs.explicit_environment {
+                            Some(explicit_env) => {
+                                if !task.environments().contains(explicit_env.name()) {
+                                    return Err(TaskGraphError::EnvironmentNotDeclared {
+                                        task_name: name,
+                                        environment: explicit_env.name().clone(),
+                                        declared: task.environments().to_vec(),
+                                    });
+                                }
+                                std::slice::from_ref(explicit_env.name())
+                            }
+                            None => task.environments(),
+                        };
+                        Self::task_in_environments(search_envs, &name, env_names)?
                     };
-                    return Self::from_root(
+
+                    return Self::from_roots(
                         project,
                         search_envs,
-                        TaskNode {
-                            name: Some(args.remove(0).into()),
-                            task: Cow::Borrowed(task),
-                            run_environment: run_env,
-                            additional_args: args,
-                            dependencies: vec![],
-                        },
+                        environments
+                            .into_iter()
+                            .map(|(run_environment, task)| TaskNode {
+                                name: Some(name.clone()),
+                                task: Cow::Borrowed(task),
+                                run_environment,
+                                additional_args: args.clone(),
+                                dependencies: vec![],
+                                skip_reason: None,
+                            })
+                            .collect(),
                     );
                 }
             }
@@ -199,10 +283,10 @@ impl<'p> TaskGraph<'p> {
             (CmdArgs::Multiple(args), vec![])
         };
 
-        Self::from_root(
+        Self::from_roots(
             project,
             search_envs,
-            TaskNode {
+            vec![TaskNode {
                 name: None,
                 task: Cow::Owned(
                     Custom {
@@ -214,51 +298,129 @@ impl<'p> TaskGraph<'p> {
                 run_environment,
                 additional_args,
                 dependencies: vec![],
-            },
+                skip_reason: None,
+            }],
         )
     }
 
-    /// Constructs a new instance of a [`TaskGraph`] from a root task.
-    fn from_root<D: TaskDisambiguation<'p>>(
+    /// Returns the task with the given name as defined in each of the given
+    /// environments.
+    fn task_in_environments<D: TaskDisambiguation<'p>>(
+        search_environments: &SearchEnvironments<'p, D>,
+        name: &TaskName,
+        env_names: &[EnvironmentName],
+    ) -> Result<Vec<TaskAndEnvironment<'p>>, TaskGraphError> {
+        let mut result = Vec::with_capacity(env_names.len());
+        for env_name in env_names {
+            let missing = || TaskGraphError::MissingTaskInEnvironment {
+                task_name: name.clone(),
+                environment: env_name.clone(),
+            };
+            let environment = search_environments
+                .project
+                .environment(env_name)
+                .ok_or_else(missing)?;
+            if !search_environments.ignore_system_requirements {
+                verify_current_platform_has_required_virtual_packages(&environment)?;
+            }
+            let task = environment
+                .task(name, search_environments.platform)
+                .map_err(|_| missing())?;
+            result.push((environment, task));
+        }
+        Ok(result)
+    }
+
+    /// Constructs a new instance of a [`TaskGraph`] from one or more root
+    /// tasks.
+    ///
+    /// Tasks are identified by their name and the environment they run in,
+    /// so a task that defines multiple environments results in one node per
+    /// environment. The `run-if` conditions of the tasks are evaluated here,
+    /// the dependencies of a skipped task are not added to the graph.
+    fn from_roots<D: TaskDisambiguation<'p>>(
         project: &'p Project,
         search_environments: &SearchEnvironments<'p, D>,
-        root: TaskNode<'p>,
+        roots: Vec<TaskNode<'p>>,
     ) -> Result<Self, TaskGraphError> {
-        let mut task_name_to_node: HashMap<TaskName, TaskId> =
-            HashMap::from_iter(root.name.clone().into_iter().map(|name| (name, TaskId(0))));
-        let mut nodes = vec![root];
+        let mut task_to_node: HashMap<(TaskName, EnvironmentName), TaskId> =
+            HashMap::from_iter(roots.iter().enumerate().filter_map(|(idx, node)| {
+                let name = node.name.clone()?;
+                Some(((name, node.run_environment.name().clone()), TaskId(idx)))
+            }));
+        let mut resolved_dependencies: HashMap<(TaskName, Option<EnvironmentName>), Vec<TaskId>> =
+            HashMap::new();
+        let mut nodes = roots;
 
         // Iterate over all the nodes in the graph and add them to the graph.
         let mut next_node_to_visit = 0;
         while next_node_to_visit < nodes.len() {
+            // Skip the task, and with that its dependencies, if its conditions are not met.
+            let node = &mut nodes[next_node_to_visit];
+            if let Some(run_if) = node.task.run_if() {
+                let platform = search_environments
+                    .platform
+                    .unwrap_or_else(|| node.run_environment.best_platform());
+                node.skip_reason = SkipReason::from_run_if(run_if, platform, project.root());
+                if node.skip_reason.is_some() {
+                    next_node_to_visit += 1;
+                    continue;
+                }
+            }
+
             let dependency_names =
                 Vec::from_iter(nodes[next_node_to_visit].task.depends_on().iter().cloned());
 
             // Iterate over all the dependencies of the node and add them to the graph.
             let mut node_dependencies = Vec::with_capacity(dependency_names.len());
             for dependency in dependency_names {
-                // Check if we visited this node before already.
-                if let Some(&task_id) = task_name_to_node.get(&dependency) {
-                    node_dependencies.push(task_id);
+                // The dependencies of a task that defines its environments are looked up in
+                // the environment the task runs in.
+                let node = &nodes[next_node_to_visit];
+                let search_environment = if node.task.environments().is_empty() {
+                    search_environments.explicit_environment.clone()
+                } else {
+                    Some(node.run_environment.clone())
+                };
+
+                // Check if we resolved this dependency before already.
+                let resolve_key = (
+                    dependency.clone(),
+                    search_environment.as_ref().map(|env| env.name().clone()),
+                );
+                if let Some(task_ids) = resolved_dependencies.get(&resolve_key) {
+                    node_dependencies.extend(task_ids.iter().copied());
                     continue;
                 }
 
                 // Find the task in the project
-                let node = &nodes[next_node_to_visit];
-                let (task_env, task_dependency) = match search_environments.find_task(
+                let source = FindTaskSource::DependsOn(
+                    node.name
+                        .clone()
+                        .expect("only named tasks can have dependencies"),
+                    match &node.task {
+                        Cow::Borrowed(task) => task,
+                        Cow::Owned(_) => {
+                            unreachable!("only named tasks can have dependencies")
+                        }
+                    },
+                );
+                let (task_env, task_dependency) = match search_environments.find_task_in(
                     dependency.clone(),
-                    FindTaskSource::DependsOn(
-                        node.name
-                            .clone()
-                            .expect("only named tasks can have dependencies"),
-                        match &node.task {
-                            Cow::Borrowed(task) => task,
-                            Cow::Owned(_) => {
-                                unreachable!("only named tasks can have dependencies")
-                            }
-                        },
-                    ),
+                    source.clone(),
+                    search_environment.as_ref(),
                 ) {
+                    // A task that defines its environments can also be found outside of the
+                    // environment that was searched.
+                    Err(FindTaskError::MissingTask(err)) if search_environment.is_some() => {
+                        match search_environments.find_task_in(dependency.clone(), source, None) {
+                            Ok(result) if !result.1.environments().is_empty() => result,
+                            Err(FindTaskError::AmbiguousTask(err)) => {
+                                return Err(TaskGraphError::AmbiguousTask(err))
+                            }
+                            _ => return Err(TaskGraphError::MissingTask(err)),
+                        }
+                    }
                     Err(FindTaskError::MissingTask(err)) => {
                         return Err(TaskGraphError::MissingTask(err))
                     }
@@ -268,21 +430,43 @@ impl<'p> TaskGraph<'p> {
                     Ok(result) => result,
                 };
 
-                // Add the node to the graph
-                let task_id = TaskId(nodes.len());
-                nodes.push(TaskNode {
-                    name: Some(dependency.clone()),
-                    task: Cow::Borrowed(task_dependency),
-                    run_environment: task_env,
-                    additional_args: Vec::new(),
-                    dependencies: Vec::new(),
-                });
+                let environments = if task_dependency.environments().is_empty() {
+                    vec![(task_env, task_dependency)]
+                } else {
+                    Self::task_in_environments(
+                        search_environments,
+                        &dependency,
+                        task_dependency.environments(),
+                    )?
+                };
 
-                // Store the task id in the map to be able to look up the name later
-                task_name_to_node.insert(dependency.clone(), task_id);
+                // Add the nodes to the graph, unless the task already runs in that
+                // environment.
+                let mut task_ids = Vec::with_capacity(environments.len());
+                for (run_environment, task) in environments {
+                    let key = (dependency.clone(), run_environment.name().clone());
+                    let task_id = match task_to_node.get(&key) {
+                        Some(&task_id) => task_id,
+                        None => {
+                            let task_id = TaskId(nodes.len());
+                            nodes.push(TaskNode {
+                                name: Some(dependency.clone()),
+                                task: Cow::Borrowed(task),
+                                run_environment,
+                                additional_args: Vec::new(),
+                                dependencies: Vec::new(),
+                                skip_reason: None,
+                            });
+                            task_to_node.insert(key, task_id);
+                            task_id
+                        }
+                    };
+                    task_ids.push(task_id);
+                }
 
                 // Add the dependency to the node
-                node_dependencies.push(task_id);
+                node_dependencies.extend(task_ids.iter().copied());
+                resolved_dependencies.insert(resolve_key, task_ids);
             }
 
             nodes[next_node_to_visit].dependencies = node_dependencies;
@@ -341,6 +525,28 @@ pub enum TaskGraphError {
 
     #[error("could not split task, assuming non valid task")]
     InvalidTask,
+
+    #[error(
+        "the task '{}' is not available in the environment '{}'",
+        .task_name.fancy_display(),
+        .environment.fancy_display()
+    )]
+    MissingTaskInEnvironment {
+        task_name: TaskName,
+        environment: EnvironmentName,
+    },
+
+    #[error(
+        "the task '{}' can not run in the environment '{}', it runs in: {}",
+        .task_name.fancy_display(),
+        .environment.fancy_display(),
+        .declared.iter().map(|env| env.fancy_display()).format(", ")
+    )]
+    EnvironmentNotDeclared {
+        task_name: TaskName,
+        environment: EnvironmentName,
+        declared: Vec<EnvironmentName>,
+    },
 }
 
 #[cfg(test)]
@@ -351,7 +557,10 @@ mod test {
     use rattler_conda_types::Platform;
 
     use crate::{
-        task::{task_environment::SearchEnvironments, task_graph::TaskGraph},
+        task::{
+            task_environment::SearchEnvironments,
+            task_graph::{SkipReason, TaskGraph, TaskGraphError},
+        },
         Project,
     };
 
@@ -609,4 +818,164 @@ mod test {
             None,
         );
     }
+
+    fn commands_and_environments_in_order(
+        project_str: &str,
+        run_args: &[&str],
+        environment_name: Option<EnvironmentName>,
+    ) -> Result<Vec<(String, String)>, TaskGraphError> {
+        let project = Project::from_str(Path::new("pixi.toml"), project_str).unwrap();
+
+        let environment = environment_name.map(|name| project.environment(&name).unwrap());
+        let search_envs = SearchEnvironments::from_opt_env(&project, environment, None)
+            .with_ignore_system_requirements(true);
+
+        let graph = TaskGraph::from_cmd_args(
+            &project,
+            &search_envs,
+            run_args.iter().map(|arg| arg.to_string()).collect(),
+        )?;
+
+        Ok(graph
+            .topological_order()
+            .into_iter()
+            .map(|task| &graph[task])
+            .filter_map(|task| {
+                Some((
+                    task.full_command()?,
+                    task.run_environment.name().to_string(),
+                ))
+            })
+            .collect())
+    }
+
+    const TASK_ENVIRONMENTS: &str = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+
+        [tasks]
+        compile = "echo compile"
+        build = { cmd = "echo build", depends-on = ["compile"], environment = "py311" }
+        test = { cmd = "echo test", environment = ["py311", "py312"] }
+        release = { cmd = "echo release", depends-on = ["build", "test", "docs"] }
+
+        [feature.docs.tasks]
+        docs = { cmd = "echo docs", environment = "docs" }
+
+        [feature.py311.dependencies]
+        python = "3.11.*"
+        [feature.py312.dependencies]
+        python = "3.12.*"
+
+        [environments]
+        py311 = ["py311"]
+        py312 = ["py312"]
+        docs = ["docs"]
+    "#;
+
+    #[test]
+    fn test_task_environments() {
+        assert_eq!(
+            commands_and_environments_in_order(TASK_ENVIRONMENTS, &["release"], None).unwrap(),
+            vec![
+                ("echo compile".to_string(), "py311".to_string()),
+                ("echo build".to_string(), "py311".to_string()),
+                ("echo test".to_string(), "py311".to_string()),
+                ("echo test".to_string(), "py312".to_string()),
+                ("echo docs".to_string(), "docs".to_string()),
+                ("echo release".to_string(), "default".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_environments_explicit() {
+        // An explicit environment selects one of the environments of the task
+        assert_eq!(
+            commands_and_environments_in_order(
+                TASK_ENVIRONMENTS,
+                &["test"],
+                Some(EnvironmentName::Named("py312".to_string()))
+            )
+            .unwrap(),
+            vec![("echo test".to_string(), "py312".to_string())]
+        );
+
+        // Dependencies still run in the environment they define
+        assert_eq!(
+            commands_and_environments_in_order(
+                TASK_ENVIRONMENTS,
+                &["release"],
+                Some(EnvironmentName::Named("docs".to_string()))
+            )
+            .unwrap()
+            .last()
+            .unwrap(),
+            &("echo release".to_string(), "docs".to_string())
+        );
+
+        assert!(matches!(
+            commands_and_environments_in_order(
+                TASK_ENVIRONMENTS,
+                &["build"],
+                Some(EnvironmentName::Named("py312".to_string()))
+            ),
+            Err(TaskGraphError::EnvironmentNotDeclared { .. })
+        ));
+    }
+
+    #[test]
+    fn test_run_if() {
+        let project = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+
+        [tasks]
+        build = "echo build"
+        test-linux = { cmd = "echo linux", depends-on = ["build"], run-if = { platform = "linux-64" } }
+        test-ci = { cmd = "echo ci", run-if = { env-var = "PIXI_TEST_RUN_IF_UNSET" } }
+        test-file = { cmd = "echo file", run-if = { file-exists = "Cargo.toml", platform = ["win-64", "osx-64"] } }
+        test-missing = { cmd = "echo missing", run-if = { file-exists = "missing.cfg" } }
+        all = { depends-on = ["test-linux", "test-ci", "test-file", "test-missing"] }
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, Some(Platform::Win64))
+            .with_ignore_system_requirements(true);
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["all".to_string()]).unwrap();
+
+        let skipped = graph
+            .topological_order()
+            .into_iter()
+            .map(|task| &graph[task])
+            .filter_map(|task| Some((task.full_command()?, task.skip_reason.clone())))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![
+                (
+                    "echo linux".to_string(),
+                    Some(SkipReason::Platform(Platform::Win64))
+                ),
+                (
+                    "echo ci".to_string(),
+                    Some(SkipReason::EnvVarNotSet(
+                        "PIXI_TEST_RUN_IF_UNSET".to_string()
+                    ))
+                ),
+                ("echo file".to_string(), None),
+                (
+                    "echo missing".to_string(),
+                    Some(SkipReason::FileMissing("missing.cfg".into()))
+                ),
+            ]
+        );
+    }
 }
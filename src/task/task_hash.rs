@@ -1,3 +1,4 @@
+use crate::activation::get_passthrough_environment_variables;
 use crate::project;
 use crate::task::{ExecutableTask, FileHashes, FileHashesError, InvalidWorkingDirectory};
 use miette::Diagnostic;
@@ -61,7 +62,8 @@ impl EnvironmentHash {
             script.hash(&mut hasher);
         }
 
-        // Hash the environment variables
+        // Hash the environment variables, this includes the variables read from
+        // the env-files so a change to one of the files changes the hash.
         let project_activation_env =
             run_environment.activation_env(Some(run_environment.best_platform()));
         let mut env_vars: Vec<_> = project_activation_env.iter().collect();
@@ -105,10 +107,12 @@ pub struct TaskHash {
 }
 
 impl TaskHash {
-    /// Constructs an instance from an executable task.
+    /// Constructs an instance from an executable task. `clean_env` is whether
+    /// the task runs in a clean environment.
     pub async fn from_task(
         task: &ExecutableTask<'_>,
         lock_file: &LockFile,
+        clean_env: bool,
     ) -> Result<Option<Self>, InputHashesError> {
         let input_hashes = InputHashes::from_task(task).await?;
         let output_hashes = OutputHashes::from_task(task, false).await?;
@@ -117,14 +121,32 @@ impl TaskHash {
             return Ok(None);
         }
 
+        // The variables that are passed through to a task in a clean environment
+        // are inputs of the task.
+        let passthrough_env = if clean_env {
+            let patterns = task
+                .project
+                .config()
+                .env_passthrough()
+                .iter()
+                .chain(task.task().env_passthrough())
+                .cloned()
+                .collect::<Vec<_>>();
+            get_passthrough_environment_variables(&patterns)
+                .into_iter()
+                .map(|(key, value)| (key, Some(value)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
         Ok(Some(Self {
-            command: task.full_command(),
+            command: task.full_rendered_command(),
             outputs: output_hashes,
             inputs: input_hashes,
-            // Skipping environment variables used for caching the task
             environment: EnvironmentHash::from_environment(
                 &task.run_environment,
-                &HashMap::new(),
+                &passthrough_env,
                 lock_file,
             ),
         }))
@@ -0,0 +1,472 @@
+//! Verifies that the files of the packages installed in a prefix still match
+//! the metadata that was recorded when they were installed.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use fs_err as fs;
+use miette::IntoDiagnostic;
+use pixi_consts::consts;
+use pypi_modifiers::pypi_tags::is_python_record;
+use rattler::install::PythonInfo;
+use rattler_conda_types::{prefix_record::PathType, Platform, PrefixRecord};
+use rattler_digest::{compute_file_digest, Sha256, Sha256Hash};
+use uv_distribution_types::InstalledDist;
+
+use crate::{install_wheel::read_record_file, prefix::Prefix};
+
+/// A package of which the installed files don't match its metadata.
+#[derive(Debug)]
+pub(crate) struct PackageDrift {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) kind: PackageKind,
+    pub(crate) files: Vec<FileDrift>,
+}
+
+/// Where a drifted package was installed from, with what is needed to remove
+/// it.
+#[derive(Debug)]
+pub(crate) enum PackageKind {
+    Conda {
+        /// The files of the package relative to the prefix.
+        files: Vec<PathBuf>,
+        /// The `conda-meta` file that records the installation.
+        meta_file: PathBuf,
+    },
+    Pypi {
+        /// The `.dist-info` directory of the package.
+        dist_info: PathBuf,
+    },
+}
+
+/// A file that doesn't match the metadata of its package.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct FileDrift {
+    pub(crate) path: PathBuf,
+    pub(crate) kind: FileDriftKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum FileDriftKind {
+    Missing,
+    Size { expected: u64, actual: u64 },
+    Hash,
+}
+
+impl Display for PackageKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageKind::Conda { .. } => write!(f, "conda"),
+            PackageKind::Pypi { .. } => write!(f, "pypi"),
+        }
+    }
+}
+
+impl Display for FileDrift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FileDriftKind::Missing => write!(f, "missing: {}", self.path.display()),
+            FileDriftKind::Size { expected, actual } => write!(
+                f,
+                "size changed from {expected} to {actual} bytes: {}",
+                self.path.display()
+            ),
+            FileDriftKind::Hash => write!(f, "content changed: {}", self.path.display()),
+        }
+    }
+}
+
+/// Checks every file of the conda packages and of the PyPI packages installed
+/// by pixi in the prefix for existence, size and sha256, and returns the
+/// packages that don't match their metadata.
+pub(crate) async fn verify_prefix(prefix: &Prefix) -> miette::Result<Vec<PackageDrift>> {
+    let installed_packages = prefix.find_installed_packages(None).await?;
+    let root = prefix.root().to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        // The PyPI packages are installed in the site-packages of the python
+        // interpreter of the prefix.
+        let python_info = installed_packages
+            .iter()
+            .find(|record| is_python_record(&record.repodata_record.package_record))
+            .and_then(|record| {
+                PythonInfo::from_python_record(
+                    &record.repodata_record.package_record,
+                    Platform::current(),
+                )
+                .ok()
+            });
+        let site_packages = python_info.map(|info| root.join(&info.site_packages_path));
+        let mut dist_infos = Vec::new();
+        if let Some(site_packages) = &site_packages {
+            for entry in fs::read_dir(site_packages).into_iter().flatten() {
+                let path = entry.into_diagnostic()?.path();
+                if path.extension().is_some_and(|ext| ext == "dist-info") {
+                    dist_infos.push(path);
+                }
+            }
+        }
+
+        // Files that are installed by more than one package were clobbered:
+        // their content belongs to only one of the packages, or the file was
+        // removed together with one of them. These are not reported as drift.
+        let mut owners = HashMap::<PathBuf, usize>::new();
+        for record in &installed_packages {
+            for entry in &record.paths_data.paths {
+                if entry.path_type != PathType::Directory {
+                    *owners.entry(root.join(&entry.relative_path)).or_default() += 1;
+                }
+            }
+        }
+        if let Some(site_packages) = &site_packages {
+            for dist_info in &dist_infos {
+                let Ok(mut file) = fs::File::open(dist_info.join("RECORD")) else {
+                    continue;
+                };
+                for record in read_record_file(&mut file).unwrap_or_default() {
+                    *owners
+                        .entry(normalize_path(&site_packages.join(&record.path)))
+                        .or_default() += 1;
+                }
+            }
+        }
+        let clobbered: HashSet<PathBuf> = owners
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(path, _)| path)
+            .collect();
+
+        let mut drift = Vec::new();
+        for record in &installed_packages {
+            drift.extend(verify_conda_package(&root, record, &clobbered)?);
+        }
+        if let Some(site_packages) = &site_packages {
+            for dist_info in &dist_infos {
+                drift.extend(verify_dist_info(site_packages, dist_info, &clobbered)?);
+            }
+        }
+
+        Ok(drift)
+    })
+    .await
+    .into_diagnostic()?
+}
+
+/// Removes the drifted packages from the prefix, so that the next update of
+/// the prefix installs them again.
+pub(crate) async fn remove_packages(
+    prefix: &Prefix,
+    packages: &[PackageDrift],
+) -> miette::Result<()> {
+    for package in packages {
+        match &package.kind {
+            PackageKind::Conda { files, meta_file } => {
+                for file in files {
+                    remove_file_if_exists(&prefix.root().join(file))?;
+                }
+                remove_file_if_exists(meta_file)?;
+            }
+            PackageKind::Pypi { dist_info } => {
+                let installed = InstalledDist::try_from_path(dist_info).into_diagnostic()?;
+                let uninstalled = match installed {
+                    Some(installed) => uv_installer::uninstall(&installed).await.is_ok(),
+                    None => false,
+                };
+                // Without a usable RECORD remove at least the metadata so the
+                // package is installed again.
+                if !uninstalled && dist_info.exists() {
+                    fs::remove_dir_all(dist_info).into_diagnostic()?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn remove_file_if_exists(path: &Path) -> miette::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e).into_diagnostic(),
+        _ => Ok(()),
+    }
+}
+
+/// Verifies the files listed in the `paths_data` of a conda package, skipping
+/// the `clobbered` files.
+fn verify_conda_package(
+    root: &Path,
+    record: &PrefixRecord,
+    clobbered: &HashSet<PathBuf>,
+) -> miette::Result<Option<PackageDrift>> {
+    let mut files = Vec::new();
+    for entry in &record.paths_data.paths {
+        let path = root.join(&entry.relative_path);
+        if clobbered.contains(&path) {
+            continue;
+        }
+
+        // Files in which the prefix was replaced have a different size and
+        // hash than in the package, only their hash in the prefix can be
+        // checked and older installations didn't record it.
+        let (expected_size, expected_hash) = match entry.path_type {
+            PathType::HardLink if entry.prefix_placeholder.is_none() => {
+                (entry.size_in_bytes, entry.sha256_in_prefix.or(entry.sha256))
+            }
+            PathType::HardLink => (None, entry.sha256_in_prefix),
+            _ => (None, None),
+        };
+        if let Some(kind) = verify_file(&path, expected_size, expected_hash)? {
+            files.push(FileDrift {
+                path: entry.relative_path.clone(),
+                kind,
+            });
+        }
+    }
+
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    let package_record = &record.repodata_record.package_record;
+    Ok(Some(PackageDrift {
+        name: package_record.name.as_normalized().to_string(),
+        version: package_record.version.to_string(),
+        kind: PackageKind::Conda {
+            files: record
+                .paths_data
+                .paths
+                .iter()
+                .filter(|entry| entry.path_type != PathType::Directory)
+                .map(|entry| entry.relative_path.clone())
+                .collect(),
+            meta_file: root.join("conda-meta").join(format!(
+                "{}-{}-{}.json",
+                package_record.name.as_normalized(),
+                package_record.version,
+                package_record.build
+            )),
+        },
+        files,
+    }))
+}
+
+/// Verifies the files listed in the `RECORD` of a PyPI package that was
+/// installed by pixi, skipping the `clobbered` files. Packages installed by
+/// other tools are skipped.
+fn verify_dist_info(
+    site_packages: &Path,
+    dist_info: &Path,
+    clobbered: &HashSet<PathBuf>,
+) -> miette::Result<Option<PackageDrift>> {
+    let installer = fs::read_to_string(dist_info.join("INSTALLER")).unwrap_or_default();
+    if installer.trim() != consts::PIXI_UV_INSTALLER {
+        return Ok(None);
+    }
+
+    let mut files = Vec::new();
+    let records = match fs::File::open(dist_info.join("RECORD")) {
+        Ok(mut file) => read_record_file(&mut file)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            files.push(FileDrift {
+                path: PathBuf::from("RECORD"),
+                kind: FileDriftKind::Missing,
+            });
+            Vec::new()
+        }
+        Err(e) => return Err(e).into_diagnostic(),
+    };
+    for record in records {
+        let expected_hash = record
+            .hash
+            .as_deref()
+            .and_then(|hash| hash.strip_prefix("sha256="))
+            .map(str::to_string);
+        let path = site_packages.join(&record.path);
+        if clobbered.contains(&normalize_path(&path)) {
+            continue;
+        }
+        let kind = match verify_file(&path, record.size, None)? {
+            Some(kind) => Some(kind),
+            None => match expected_hash {
+                Some(expected) if record_hash(&path)? != expected => Some(FileDriftKind::Hash),
+                _ => None,
+            },
+        };
+        if let Some(kind) = kind {
+            files.push(FileDrift {
+                path: PathBuf::from(record.path),
+                kind,
+            });
+        }
+    }
+
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    // The name of the directory is `{name}-{version}.dist-info`.
+    let stem = dist_info
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (name, version) = stem.split_once('-').unwrap_or((&stem, ""));
+    Ok(Some(PackageDrift {
+        name: name.to_string(),
+        version: version.to_string(),
+        kind: PackageKind::Pypi {
+            dist_info: dist_info.to_path_buf(),
+        },
+        files,
+    }))
+}
+
+/// Checks that a file exists and, if given, has the expected size and hash.
+/// Symbolic links are only checked for existence.
+fn verify_file(
+    path: &Path,
+    expected_size: Option<u64>,
+    expected_hash: Option<Sha256Hash>,
+) -> miette::Result<Option<FileDriftKind>> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Some(FileDriftKind::Missing)),
+        Err(e) => return Err(e).into_diagnostic(),
+    };
+    if !metadata.is_file() {
+        return Ok(None);
+    }
+
+    if let Some(expected) = expected_size {
+        if metadata.len() != expected {
+            return Ok(Some(FileDriftKind::Size {
+                expected,
+                actual: metadata.len(),
+            }));
+        }
+    }
+
+    if let Some(expected) = expected_hash {
+        if compute_file_digest::<Sha256>(path).into_diagnostic()? != expected {
+            return Ok(Some(FileDriftKind::Hash));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Lexically resolves the `..` and `.` components of a path, `RECORD` files
+/// refer to scripts relative to the site-packages directory.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Computes the hash of a file in the format used by `RECORD` files.
+fn record_hash(path: &Path) -> miette::Result<String> {
+    let digest = compute_file_digest::<Sha256>(path).into_diagnostic()?;
+    Ok(URL_SAFE_NO_PAD.encode(digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_dist_info() {
+        let site_packages = tempfile::tempdir().unwrap();
+        let dist_info = site_packages.path().join("foo-1.0.0.dist-info");
+        fs::create_dir_all(site_packages.path().join("foo")).unwrap();
+        fs::create_dir_all(&dist_info).unwrap();
+
+        let files = [
+            ("foo/__init__.py", "print('foo')\n"),
+            ("foo/bar.py", "x = 1\n"),
+        ];
+        let mut record = String::new();
+        for (path, contents) in files {
+            let full_path = site_packages.path().join(path);
+            fs::write(&full_path, contents).unwrap();
+            record.push_str(&format!(
+                "{path},sha256={},{}\n",
+                record_hash(&full_path).unwrap(),
+                contents.len()
+            ));
+        }
+        record.push_str("foo-1.0.0.dist-info/RECORD,,\n");
+        fs::write(dist_info.join("RECORD"), record).unwrap();
+        fs::write(dist_info.join("INSTALLER"), consts::PIXI_UV_INSTALLER).unwrap();
+
+        assert!(
+            verify_dist_info(site_packages.path(), &dist_info, &HashSet::new())
+                .unwrap()
+                .is_none()
+        );
+
+        fs::remove_file(site_packages.path().join("foo/__init__.py")).unwrap();
+        fs::write(site_packages.path().join("foo/bar.py"), "x = 2\n").unwrap();
+        let drift = verify_dist_info(site_packages.path(), &dist_info, &HashSet::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(drift.name, "foo");
+        assert_eq!(drift.version, "1.0.0");
+        assert_eq!(
+            drift.files,
+            vec![
+                FileDrift {
+                    path: PathBuf::from("foo/__init__.py"),
+                    kind: FileDriftKind::Missing,
+                },
+                FileDrift {
+                    path: PathBuf::from("foo/bar.py"),
+                    kind: FileDriftKind::Hash,
+                },
+            ]
+        );
+
+        // Clobbered files are not reported.
+        let clobbered = HashSet::from([
+            site_packages.path().join("foo/__init__.py"),
+            site_packages.path().join("foo/bar.py"),
+        ]);
+        assert!(
+            verify_dist_info(site_packages.path(), &dist_info, &clobbered)
+                .unwrap()
+                .is_none()
+        );
+
+        // Packages installed by other tools are not verified.
+        fs::write(dist_info.join("INSTALLER"), "pip").unwrap();
+        assert!(
+            verify_dist_info(site_packages.path(), &dist_info, &HashSet::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path(Path::new(
+                "/env/lib/python3.12/site-packages/../../../bin/foo"
+            )),
+            PathBuf::from("/env/bin/foo")
+        );
+        assert_eq!(
+            normalize_path(Path::new("/env/./lib/foo.py")),
+            PathBuf::from("/env/lib/foo.py")
+        );
+    }
+}
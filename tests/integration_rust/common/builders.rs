@@ -35,7 +35,8 @@ use std::{
 use futures::FutureExt;
 use pixi::{
     cli::{
-        add, cli_config::DependencyConfig, init, install, project, remove, search, task, update,
+        add, cli_config::DependencyConfig, init, install, outdated, project, remove, search, task,
+        update,
     },
     task::TaskName,
     DependencyType,
@@ -416,6 +417,18 @@ impl InstallBuilder {
         self.args.lock_file_usage.frozen = true;
         self
     }
+    pub fn with_skip_pypi(mut self) -> Self {
+        self.args.skip_pypi = true;
+        self
+    }
+    pub fn with_only_pypi(mut self) -> Self {
+        self.args.only_pypi = true;
+        self
+    }
+    pub fn with_skip_editable(mut self) -> Self {
+        self.args.skip_editable = true;
+        self
+    }
 }
 
 impl IntoFuture for InstallBuilder {
@@ -520,3 +533,29 @@ impl IntoFuture for UpdateBuilder {
         update::execute(self.args).boxed_local()
     }
 }
+
+/// Contains the arguments to pass to [`outdated::execute()`]. Call `.await` to
+/// call the CLI execute method and await the result at the same time.
+pub struct OutdatedBuilder {
+    pub args: outdated::Args,
+}
+
+impl OutdatedBuilder {
+    pub fn all(mut self, all: bool) -> Self {
+        self.args.all = all;
+        self
+    }
+
+    pub fn json(mut self, json: bool) -> Self {
+        self.args.json = json;
+        self
+    }
+}
+
+impl IntoFuture for OutdatedBuilder {
+    type Output = miette::Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'static>>;
+    fn into_future(self) -> Self::IntoFuture {
+        outdated::execute(self.args).boxed_local()
+    }
+}
@@ -19,7 +19,7 @@ use pixi::{
         cli_config::{PrefixUpdateConfig, ProjectConfig},
         init::{self, GitAttributes},
         install::Args,
-        project, remove, run,
+        outdated, project, remove, run,
         task::{self, AddArgs, AliasArgs},
         update, LockFileUsageArgs,
     },
@@ -43,8 +43,9 @@ use thiserror::Error;
 
 use self::builders::{HasDependencyConfig, RemoveBuilder};
 use crate::common::builders::{
-    AddBuilder, InitBuilder, InstallBuilder, ProjectChannelAddBuilder, ProjectChannelRemoveBuilder,
-    ProjectEnvironmentAddBuilder, TaskAddBuilder, TaskAliasBuilder, UpdateBuilder,
+    AddBuilder, InitBuilder, InstallBuilder, OutdatedBuilder, ProjectChannelAddBuilder,
+    ProjectChannelRemoveBuilder, ProjectEnvironmentAddBuilder, TaskAddBuilder, TaskAliasBuilder,
+    UpdateBuilder,
 };
 
 /// To control the pixi process
@@ -459,6 +460,9 @@ impl PixiControl {
         let mut task_env = None;
         let mut result = RunOutput::default();
         for task_id in task_graph.topological_order() {
+            if task_graph[task_id].skip_reason.is_some() {
+                continue;
+            }
             let task = ExecutableTask::from_task_graph(&task_graph, task_id);
 
             // Construct the task environment if not already created.
@@ -502,6 +506,11 @@ impl PixiControl {
                 },
                 config: Default::default(),
                 all: false,
+                skip_pypi: false,
+                only_pypi: false,
+                skip_editable: false,
+                verify: false,
+                repair: false,
             },
         }
     }
@@ -523,6 +532,24 @@ impl PixiControl {
         }
     }
 
+    /// Returns a [`OutdatedBuilder`]. To execute the command and await the
+    /// result call `.await` on the return value.
+    pub fn outdated(&self) -> OutdatedBuilder {
+        OutdatedBuilder {
+            args: outdated::Args {
+                config: Default::default(),
+                project_config: ProjectConfig {
+                    manifest_path: Some(self.manifest_path()),
+                },
+                environments: None,
+                platform: None,
+                no_install: false,
+                all: false,
+                json: false,
+            },
+        }
+    }
+
     /// Load the current lock-file.
     ///
     /// If you want to lock-file to be up-to-date with the project call
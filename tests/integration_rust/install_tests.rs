@@ -558,6 +558,97 @@ async fn test_installer_name() {
     assert_eq!(installer, consts::PIXI_UV_INSTALLER);
 }
 
+/// Install the conda and PyPI packages in separate steps, like in separate
+/// layers of a container image.
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[cfg_attr(not(feature = "slow_integration_tests"), ignore)]
+async fn install_skip_pypi_then_only_pypi() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().await.unwrap();
+    pixi.add("python==3.11").with_install(false).await.unwrap();
+    pixi.add("click==8.0.0")
+        .set_type(pixi::DependencyType::PypiDependency)
+        .with_install(false)
+        .await
+        .unwrap();
+
+    let prefix = pixi.default_env_path().unwrap();
+    let dist_info = if cfg!(not(target_os = "windows")) {
+        prefix.join("lib/python3.11/site-packages/click-8.0.0.dist-info")
+    } else {
+        prefix.join("Lib/site-packages/click-8.0.0.dist-info")
+    };
+
+    // Installing only the PyPI packages requires the conda packages
+    assert!(pixi.install().with_frozen().with_only_pypi().await.is_err());
+
+    pixi.install().with_frozen().with_skip_pypi().await.unwrap();
+    assert!(prefix.join("conda-meta").exists());
+    assert!(!dist_info.exists());
+
+    pixi.install().with_frozen().with_only_pypi().await.unwrap();
+    assert!(dist_info.exists());
+
+    // The environment file marks the environment as complete
+    let environment_file = std::fs::read_to_string(
+        prefix
+            .join(consts::CONDA_META_DIR)
+            .join(consts::ENVIRONMENT_FILE_NAME),
+    )
+    .unwrap();
+    assert!(!environment_file.contains("skipped"));
+}
+
+/// Installing with `--skip-editable` on a prefix that already contains the
+/// editable packages keeps them installed.
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[cfg_attr(not(feature = "slow_integration_tests"), ignore)]
+async fn install_skip_editable_keeps_installed_editables() {
+    let manifest = format!(
+        r#"
+    [project]
+    name = "skip-editable"
+    channels = ["conda-forge"]
+    platforms = ["{platform}"]
+
+    [dependencies]
+    python = "3.12.*"
+
+    [pypi-dependencies]
+    my-pkg = {{ path = "./my-pkg", editable = true }}
+    "#,
+        platform = Platform::current(),
+    );
+    let pixi = PixiControl::from_manifest(&manifest).expect("cannot instantiate pixi project");
+
+    let my_pkg = pixi.project_path().join("my-pkg");
+    std::fs::create_dir_all(my_pkg.join("my_pkg")).unwrap();
+    std::fs::write(my_pkg.join("my_pkg/__init__.py"), "").unwrap();
+    std::fs::write(
+        my_pkg.join("setup.py"),
+        "from setuptools import setup\nsetup(name=\"my-pkg\", version=\"0.1.0\", packages=[\"my_pkg\"])\n",
+    )
+    .unwrap();
+
+    let prefix = pixi.default_env_path().unwrap();
+    let dist_info = if cfg!(not(target_os = "windows")) {
+        prefix.join("lib/python3.12/site-packages/my_pkg-0.1.0.dist-info")
+    } else {
+        prefix.join("Lib/site-packages/my_pkg-0.1.0.dist-info")
+    };
+
+    pixi.install().await.unwrap();
+    assert!(dist_info.exists());
+
+    // The editable package is neither reinstalled nor removed
+    pixi.install()
+        .with_frozen()
+        .with_skip_editable()
+        .await
+        .unwrap();
+    assert!(dist_info.exists());
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 #[cfg_attr(not(feature = "slow_integration_tests"), ignore)]
 /// Test full prefix install for an old lock file to see if it still works.
@@ -756,3 +847,61 @@ async fn test_ensure_gitignore_file_creation() {
         ".pixi/.gitignore file does not contain the expected content"
     );
 }
+
+/// The PyPI packages of a shared environment are installed in the store, also
+/// when the lock-file is solved in the same command.
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[cfg_attr(not(feature = "slow_integration_tests"), ignore)]
+async fn install_shared_environment_with_pypi_dependencies() {
+    let manifest = format!(
+        r#"
+    [project]
+    name = "shared-pypi"
+    channels = ["conda-forge"]
+    platforms = ["{platform}"]
+
+    [dependencies]
+    python = "3.12.*"
+
+    [pypi-dependencies]
+    boltons = "==24.0.0"
+    "#,
+        platform = Platform::current(),
+    );
+    let pixi = PixiControl::from_manifest(&manifest).expect("cannot instantiate pixi project");
+
+    // Keep the store in the temporary directory
+    let target_dir = pixi.project_path().join("target");
+    let config = Config {
+        detached_environments: Some(DetachedEnvironments::Path(target_dir.clone())),
+        shared_environments: Some(true),
+        ..Config::default()
+    };
+    let config_path = pixi.project().unwrap().pixi_dir().join(consts::CONFIG_FILE);
+    create_dir_all(config_path.parent().unwrap()).unwrap();
+    std::fs::write(&config_path, toml_edit::ser::to_string(&config).unwrap()).unwrap();
+
+    assert!(!pixi
+        .manifest_path()
+        .with_file_name(consts::PROJECT_LOCK_FILE)
+        .exists());
+    pixi.install().await.unwrap();
+
+    let env_dir = pixi.default_env_path().unwrap();
+    let stored_env = std::fs::read_link(&env_dir).expect("the environment links into the store");
+    assert!(stored_env.starts_with(&target_dir));
+
+    let site_packages = if cfg!(not(target_os = "windows")) {
+        "lib/python3.12/site-packages"
+    } else {
+        "Lib/site-packages"
+    };
+    assert!(stored_env
+        .join(site_packages)
+        .join("boltons-24.0.0.dist-info")
+        .is_dir());
+    assert!(env_dir
+        .join(site_packages)
+        .join("boltons-24.0.0.dist-info")
+        .is_dir());
+}
@@ -3,6 +3,7 @@ use std::{
     str::FromStr,
 };
 
+use pixi_consts::consts;
 use pypi_mapping::{self, PurlSource};
 use rattler_conda_types::{PackageName, Platform, RepoDataRecord};
 use rattler_lock::DEFAULT_ENVIRONMENT_NAME;
@@ -173,7 +174,7 @@ async fn test_purl_are_missing_for_non_conda_forge() {
     pixi.init().await.unwrap();
 
     let project = pixi.project().unwrap();
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
     let foo_bar_package = Package::build("foo-bar-car", "2").finish();
 
     let mut repo_data_record = RepoDataRecord {
@@ -216,7 +217,7 @@ async fn test_purl_are_generated_using_custom_mapping() {
     pixi.init().await.unwrap();
 
     let project = pixi.project().unwrap();
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
     let foo_bar_package = Package::build("foo-bar-car", "2").finish();
 
     let mut repo_data_record = RepoDataRecord {
@@ -260,7 +261,7 @@ async fn test_compressed_mapping_catch_not_pandoc_not_a_python_package() {
     pixi.init().await.unwrap();
 
     let project = pixi.project().unwrap();
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
     let foo_bar_package = Package::build("pandoc", "2").finish();
 
     let mut repo_data_record = RepoDataRecord {
@@ -300,7 +301,7 @@ async fn test_dont_record_not_present_package_as_purl() {
     pixi.init().await.unwrap();
 
     let project = pixi.project().unwrap();
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
     // We use one package that is present in our mapping: `boltons`
     // and another one that is missing from conda and our mapping:
     // `pixi-something-new-for-test` because `pixi-something-new-for-test` is
@@ -394,7 +395,7 @@ async fn test_we_record_not_present_package_as_purl_for_custom_mapping() {
 
     let project = pixi.project().unwrap();
 
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
 
     // We use one package that is present in our mapping: `boltons`
     // and another one that is missing from conda and our mapping:
@@ -429,9 +430,15 @@ async fn test_we_record_not_present_package_as_purl_for_custom_mapping() {
         .custom()
         .unwrap();
 
-    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(client, &mapping_map, &mut packages, None)
-        .await
-        .unwrap();
+    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(
+        client,
+        client,
+        &mapping_map,
+        &mut packages,
+        None,
+    )
+    .await
+    .unwrap();
 
     let boltons_package = packages.pop().unwrap();
 
@@ -480,7 +487,7 @@ async fn test_custom_mapping_channel_with_suffix() {
 
     let project = pixi.project().unwrap();
 
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
 
     let foo_bar_package = Package::build("pixi-something-new", "2").finish();
 
@@ -497,9 +504,15 @@ async fn test_custom_mapping_channel_with_suffix() {
 
     let mapping_map = mapping_source.custom().unwrap();
 
-    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(client, &mapping_map, &mut packages, None)
-        .await
-        .unwrap();
+    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(
+        client,
+        client,
+        &mapping_map,
+        &mut packages,
+        None,
+    )
+    .await
+    .unwrap();
 
     let package = packages.pop().unwrap();
 
@@ -531,7 +544,7 @@ async fn test_repo_data_record_channel_with_suffix() {
 
     let project = pixi.project().unwrap();
 
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
 
     let foo_bar_package = Package::build("pixi-something-new", "2").finish();
 
@@ -548,9 +561,15 @@ async fn test_repo_data_record_channel_with_suffix() {
 
     let mapping_map = mapping_source.custom().unwrap();
 
-    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(client, &mapping_map, &mut packages, None)
-        .await
-        .unwrap();
+    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(
+        client,
+        client,
+        &mapping_map,
+        &mut packages,
+        None,
+    )
+    .await
+    .unwrap();
 
     let package = packages.pop().unwrap();
     assert_eq!(
@@ -582,7 +601,7 @@ async fn test_path_channel() {
 
     let project = pixi.project().unwrap();
 
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
 
     let foo_bar_package = Package::build("pixi-something-new", "2").finish();
 
@@ -599,9 +618,15 @@ async fn test_path_channel() {
 
     let mapping_map = mapping_source.custom().unwrap();
 
-    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(client, &mapping_map, &mut packages, None)
-        .await
-        .unwrap();
+    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(
+        client,
+        client,
+        &mapping_map,
+        &mut packages,
+        None,
+    )
+    .await
+    .unwrap();
 
     let package = packages.pop().unwrap();
 
@@ -656,7 +681,7 @@ async fn test_file_url_as_mapping_location() {
 
     let project = pixi.project().unwrap();
 
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
 
     let foo_bar_package = Package::build("pixi-something-new", "2").finish();
 
@@ -672,9 +697,15 @@ async fn test_file_url_as_mapping_location() {
     let mapping_source = project.pypi_name_mapping_source().unwrap();
 
     let mapping_map = mapping_source.custom().unwrap();
-    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(client, &mapping_map, &mut packages, None)
-        .await
-        .unwrap();
+    pypi_mapping::custom_pypi_mapping::amend_pypi_purls(
+        client,
+        client,
+        &mapping_map,
+        &mut packages,
+        None,
+    )
+    .await
+    .unwrap();
 
     let package = packages.pop().unwrap();
 
@@ -707,7 +738,7 @@ async fn test_disabled_mapping() {
 
     let project = pixi.project().unwrap();
 
-    let client = project.authenticated_client();
+    let client = project.authenticated_client().unwrap();
 
     let blocking_middleware = OfflineMiddleware;
 
@@ -727,6 +758,7 @@ async fn test_disabled_mapping() {
     let mut packages = vec![boltons_repo_data_record];
 
     pypi_mapping::amend_pypi_purls(
+        blocked_client.clone(),
         blocked_client,
         project.pypi_name_mapping_source().unwrap(),
         &mut packages,
@@ -750,3 +782,49 @@ async fn test_disabled_mapping() {
     assert_eq!(boltons_first_purl.name(), "boltons");
     assert!(boltons_first_purl.qualifiers().is_empty());
 }
+
+#[tokio::test]
+async fn test_offline_mapping_sends_no_requests() {
+    let pixi = PixiControl::from_manifest(
+        r#"
+    [project]
+    name = "test-offline-mapping"
+    channels = ["conda-forge"]
+    platforms = ["linux-64"]
+    "#,
+    )
+    .unwrap();
+
+    // Use the prefix.dev mapping, which needs the network, while offline
+    let config_path = pixi.project().unwrap().pixi_dir().join(consts::CONFIG_FILE);
+    fs_err::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs_err::write(
+        &config_path,
+        "offline = true\n\n[pypi-config]\nconda-pypi-map = \"prefix\"\n",
+    )
+    .unwrap();
+    let project = pixi.project().unwrap();
+
+    let boltons_package = Package::build("boltons", "2").finish();
+    let mut packages = vec![RepoDataRecord {
+        package_record: boltons_package.package_record,
+        file_name: "boltons".to_owned(),
+        url: Url::parse("https://conda.anaconda.org/conda-forge/noarch/boltons-2-0.conda").unwrap(),
+        channel: "https://conda.anaconda.org/conda-forge/".to_owned(),
+    }];
+
+    // The mapping requests are blocked before they are sent
+    let error = pypi_mapping::amend_pypi_purls(
+        project.client().unwrap().clone(),
+        project.authenticated_client().unwrap().clone(),
+        project.pypi_name_mapping_source().unwrap(),
+        &mut packages,
+        None,
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        format!("{error:?}").contains("pixi is offline"),
+        "{error:?}"
+    );
+}
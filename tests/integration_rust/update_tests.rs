@@ -151,6 +151,42 @@ async fn test_update_single_package() {
     );
 }
 
+#[tokio::test]
+async fn test_outdated_leaves_project_untouched() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    let channel_dir = TempDir::new().unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+    pixi.add("foo").await.unwrap();
+
+    package_database.add_package(Package::build("foo", "2").finish());
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+
+    pixi.outdated().json(true).await.unwrap();
+    pixi.outdated().all(true).await.unwrap();
+
+    // Neither the lock-file nor the environment is changed.
+    let lock = pixi.lock_file().await.unwrap();
+    assert!(lock.contains_match_spec(
+        consts::DEFAULT_ENVIRONMENT_NAME,
+        Platform::current(),
+        "foo ==1"
+    ));
+    assert!(!pixi.default_env_path().unwrap().exists());
+}
+
 // #[tokio::test]
 // async fn test_update_single_environment() {
 //     let mut package_database = PackageDatabase::default();